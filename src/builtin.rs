@@ -0,0 +1,81 @@
+use crate::{node::Operator, value::Value};
+
+/// A function that's built into the language, eg `max(a, b)`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Builtin {
+  /// `abs(x)`, the absolute value of a number.
+  Abs,
+  /// `min(a, b)`, the lesser of two values that can be compared with `<`, or `a` if neither is.
+  Min,
+  /// `max(a, b)`, the greater of two values that can be compared with `<`, or `a` if neither is.
+  Max,
+  /// `pow(a, b)`, `a` raised to the power of `b`, like `a ** b`.
+  Pow,
+}
+
+impl Builtin {
+  /// Every built-in function, in the order that they're listed in diagnostics.
+  pub const ALL: [Builtin; 4] = [Builtin::Abs, Builtin::Min, Builtin::Max, Builtin::Pow];
+
+  /// Returns the built-in function with the name, if there is one.
+  pub fn from_name(name: &str) -> Option<Self> {
+    Builtin::ALL
+      .into_iter()
+      .find(|builtin| builtin.name() == name)
+  }
+
+  /// Returns the name that the function is called by.
+  pub fn name(self) -> &'static str {
+    match self {
+      Builtin::Abs => "abs",
+      Builtin::Min => "min",
+      Builtin::Max => "max",
+      Builtin::Pow => "pow",
+    }
+  }
+
+  /// Returns the number of arguments that the function takes.
+  pub fn arity(self) -> usize {
+    match self {
+      Builtin::Abs => 1,
+      Builtin::Min | Builtin::Max | Builtin::Pow => 2,
+    }
+  }
+
+  /// Returns whether the function applies to the arguments, which is never the case if there
+  /// isn't one for each of its parameters.
+  ///
+  /// `abs` and `pow` only apply to numbers, and `min` and `max` apply to whatever `<` does.
+  pub fn applies_to(self, args: &[Value]) -> bool {
+    match (self, args) {
+      (Builtin::Abs, [x]) => x.is_number(),
+      (Builtin::Min | Builtin::Max, [a, b]) => Operator::Less.applies_to(a, b),
+      (Builtin::Pow, [a, b]) => Operator::Power.applies_to(a, b),
+      _ => false,
+    }
+  }
+
+  /// Returns whether the function has no value for the arguments, which is when `pow` raises to
+  /// a negative power, like `**`.
+  pub fn is_undefined_for(self, args: &[Value]) -> bool {
+    match (self, args) {
+      (Builtin::Pow, [base, exponent]) => Operator::Power.is_undefined_for(base, exponent),
+      _ => false,
+    }
+  }
+
+  /// Returns the rule that the arguments of the function break when it doesn't apply to them.
+  pub fn rule(self) -> &'static str {
+    match self {
+      Builtin::Abs => "it takes a number",
+      Builtin::Min | Builtin::Max => "its arguments have to be comparable with `<`",
+      Builtin::Pow => "its arguments have to be numbers",
+    }
+  }
+}
+
+impl std::fmt::Display for Builtin {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
@@ -0,0 +1,139 @@
+use crate::{
+  interpreter::checked_unary,
+  node::{LiteralNode, Node, Operator},
+};
+
+impl Node {
+  /// Returns the canonical form of an expression, so that equivalent formulations, like
+  /// `b * (a + 1)` and `(1 + a) * b`, compare equal when printed.
+  ///
+  /// The operands of chained `+` and `*` operations are sorted, with literals first, and unary
+  /// chains are normalized, eg `--x` becomes `x` and `-5` becomes the literal `-5`. Since `+` also
+  /// concatenates strings and arrays, the operands of a `+` chain are only sorted if one of them
+  /// is known to be a number, which the rest then have to be too.
+  pub fn canonicalize(&self) -> Node {
+    canonicalize(self.strip()).parenthesize()
+  }
+}
+
+fn canonicalize(node: Node) -> Node {
+  match node {
+    Node::UnaryOperator(op, rhs) => match (op, canonicalize(*rhs)) {
+      (Operator::Plus, rhs) => rhs,
+      // Negating `isize::MIN` overflows, so it's left as is
+      (_, Node::Literal(lit)) => match checked_unary(op, &lit.value) {
+        Some(value) => Node::Literal(LiteralNode { value }),
+        None => Node::UnaryOperator(op, Box::new(Node::Literal(lit))),
+      },
+      (Operator::Minus, Node::UnaryOperator(Operator::Minus, inner)) => *inner,
+      (_, rhs) => Node::UnaryOperator(op, Box::new(rhs)),
+    },
+    Node::Term(_, op @ (Operator::Plus | Operator::Multiply), _) => {
+      let mut operands = Vec::new();
+      flatten(node, op, &mut operands);
+
+      let operands = operands.into_iter().map(canonicalize).collect::<Vec<_>>();
+      // `*` only applies to numbers, but `+` can concatenate, which isn't commutative
+      let commutes = matches!(op, Operator::Multiply) || operands.iter().any(is_number);
+      let mut operands = operands
+        .into_iter()
+        .map(|operand| {
+          let key = (!matches!(operand, Node::Literal(_)), operand.to_string());
+
+          (key, operand)
+        })
+        .collect::<Vec<_>>();
+
+      if commutes {
+        operands.sort_by(|(a, _), (b, _)| a.cmp(b));
+      }
+
+      operands
+        .into_iter()
+        .map(|(_, operand)| operand)
+        .reduce(|lhs, rhs| Node::Term(Box::new(lhs), op, Box::new(rhs)))
+        .unwrap()
+    }
+    Node::Term(lhs, op, rhs) => Node::Term(
+      Box::new(canonicalize(*lhs)),
+      op,
+      Box::new(canonicalize(*rhs)),
+    ),
+    _ => node,
+  }
+}
+
+// Returns whether the stripped expression can only be a number, if it has a value at all.
+fn is_number(node: &Node) -> bool {
+  match node {
+    Node::Literal(lit) => lit.value.is_number(),
+    // Only `+` applies to anything but numbers, other than comparisons and logic, which result in
+    // integers, and `??`, which results in either operand
+    Node::UnaryOperator(..) => true,
+    Node::Term(lhs, Operator::Plus, rhs) => is_number(lhs) || is_number(rhs),
+    Node::Term(_, Operator::Coalesce, _) => false,
+    Node::Term(..) => true,
+    _ => false,
+  }
+}
+
+// Collects the operands of a chain of the same commutative operator, eg `a + (b + c)`.
+fn flatten(node: Node, op: Operator, operands: &mut Vec<Node>) {
+  match node {
+    Node::Term(lhs, term_op, rhs) if term_op == op => {
+      flatten(*lhs, op, operands);
+      flatten(*rhs, op, operands);
+    }
+    _ => operands.push(node),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{node::Node, parser::Parser};
+
+  fn canonical(src: &str) -> String {
+    match Parser::new(src).parse().unwrap() {
+      Node::Program(assignments) => match &assignments[0] {
+        Node::Assignment(_, expr) => expr.canonicalize().to_string(),
+        _ => unreachable!(),
+      },
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn commutative() {
+    assert_eq!(canonical("x = b * (a + 1);"), canonical("x = (1 + a) * b;"));
+    assert_eq!(canonical("x = c * (b * a);"), "a * b * c");
+    assert_eq!(canonical("x = c + (b + 2 * a);"), "2 * a + b + c");
+  }
+
+  #[test]
+  fn concatenation_is_ordered() {
+    assert_eq!(canonical("x = c + (b + a);"), "c + b + a");
+    assert_eq!(canonical("x = \"b\" + \"a\";"), "\"b\" + \"a\"");
+    assert_eq!(canonical("x = [2] + [1];"), "[2] + [1]");
+    assert_eq!(canonical("x = \"${b}\" + a;"), "\"${b}\" + a");
+  }
+
+  #[test]
+  fn unary_chains() {
+    assert_eq!(canonical("x = --+y - -5;"), "y - -5");
+  }
+
+  #[test]
+  fn comparisons_and_logic_keep_parentheses() {
+    assert_eq!(canonical("x = (b < a) + (a == 1);"), "(a == 1) + (b < a)");
+    assert_eq!(canonical("x = (a < b) < (c * 1);"), "(a < b) < 1 * c");
+    assert_eq!(
+      canonical("x = (a || b) && !(c + 0);"),
+      "(a || b) && !(0 + c)"
+    );
+  }
+
+  #[test]
+  fn subtraction_is_ordered() {
+    assert_ne!(canonical("x = a - b;"), canonical("x = b - a;"));
+  }
+}
@@ -0,0 +1,69 @@
+/// The capabilities that can be granted with `--allow=<name>,...`.
+///
+/// A program can only use the statements that need a capability if it's granted, so that
+/// untrusted code can be run without access to the input or the filesystem.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Capability {
+  /// Reading from the input with `read` statements.
+  Read,
+  /// Running other files with `import` statements.
+  Import,
+  /// Splicing other files into the source with `#include` lines.
+  Include,
+}
+
+/// The set of granted [Capability]s, which is empty by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+  read: bool,
+  import: bool,
+  include: bool,
+}
+
+impl Capability {
+  /// Returns the capability with the given command line name.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "read" => Some(Capability::Read),
+      "import" => Some(Capability::Import),
+      "include" => Some(Capability::Include),
+      _ => None,
+    }
+  }
+}
+
+impl Capabilities {
+  /// Returns whether the capability is granted.
+  pub fn is_granted(&self, capability: Capability) -> bool {
+    match capability {
+      Capability::Read => self.read,
+      Capability::Import => self.import,
+      Capability::Include => self.include,
+    }
+  }
+
+  /// Grants the capability.
+  pub fn grant(&mut self, capability: Capability) {
+    match capability {
+      Capability::Read => self.read = true,
+      Capability::Import => self.import = true,
+      Capability::Include => self.include = true,
+    }
+  }
+
+  /// Returns the set with the capability granted.
+  pub fn with(mut self, capability: Capability) -> Self {
+    self.grant(capability);
+    self
+  }
+}
+
+impl std::fmt::Display for Capability {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Capability::Read => write!(f, "read"),
+      Capability::Import => write!(f, "import"),
+      Capability::Include => write!(f, "include"),
+    }
+  }
+}
@@ -0,0 +1,342 @@
+use crate::{
+  interpreter::{checked_binary, checked_unary},
+  node::{LiteralNode, Node, Operator},
+  value::Value,
+};
+
+/// Symbolically differentiates the expression with respect to the variable `wrt`.
+///
+/// Every other variable is treated as a constant. The result is simplified and
+/// parenthesized, so it can be printed back as source.
+///
+/// Returns `None` if a power's exponent depends on `wrt`, since its derivative needs logarithms,
+/// and likewise if a shift's amount, the value of a `match`, or an index does. It's also `None` if
+/// the expression calls a function or makes one, since functions can't be differentiated.
+pub fn derivative(expr: &Node, wrt: &str) -> Option<Node> {
+  Some(simplify(differentiate(&expr.strip(), wrt)?).parenthesize())
+}
+
+// Applies the differentiation rules to a stripped node.
+fn differentiate(node: &Node, wrt: &str) -> Option<Node> {
+  let derivative = match node {
+    Node::Identifier(ident) if ident.literal == wrt => literal(1),
+    Node::Identifier(_) | Node::Literal(_) => literal(0),
+    // Strings aren't numbers, so like string literals they're treated as constants
+    Node::Interpolation(_) => literal(0),
+    // Comparisons and logic are piecewise constant, like the quotient of a modulo
+    Node::UnaryOperator(Operator::Not, _) => literal(0),
+    Node::UnaryOperator(op, rhs) => Node::UnaryOperator(*op, Box::new(differentiate(rhs, wrt)?)),
+    Node::Term(lhs, Operator::Multiply, rhs) => {
+      // The product rule, (fg)' = f'g + fg'
+      term(
+        term(
+          differentiate(lhs, wrt)?,
+          Operator::Multiply,
+          (**rhs).clone(),
+        ),
+        Operator::Plus,
+        term(
+          (**lhs).clone(),
+          Operator::Multiply,
+          differentiate(rhs, wrt)?,
+        ),
+      )
+    }
+    Node::Term(lhs, Operator::Divide, rhs) => {
+      // The quotient rule, (f/g)' = (f'g - fg') / g^2
+      term(
+        term(
+          term(
+            differentiate(lhs, wrt)?,
+            Operator::Multiply,
+            (**rhs).clone(),
+          ),
+          Operator::Minus,
+          term(
+            (**lhs).clone(),
+            Operator::Multiply,
+            differentiate(rhs, wrt)?,
+          ),
+        ),
+        Operator::Divide,
+        term((**rhs).clone(), Operator::Multiply, (**rhs).clone()),
+      )
+    }
+    Node::Term(lhs, Operator::Modulo, rhs) => {
+      // f % g = f - g * (f / g), where the truncated quotient is piecewise constant, so
+      // (f % g)' = f' - g' * (f / g)
+      term(
+        differentiate(lhs, wrt)?,
+        Operator::Minus,
+        term(
+          differentiate(rhs, wrt)?,
+          Operator::Multiply,
+          term((**lhs).clone(), Operator::Divide, (**rhs).clone()),
+        ),
+      )
+    }
+    Node::Term(_, Operator::Power | Operator::ShiftLeft | Operator::ShiftRight, rhs)
+      if depends_on(rhs, wrt) =>
+    {
+      return None
+    }
+    // A shift scales by a constant power of two, so it's linear
+    Node::Term(lhs, op @ (Operator::ShiftLeft | Operator::ShiftRight), rhs) => {
+      term(differentiate(lhs, wrt)?, *op, (**rhs).clone())
+    }
+    Node::Term(lhs, Operator::Power, rhs) => {
+      // The power rule, (f^n)' = n * f^(n - 1) * f'
+      let exponent = term((**rhs).clone(), Operator::Minus, literal(1));
+
+      term(
+        term(
+          (**rhs).clone(),
+          Operator::Multiply,
+          term((**lhs).clone(), Operator::Power, exponent),
+        ),
+        Operator::Multiply,
+        differentiate(lhs, wrt)?,
+      )
+    }
+    // Which operand is picked can't be known without evaluating the left one
+    Node::Term(_, Operator::Coalesce, _) => return None,
+    Node::Term(_, op, _) if op.is_comparison() || op.is_logical() => literal(0),
+    Node::Term(lhs, op, rhs) => term(differentiate(lhs, wrt)?, *op, differentiate(rhs, wrt)?),
+    // The rules of built-in and host functions aren't known, and a lambda is a value that can only
+    // be called, not a number
+    Node::Call(..) | Node::Lambda(..) => return None,
+    // Which arm is picked can only change with `wrt` by jumping between arms
+    Node::Match(_, value, _) if depends_on(value, wrt) => return None,
+    Node::Match(keyword, value, arms) => Node::Match(
+      keyword.clone(),
+      value.clone(),
+      arms
+        .iter()
+        .map(|(pattern, arm)| Some((pattern.clone(), differentiate(arm, wrt)?)))
+        .collect::<Option<_>>()?,
+    ),
+    // Arrays, tuples, and structs are differentiated element by element, so an element of one is
+    // the derivative's element at the same index
+    Node::Array(elements) => Node::Array(
+      elements
+        .iter()
+        .map(|element| differentiate(element, wrt))
+        .collect::<Option<_>>()?,
+    ),
+    Node::Tuple(elements) => Node::Tuple(
+      elements
+        .iter()
+        .map(|element| differentiate(element, wrt))
+        .collect::<Option<_>>()?,
+    ),
+    Node::Struct(name, fields) => Node::Struct(
+      name.clone(),
+      fields
+        .iter()
+        .map(|(field, value)| Some((field.clone(), differentiate(value, wrt)?)))
+        .collect::<Option<_>>()?,
+    ),
+    Node::Field(dot, tuple, index) => match differentiate(tuple, wrt)? {
+      Node::Literal(_) => literal(0),
+      tuple => Node::Field(dot.clone(), Box::new(tuple), *index),
+    },
+    Node::NamedField(dot, value, field) => match differentiate(value, wrt)? {
+      Node::Literal(_) => literal(0),
+      value => Node::NamedField(dot.clone(), Box::new(value), field.clone()),
+    },
+    Node::Index(_, _, index) if depends_on(index, wrt) => return None,
+    Node::Index(bracket, array, index) => match differentiate(array, wrt)? {
+      // An array that doesn't depend on `wrt`, like another variable, has constant elements
+      Node::Literal(_) => literal(0),
+      array => Node::Index(bracket.clone(), Box::new(array), index.clone()),
+    },
+    _ => unreachable!("only stripped expressions can be differentiated"),
+  };
+
+  Some(derivative)
+}
+
+// Returns whether the stripped node refers to the variable.
+fn depends_on(node: &Node, wrt: &str) -> bool {
+  match node {
+    Node::Identifier(ident) => ident.literal == wrt,
+    Node::UnaryOperator(_, rhs) => depends_on(rhs, wrt),
+    Node::Term(lhs, _, rhs) => depends_on(lhs, wrt) || depends_on(rhs, wrt),
+    Node::Match(_, value, arms) => {
+      depends_on(value, wrt) || arms.iter().any(|(_, arm)| depends_on(arm, wrt))
+    }
+    Node::Array(elements) | Node::Tuple(elements) => {
+      elements.iter().any(|element| depends_on(element, wrt))
+    }
+    Node::Struct(_, fields) => fields.iter().any(|(_, value)| depends_on(value, wrt)),
+    Node::Field(_, tuple, _) | Node::NamedField(_, tuple, _) => depends_on(tuple, wrt),
+    Node::Index(_, array, index) => depends_on(array, wrt) || depends_on(index, wrt),
+    Node::Call(_, args, _) => args.iter().any(|arg| depends_on(arg, wrt)),
+    _ => false,
+  }
+}
+
+// Folds constants and removes identity operations, bottom up.
+fn simplify(node: Node) -> Node {
+  match node {
+    Node::UnaryOperator(op, rhs) => match (op, simplify(*rhs)) {
+      (Operator::Plus, rhs) => rhs,
+      // Constants are only folded if they don't overflow
+      (_, Node::Literal(lit)) => match checked_unary(op, &lit.value) {
+        Some(value) => literal(value),
+        None => Node::UnaryOperator(op, Box::new(Node::Literal(lit))),
+      },
+      (Operator::Minus, Node::UnaryOperator(Operator::Minus, inner)) => *inner,
+      (_, rhs) => Node::UnaryOperator(op, Box::new(rhs)),
+    },
+    Node::Term(lhs, op, rhs) => match (simplify(*lhs), op, simplify(*rhs)) {
+      // Integer division truncates, so an inexact quotient is kept as a fraction in lowest terms
+      (Node::Literal(l), Operator::Divide, Node::Literal(r)) if is_inexact(&l.value, &r.value) => {
+        let (Value::Int(l), Value::Int(r)) = (l.value, r.value) else {
+          unreachable!("only integer division truncates");
+        };
+        let gcd = gcd(l, r);
+
+        // Dividing `isize::MIN` by `-1` overflows, so it's left as is
+        let (l, r) = l.checked_div(gcd).zip(r.checked_div(gcd)).unwrap_or((l, r));
+
+        term(literal(l), op, literal(r))
+      }
+      (Node::Literal(l), _, Node::Literal(r)) => match checked_binary(op, &l.value, &r.value) {
+        Some(value) => literal(value),
+        None => term(Node::Literal(l), op, Node::Literal(r)),
+      },
+      (lhs, Operator::Plus | Operator::Minus, rhs) if is_literal(&rhs, 0) => lhs,
+      (lhs, Operator::Plus, rhs) if is_literal(&lhs, 0) => rhs,
+      (lhs, Operator::Minus, rhs) if is_literal(&lhs, 0) => {
+        simplify(Node::UnaryOperator(Operator::Minus, Box::new(rhs)))
+      }
+      (lhs, Operator::Multiply, rhs) if is_literal(&lhs, 0) || is_literal(&rhs, 0) => literal(0),
+      (lhs, Operator::Multiply, rhs) if is_literal(&lhs, 1) => rhs,
+      (lhs, Operator::Multiply | Operator::Divide, rhs) if is_literal(&rhs, 1) => lhs,
+      (lhs, Operator::Divide, _) if is_literal(&lhs, 0) => literal(0),
+      (_, Operator::Power, rhs) if is_literal(&rhs, 0) => literal(1),
+      (lhs, Operator::Power, rhs) if is_literal(&rhs, 1) => lhs,
+      (lhs, op, rhs) => term(lhs, op, rhs),
+    },
+    Node::Match(keyword, value, arms) => Node::Match(
+      keyword,
+      value,
+      arms
+        .into_iter()
+        .map(|(pattern, arm)| (pattern, simplify(arm)))
+        .collect(),
+    ),
+    Node::Array(elements) => Node::Array(elements.into_iter().map(simplify).collect()),
+    Node::Tuple(elements) => Node::Tuple(elements.into_iter().map(simplify).collect()),
+    Node::Struct(name, fields) => Node::Struct(
+      name,
+      fields
+        .into_iter()
+        .map(|(field, value)| (field, simplify(value)))
+        .collect(),
+    ),
+    Node::Field(dot, tuple, index) => Node::Field(dot, Box::new(simplify(*tuple)), index),
+    Node::NamedField(dot, value, field) => Node::NamedField(dot, Box::new(simplify(*value)), field),
+    Node::Index(bracket, array, index) => Node::Index(bracket, Box::new(simplify(*array)), index),
+    _ => node,
+  }
+}
+
+// Returns whether dividing the values would truncate. Dividing by zero isn't, since it has no
+// value to fold.
+fn is_inexact(lhs: &Value, rhs: &Value) -> bool {
+  match (lhs, rhs) {
+    (Value::Int(lhs), Value::Int(rhs)) => lhs.checked_rem(*rhs).is_some_and(|rem| rem != 0),
+    _ => false,
+  }
+}
+
+// Returns the greatest common divisor of the integers, with the sign of the divisor, so that a
+// fraction divided by it keeps its sign in the numerator when the divisor is negative.
+fn gcd(lhs: isize, rhs: isize) -> isize {
+  let (mut a, mut b) = (lhs, rhs);
+
+  while b != 0 {
+    (a, b) = (b, a.wrapping_rem(b));
+  }
+
+  if (a < 0) == (rhs < 0) {
+    a
+  } else {
+    -a
+  }
+}
+
+fn is_literal(node: &Node, value: isize) -> bool {
+  matches!(node, Node::Literal(lit) if lit.value == Value::Int(value))
+}
+
+fn term(lhs: Node, op: Operator, rhs: Node) -> Node {
+  Node::Term(Box::new(lhs), op, Box::new(rhs))
+}
+
+fn literal(value: impl Into<Value>) -> Node {
+  Node::Literal(LiteralNode {
+    value: value.into(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{parser::Parser, steps::substitute_variable};
+
+  fn derive(src: &str, wrt: &str) -> String {
+    let program = Parser::new(src).parse().unwrap();
+    let expr = substitute_variable(&program, "x", &[wrt]).unwrap();
+
+    derivative(&expr, wrt).unwrap().to_string()
+  }
+
+  #[test]
+  fn polynomial() {
+    assert_eq!(
+      derive("x = 3 * t * t + 2 * t + 7;", "t"),
+      "3 * t + 3 * t + 2"
+    );
+  }
+
+  #[test]
+  fn constants() {
+    assert_eq!(derive("x = a * t - (t + a);", "t"), "a - 1");
+  }
+
+  #[test]
+  fn powers() {
+    assert_eq!(derive("x = (t + 1) ** 3;", "t"), "3 * (t + 1) ** 2");
+
+    let program = Parser::new("x = 2 ** t;").parse().unwrap();
+    let expr = substitute_variable(&program, "x", &["t"]).unwrap();
+
+    assert!(derivative(&expr, "t").is_none());
+  }
+
+  #[test]
+  fn functions() {
+    for src in ["x = abs(t);", "x = max(t, 1) * 2;", "f = |a| a * t; x = f;"] {
+      let program = Parser::new(src).parse().unwrap();
+      let expr = substitute_variable(&program, "x", &["t"]).unwrap();
+
+      assert!(derivative(&expr, "t").is_none(), "{}", src);
+    }
+  }
+
+  #[test]
+  fn division() {
+    assert_eq!(derive("x = t / 2;", "t"), "1 / 2");
+    assert_eq!(derive("x = 6 * t / 3;", "t"), "2");
+    assert_eq!(derive("x = t / -4;", "t"), "-1 / 4");
+    assert_eq!(derive("x = t / 2.0;", "t"), "0.5");
+  }
+
+  #[test]
+  fn through_variables() {
+    assert_eq!(derive("y = t * t; x = -(y + t);", "t"), "-(t + t + 1)");
+  }
+}
@@ -1,26 +1,65 @@
+/// How severe a [DiagnosticError] is.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+  /// The program can't be run.
+  Error,
+  /// The program can still run, but likely doesn't do what was intended.
+  Warning,
+}
+
+impl std::fmt::Display for Severity {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Severity::Error => write!(f, "error"),
+      Severity::Warning => write!(f, "warning"),
+    }
+  }
+}
+
+/// A diagnostic reported at a position in the source, either an error or a warning.
 #[derive(Clone, Debug)]
 pub struct DiagnosticError {
   msg: String,
   line: usize,
   column: usize,
+  severity: Severity,
 }
 
 impl DiagnosticError {
+  /// Creates a diagnostic with [Severity::Error].
   pub const fn new(msg: String, line: usize, col: usize) -> Self {
     Self {
       msg,
       line,
       column: col,
+      severity: Severity::Error,
     }
   }
 
+  /// Creates a diagnostic with [Severity::Warning].
+  pub const fn warning(msg: String, line: usize, col: usize) -> Self {
+    Self {
+      msg,
+      line,
+      column: col,
+      severity: Severity::Warning,
+    }
+  }
+
+  /// Returns the line of the diagnostic, starting at 1.
   pub const fn line(&self) -> usize {
     self.line
   }
 
+  /// Returns the column of the diagnostic, starting at 1.
   pub const fn column(&self) -> usize {
     self.column
   }
+
+  /// Returns how severe the diagnostic is.
+  pub const fn severity(&self) -> Severity {
+    self.severity
+  }
 }
 
 impl std::fmt::Display for DiagnosticError {
@@ -0,0 +1,152 @@
+use crate::minimize::NO_BUG_REPORT_VAR;
+use std::{
+  backtrace::Backtrace,
+  cell::RefCell,
+  env,
+  fmt::Write as _,
+  fs::OpenOptions,
+  io::{self, Write as _},
+  panic,
+  path::{Path, PathBuf},
+  process,
+  sync::Mutex,
+  time::{SystemTime, UNIX_EPOCH},
+};
+use toy_language::{run::Phase, token::Token};
+
+// What's known about the current run, for the bug report.
+struct Context {
+  phase: Phase,
+  file_name: Option<String>,
+  src: Option<String>,
+}
+
+static CONTEXT: Mutex<Context> = Mutex::new(Context {
+  phase: Phase::Startup,
+  file_name: None,
+  src: None,
+});
+
+thread_local! {
+  // The lexed tokens of the source, which are only formatted if there's a panic, since that's slow
+  // for a large source. They can't be shared between threads, but the hook runs on the thread
+  // that panicked, which is the one that lexed them.
+  static TOKENS: RefCell<Option<Vec<Token>>> = const { RefCell::new(None) };
+}
+
+/// Installs a panic hook that reports panics as internal compiler errors.
+///
+/// Alongside the message, a bug report containing the input, the lexed tokens, and a backtrace
+/// is written to the temp directory.
+pub fn install_hook() {
+  panic::set_hook(Box::new(|info| {
+    let backtrace = Backtrace::force_capture();
+    // Don't deadlock if the panic happened while the context was being updated
+    let context = CONTEXT.try_lock().ok();
+    let phase = context.as_ref().map_or(Phase::Startup, |c| c.phase);
+    let file_name = context
+      .as_ref()
+      .and_then(|c| c.file_name.clone())
+      .unwrap_or_else(|| "<unknown>".to_string());
+
+    eprintln!(
+      "internal compiler error: the interpreter panicked while {} `{}`.\n\t{}",
+      phase, file_name, info
+    );
+
+    let mut report = String::new();
+    let _ = writeln!(
+      report,
+      "file: {}\nphase: {}\npanic: {}\n",
+      file_name, phase, info
+    );
+
+    if let Some(context) = &context {
+      let src = context.src.as_deref().unwrap_or("<not read>");
+      let tokens = TOKENS
+        .try_with(|tokens| match tokens.try_borrow().as_deref() {
+          Ok(Some(tokens)) => format!("{:#?}", tokens),
+          _ => "<not lexed>".to_string(),
+        })
+        .unwrap_or_else(|_| "<not lexed>".to_string());
+      let _ = writeln!(report, "input:\n{}\n\ntokens:\n{}\n", src, tokens);
+    }
+
+    let _ = writeln!(report, "backtrace:\n{}", backtrace);
+
+    if env::var_os(NO_BUG_REPORT_VAR).is_some() {
+      return;
+    }
+
+    match write_report(&env::temp_dir(), &report) {
+      Some(path) => eprintln!("A bug report was written to `{}`.", path.display()),
+      None => eprintln!("A bug report couldn't be written:\n\n{}", report),
+    }
+  }));
+}
+
+/// Sets the phase that the interpreter is in.
+pub fn set_phase(phase: Phase) {
+  if let Ok(mut context) = CONTEXT.lock() {
+    context.phase = phase;
+  }
+}
+
+/// Records the source file being run.
+pub fn set_source(file_name: &str, src: &str) {
+  if let Ok(mut context) = CONTEXT.lock() {
+    context.file_name = Some(file_name.to_string());
+    context.src = Some(src.to_string());
+  }
+}
+
+/// Records the lexed tokens of the source file.
+pub fn set_tokens(tokens: &[Token]) {
+  TOKENS.with(|stored| *stored.borrow_mut() = Some(tokens.to_vec()));
+}
+
+// Writes the report to a new file in the directory. The file is never one that already exists, so
+// that a file or link left at the path by someone else can't be written through.
+fn write_report(dir: &Path, report: &str) -> Option<PathBuf> {
+  let nanos = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |time| time.subsec_nanos());
+
+  for attempt in 0..100 {
+    let path = dir.join(format!(
+      "toy_language-ice-{}-{}-{}.txt",
+      process::id(),
+      nanos,
+      attempt
+    ));
+
+    match OpenOptions::new().write(true).create_new(true).open(&path) {
+      Ok(mut file) => return file.write_all(report.as_bytes()).ok().map(|()| path),
+      Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+      Err(_) => return None,
+    }
+  }
+
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+
+  #[test]
+  fn reports_are_written_to_new_files() {
+    let dir = env::temp_dir().join(format!("toy_language-ice-test-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let first = write_report(&dir, "first").unwrap();
+    let second = write_report(&dir, "second").unwrap();
+
+    assert_ne!(first, second);
+    assert_eq!(fs::read_to_string(&first).unwrap(), "first");
+    assert_eq!(fs::read_to_string(&second).unwrap(), "second");
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,368 @@
+use crate::{
+  capability::{Capabilities, Capability},
+  error::DiagnosticError,
+};
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+/// A source with the files that it includes with `#include "file"` spliced in, which keeps where
+/// each of its lines came from, so that diagnostics can be reported in the original files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expansion {
+  src: String,
+  // The name and source of every file that was read, starting with the one that was expanded
+  files: Vec<(String, String)>,
+  // The file and line that each line of the expanded source came from, starting at 1
+  lines: Vec<(usize, usize)>,
+}
+
+impl Expansion {
+  /// Returns the source with the included files spliced in.
+  pub fn src(&self) -> &str {
+    &self.src
+  }
+
+  /// Returns whether the source included any files, which is when its lines have to be located.
+  pub fn has_includes(&self) -> bool {
+    self.files.len() > 1
+  }
+
+  /// Returns the number of files that were read, including the one that was expanded.
+  pub fn file_count(&self) -> usize {
+    self.files.len()
+  }
+
+  /// Returns the name and source of the file that a line of the expanded source came from, along
+  /// with its line in that file.
+  ///
+  /// A line past the end, like the one after a trailing newline, is located after the last line.
+  pub fn locate(&self, line: usize) -> (&str, &str, usize) {
+    let (file, line) = match self.lines.get(line.saturating_sub(1)) {
+      Some(&location) => location,
+      None => self.lines.last().map_or((0, line), |&(file, last)| {
+        (file, last + line - self.lines.len())
+      }),
+    };
+    let (name, src) = &self.files[file];
+
+    (name, src, line)
+  }
+}
+
+/// Splices the files named by the `#include "file"` lines of the source in place of those lines.
+/// The path of an included file is relative to the directory of the file that includes it, and
+/// the files that it includes are expanded too.
+///
+/// Every include is an error unless [Capability::Include] is granted.
+///
+/// # Returns
+/// Returns the expansion, along with the errors of any includes that couldn't be expanded, which
+/// are at their lines in the expanded source. Those lines are left empty.
+pub fn expand(
+  file_name: &str,
+  src: &str,
+  capabilities: Capabilities,
+) -> (Expansion, Vec<DiagnosticError>) {
+  let mut expansion = Expansion {
+    src: String::new(),
+    files: Vec::new(),
+    lines: Vec::new(),
+  };
+  let mut errors = Vec::new();
+
+  splice(
+    &mut expansion,
+    &mut errors,
+    &mut vec![canonical_path(Path::new(file_name))],
+    capabilities,
+    file_name,
+    src,
+  );
+
+  (expansion, errors)
+}
+
+// Appends the lines of the file to the expansion, splicing in the files that it includes.
+// `including` is the chain of files being included, which including again would be a cycle.
+fn splice(
+  expansion: &mut Expansion,
+  errors: &mut Vec<DiagnosticError>,
+  including: &mut Vec<PathBuf>,
+  capabilities: Capabilities,
+  file_name: &str,
+  src: &str,
+) {
+  let file = expansion.files.len();
+  expansion
+    .files
+    .push((file_name.to_string(), src.to_string()));
+
+  for (line, text) in (1..).zip(src.split_inclusive('\n')) {
+    let Some(directive) = text.trim_start().strip_prefix("#include") else {
+      expansion.src.push_str(text);
+      expansion.lines.push((file, line));
+      continue;
+    };
+    let column = text.len() - text.trim_start().len() + 1;
+    let mut report = |msg: String| {
+      errors.push(DiagnosticError::new(msg, expansion.lines.len() + 1, column));
+    };
+
+    let path = match directive.trim().strip_prefix('"') {
+      Some(path)
+        if path.ends_with('"') && path.len() > 1 && !path[..path.len() - 1].contains('"') =>
+      {
+        &path[..path.len() - 1]
+      }
+      _ => {
+        let found = match directive.trim() {
+          "" => String::new(),
+          found => format!(", but found `{}`", found),
+        };
+
+        report(format!(
+          "Expected the path of a file after `#include`, eg `#include \"other.toy\"`{}.",
+          found
+        ));
+        expansion.src.push('\n');
+        expansion.lines.push((file, line));
+        continue;
+      }
+    };
+
+    // Nothing is read without the capability, so that the program can't see other files
+    if !capabilities.is_granted(Capability::Include) {
+      report(format!(
+        "The capability `{}` is not granted, so `{}` can't be included. it needs `--allow={}`.",
+        Capability::Include,
+        path,
+        Capability::Include
+      ));
+      expansion.src.push('\n');
+      expansion.lines.push((file, line));
+      continue;
+    }
+
+    let included = Path::new(file_name)
+      .parent()
+      .unwrap_or(Path::new(""))
+      .join(path);
+    // The file is recognized by where it really is, however it's reached, including by a symlink
+    let canonical = match fs::canonicalize(&included) {
+      Ok(canonical) => canonical,
+      Err(err) => {
+        report(format!(
+          "The file `{}` can't be included. reading it failed with `{}`.",
+          path, err
+        ));
+        expansion.src.push('\n');
+        expansion.lines.push((file, line));
+        continue;
+      }
+    };
+
+    if let Some(start) = including.iter().position(|file| *file == canonical) {
+      let chain = including[start..]
+        .iter()
+        .chain([&canonical])
+        .map(|file| format!("`{}`", file.display()))
+        .collect::<Vec<_>>();
+
+      report(format!(
+        "The include of `{}` is a cycle. the files include each other in the order {}.",
+        path,
+        chain.join(", ")
+      ));
+      expansion.src.push('\n');
+      expansion.lines.push((file, line));
+      continue;
+    }
+
+    match fs::read_to_string(&canonical) {
+      Ok(mut included_src) => {
+        // The line after the include starts on its own line, even if the file doesn't end with one
+        if text.ends_with('\n') && !included_src.is_empty() && !included_src.ends_with('\n') {
+          included_src.push('\n');
+        }
+
+        let included_name = included.to_string_lossy().into_owned();
+
+        including.push(canonical);
+        splice(
+          expansion,
+          errors,
+          including,
+          capabilities,
+          &included_name,
+          &included_src,
+        );
+        including.pop();
+      }
+      Err(err) => {
+        report(format!(
+          "The file `{}` can't be included. reading it failed with `{}`.",
+          path, err
+        ));
+        expansion.src.push('\n');
+        expansion.lines.push((file, line));
+      }
+    }
+  }
+}
+
+// Returns the path that a file is at, without any `.`, `..`, or symlinks, so that the same file
+// included by different paths is recognized.
+fn canonical_path(path: &Path) -> PathBuf {
+  fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{env, process};
+
+  // A directory of files written for a test, which is removed when it's dropped, even if the test
+  // fails.
+  struct Fixture(PathBuf);
+
+  impl std::ops::Deref for Fixture {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+      &self.0
+    }
+  }
+
+  impl Drop for Fixture {
+    fn drop(&mut self) {
+      let _ = fs::remove_dir_all(&self.0);
+    }
+  }
+
+  // Writes the files into a new directory, then expands the source of `main.toy` in it, returning
+  // the directory along with the expansion.
+  fn expand_files(
+    test: &str,
+    src: &str,
+    files: &[(&str, &str)],
+    capabilities: Capabilities,
+  ) -> (Fixture, Expansion, Vec<DiagnosticError>) {
+    let dir =
+      Fixture(env::temp_dir().join(format!("toy_language-include-{}-{}", test, process::id())));
+    let _ = fs::remove_dir_all(&*dir);
+
+    for (path, src) in [("main.toy", src)].iter().chain(files) {
+      let path = dir.join(path);
+
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(path, src).unwrap();
+    }
+
+    let main = dir.join("main.toy");
+    let (expansion, errors) = expand(main.to_str().unwrap(), src, capabilities);
+
+    (dir, expansion, errors)
+  }
+
+  fn include() -> Capabilities {
+    Capabilities::default().with(Capability::Include)
+  }
+
+  #[test]
+  fn splices_and_locates_lines() {
+    let src = "a = 1;\n  #include \"lib/b.toy\"\nc = 3;";
+    let (dir, expansion, errors) = expand_files(
+      "splices",
+      src,
+      &[
+        ("lib/b.toy", "b = 2;\n#include \"d.toy\""),
+        ("lib/d.toy", "d = 4;"),
+      ],
+      include(),
+    );
+
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert!(expansion.has_includes());
+    assert_eq!(expansion.src(), "a = 1;\nb = 2;\nd = 4;\nc = 3;");
+    assert_eq!(
+      expansion.locate(1),
+      (dir.join("main.toy").to_str().unwrap(), src, 1)
+    );
+    assert_eq!(
+      expansion.locate(3).0,
+      dir.join("lib/d.toy").to_str().unwrap()
+    );
+    assert_eq!(expansion.locate(3).2, 1);
+    assert_eq!(expansion.locate(4).2, 3);
+  }
+
+  #[test]
+  fn reports_bad_includes() {
+    let (dir, expansion, errors) = expand_files(
+      "bad",
+      "#include nope\n#include \"a.toy\"\n#include \"missing.toy\"",
+      &[("a.toy", "#include \"./a.toy\"")],
+      include(),
+    );
+    let errors = errors
+      .iter()
+      .map(|err| {
+        let (file, _, line) = expansion.locate(err.line());
+
+        (file.to_string(), line, err.column())
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      errors,
+      vec![
+        (dir.join("main.toy").to_str().unwrap().to_string(), 1, 1),
+        (dir.join("a.toy").to_str().unwrap().to_string(), 1, 1),
+        (dir.join("main.toy").to_str().unwrap().to_string(), 3, 1),
+      ]
+    );
+  }
+
+  #[test]
+  fn includes_need_capability() {
+    let (_, expansion, errors) = expand_files(
+      "capability",
+      "#include \"/etc/hostname\"\nx = 1;",
+      &[],
+      Capabilities::default(),
+    );
+    let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+    assert_eq!(expansion.src(), "\nx = 1;");
+    assert_eq!(
+      errors,
+      vec![
+        "The capability `include` is not granted, so `/etc/hostname` can't be included. it needs \
+         `--allow=include`."
+      ]
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn symlinks_are_cycles() {
+    let (dir, _, _) = expand_files(
+      "symlink",
+      "",
+      &[("a.toy", "#include \"link.toy\"")],
+      include(),
+    );
+
+    std::os::unix::fs::symlink(dir.join("a.toy"), dir.join("link.toy")).unwrap();
+
+    let main = dir.join("main.toy");
+    let (_, errors) = expand(main.to_str().unwrap(), "#include \"a.toy\"", include());
+
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0]
+      .to_string()
+      .starts_with("The include of `link.toy` is a cycle."));
+  }
+}
@@ -1,119 +1,3107 @@
 use crate::{
+  builtin::Builtin,
+  capability::{Capabilities, Capability},
   error::DiagnosticError,
-  node::{Node, Operator},
+  lexer::Lexer,
+  limits::DEFAULT_MAX_CALL_DEPTH,
+  memory::{self, MemoryUsage},
+  node::{IdentifierNode, Node, Operator, Pattern, Segment},
+  steps::reduction_steps,
+  token::TokenKind,
   util::linebreak_index,
+  value::{format_value, operator_rule, Function, Value},
 };
-use std::collections::HashMap;
+use std::{
+  collections::HashMap,
+  io::{self, Read, Write},
+  ops::Range,
+  rc::Rc,
+};
+
+// The structs declared so far, by name, with the names of their fields and the line of their
+// declaration.
+type Structs = HashMap<String, (Rc<[Rc<str>]>, usize)>;
+
+// A function registered by the embedder, which is called with the values of its arguments.
+type HostFunction = Rc<dyn Fn(&[Value]) -> Result<Value, String>>;
 
 /// An interpreter for the toy language.
-pub struct Interpreter<'a> {
-  src: &'a str,
+pub struct Interpreter {
+  src: String,
   root: Node,
-  variables: HashMap<&'a str, isize>,
+  variables: HashMap<String, Value>,
+  // The variables declared with `const`, with the line of their declaration
+  constants: HashMap<String, usize>,
+  structs: Structs,
+  // The functions registered with `register_builtin`, by name
+  functions: HashMap<String, HostFunction>,
+  // The names of the variables in the order they were first assigned in, since the map's order
+  // changes from run to run
+  order: Vec<String>,
+  arithmetic: Arithmetic,
+  warnings: Vec<DiagnosticError>,
+  operations: usize,
+  output: Box<dyn Write>,
+  input: Box<dyn Read>,
+  capabilities: Capabilities,
+  // The flags that the statements marked with `#[if: FLAG]` are run for
+  flags: Vec<String>,
+  // How many calls of functions made by lambdas can be nested
+  max_call_depth: usize,
+  // The changes made by each program that was committed, oldest first, and the changes that were
+  // undone since the last commit, most recently undone last
+  history: Vec<Change>,
+  undone: Vec<Change>,
+}
+
+// The variables changed by a committed program, so that they can be undone and redone.
+#[derive(Clone, Debug)]
+struct Change {
+  // The name of each changed variable, with its value before and after the program
+  values: Vec<(String, Option<Value>, Value)>,
+  // The variables that were first assigned by the program, in the order they were assigned in
+  added: Vec<String>,
+}
+
+/// The result of evaluating one statement, from [Interpreter::evaluate_statements].
+#[derive(Clone, Debug)]
+pub struct StatementResult {
+  /// The byte range of the statement in the source, from its first token to its last.
+  pub span: Range<usize>,
+  /// The variable that the statement assigns, if it's an assignment or a read.
+  ///
+  /// This is the outermost variable of a chained assignment, eg `a` in `a = b = 1;`.
+  pub target: Option<String>,
+  /// The value that the statement assigned to its target or printed, if it didn't have errors.
+  pub value: Option<Value>,
+  /// The errors and then the warnings reported by the statement.
+  pub diagnostics: Vec<DiagnosticError>,
+}
+
+/// An iterator that evaluates a program a statement at a time, from
+/// [Interpreter::evaluate_statements].
+pub struct Statements<'a> {
+  interpreter: &'a mut Interpreter,
+  // The variables as of the last statement, which are taken once every statement is evaluated
+  variables: Option<HashMap<String, Value>>,
+  // Likewise for the constants and structs
+  constants: HashMap<String, usize>,
+  structs: Structs,
+  next: usize,
+  failed: bool,
+}
+
+/// How arithmetic is evaluated.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Arithmetic {
+  /// What happens when arithmetic overflows, if there's no modulus.
+  pub overflow: Overflow,
+  /// The modulus that all arithmetic happens in, set with `#[modulus: N]`.
+  pub modulus: Option<isize>,
+}
+
+/// What happens when arithmetic overflows an [isize].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+  /// Report an error.
+  #[default]
+  Checked,
+  /// Wrap around at the bounds of an [isize].
+  Wrapping,
+  /// Clamp to [isize::MIN] or [isize::MAX], with a warning for each assignment that does.
+  Saturating,
 }
 
-impl<'a> Interpreter<'a> {
+impl Interpreter {
   /// Creates a new interpreter from the souce string and root node.
   ///
   /// The source string is needed for better error diagnostics such as reporting
   /// uninitialized variables.
-  pub fn new(src: &'a str, root: Node) -> Self {
+  pub fn new(src: &str, root: Node) -> Self {
     Self {
-      src,
+      src: src.to_string(),
       root,
       variables: HashMap::new(),
+      constants: HashMap::new(),
+      structs: HashMap::new(),
+      functions: HashMap::new(),
+      order: Vec::new(),
+      arithmetic: Arithmetic::default(),
+      warnings: Vec::new(),
+      operations: 0,
+      output: Box::new(io::stdout()),
+      input: Box::new(io::stdin()),
+      capabilities: Capabilities::default(),
+      flags: Vec::new(),
+      max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+      history: Vec::new(),
+      undone: Vec::new(),
     }
   }
 
+  /// Registers a function that programs can call by the name, like the built-in ones, replacing
+  /// any function of the same name.
+  ///
+  /// The function is called with the values of the arguments, however many there are. An error
+  /// that it returns is reported at the statement that called it.
+  pub fn register_builtin(
+    &mut self,
+    name: &str,
+    function: impl Fn(&[Value]) -> Result<Value, String> + 'static,
+  ) {
+    self.functions.insert(name.to_string(), Rc::new(function));
+  }
+
+  /// Sets where print statements write to, which is stdout by default.
+  pub fn with_output(mut self, output: impl Write + 'static) -> Self {
+    self.output = Box::new(output);
+    self
+  }
+
+  /// Sets where read statements read from, which is stdin by default.
+  ///
+  /// Each read statement consumes one line.
+  pub fn with_input(mut self, input: impl Read + 'static) -> Self {
+    self.input = Box::new(input);
+    self
+  }
+
+  /// Sets the capabilities that the program is granted, which is none by default.
+  ///
+  /// Read statements are errors unless [Capability::Read] is granted, even with an input.
+  pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+    self.capabilities = capabilities;
+    self
+  }
+
+  /// Sets the flags that the statements marked with `#[if: FLAG]` are run for, which is none by
+  /// default. The statements whose flags aren't all set are skipped.
+  pub fn with_flags(mut self, flags: &[String]) -> Self {
+    self.flags = flags.to_vec();
+    self
+  }
+
+  /// Sets what happens when arithmetic overflows.
+  pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+    self.arithmetic.overflow = overflow;
+    self
+  }
+
+  /// Sets how many calls of functions made by lambdas can be nested, which is
+  /// [DEFAULT_MAX_CALL_DEPTH] by default, so that a function that calls itself forever is
+  /// reported rather than overflowing the stack.
+  pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+    self.max_call_depth = max_call_depth;
+    self
+  }
+
+  /// Makes all arithmetic happen modulo the number, if one is given.
+  pub fn with_modulus(mut self, modulus: Option<isize>) -> Self {
+    self.arithmetic.modulus = modulus;
+    self
+  }
+
+  /// Replaces the program to run, keeping the variables set by the previous ones.
+  ///
+  /// This lets a session, like the REPL, run a program one piece at a time.
+  pub fn load(&mut self, src: &str, root: Node) {
+    self.src = src.to_string();
+    self.root = root;
+  }
+
   /// Evaluates the results, updating the set variables in memory.
   ///
+  /// The variables are only updated if there aren't any errors.
+  ///
   /// # Returns
   /// Returns all diagnostics errors in the case of failure.
   pub fn evaluate(&mut self) -> Result<(), Vec<DiagnosticError>> {
-    let mut errors = Vec::new();
+    let mut variables = self.variables.clone();
+    let mut evaluator = Evaluator {
+      src: &self.src,
+      variables: &mut variables,
+      constants: self.constants.clone(),
+      structs: self.structs.clone(),
+      functions: &self.functions,
+      scopes: Vec::new(),
+      ended: HashMap::new(),
+      arithmetic: self.arithmetic,
+      errors: Vec::new(),
+      warnings: Vec::new(),
+      statement: None,
+      overflowed: false,
+      operations: 0,
+      output: &mut *self.output,
+      input: &mut *self.input,
+      capabilities: self.capabilities,
+      flags: &self.flags,
+      jump: None,
+      depth: 0,
+      max_depth: self.max_call_depth,
+    };
+
+    evaluator.evaluate(&self.root);
 
-    evaluate_node(self.src, &self.root, &mut self.variables, &mut errors);
+    let Evaluator {
+      errors,
+      warnings,
+      operations,
+      constants,
+      structs,
+      ..
+    } = evaluator;
+    self.warnings = warnings;
+    self.operations = operations;
 
     if errors.is_empty() {
+      self.constants = constants;
+      self.structs = structs;
+      self.commit(variables);
+
       Ok(())
     } else {
       Err(errors)
     }
   }
 
-  /// Prints the set variables in memory
-  pub fn dump(&self) {
-    for (k, v) in &self.variables {
-      println!("{} => {}", k, v);
+  /// Returns an iterator that evaluates the statements of the program one at a time, as it's
+  /// advanced, with the result of each one.
+  ///
+  /// Like [Interpreter::evaluate], the variables are only updated once every statement has been
+  /// evaluated without errors, so nothing is updated if the iterator is dropped early.
+  pub fn evaluate_statements(&mut self) -> Statements<'_> {
+    self.warnings.clear();
+    self.operations = 0;
+
+    Statements {
+      variables: Some(self.variables.clone()),
+      constants: self.constants.clone(),
+      structs: self.structs.clone(),
+      interpreter: self,
+      next: 0,
+      failed: false,
     }
   }
-}
 
-fn evaluate_node<'a>(
-  src: &'a str,
-  node: &Node,
-  variables: &mut HashMap<&'a str, isize>,
-  errors: &mut Vec<DiagnosticError>,
-) -> isize {
-  match node {
-    Node::Program(nodes) => {
-      for node in nodes {
-        evaluate_node(src, node, variables, errors);
+  // Sets the variables of a program that was evaluated without errors, recording what changed so
+  // that it can be undone.
+  fn commit(&mut self, variables: HashMap<String, Value>) {
+    let mut added = Vec::new();
+
+    for name in self.root.assigned_variables() {
+      if variables.contains_key(name) && !self.order.iter().any(|prev| prev == name) {
+        self.order.push(name.to_string());
+        added.push(name.to_string());
       }
+    }
+
+    let mut values = variables
+      .iter()
+      .filter(|(name, value)| self.variables.get(*name) != Some(value))
+      .map(|(name, value)| {
+        (
+          name.clone(),
+          self.variables.get(name).cloned(),
+          value.clone(),
+        )
+      })
+      .collect::<Vec<_>>();
+
+    // Sorted so that the changes are reported in the same order from run to run
+    values.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+
+    // A program that didn't change anything, like a `print`, isn't worth undoing
+    if !values.is_empty() {
+      self.history.push(Change { values, added });
+      self.undone.clear();
+    }
+
+    self.variables = variables;
+  }
+
+  /// Restores the variables to how they were before the last program that changed them.
+  ///
+  /// # Returns
+  /// Returns the names of the variables that were restored, or [None] if there's nothing to undo.
+  pub fn undo(&mut self) -> Option<Vec<String>> {
+    let change = self.history.pop()?;
+
+    for (name, before, _) in &change.values {
+      match before {
+        Some(value) => self.variables.insert(name.clone(), value.clone()),
+        None => self.variables.remove(name),
+      };
+    }
+
+    self.order.truncate(self.order.len() - change.added.len());
+
+    let names = change
+      .values
+      .iter()
+      .map(|(name, ..)| name.clone())
+      .collect();
+    self.undone.push(change);
+
+    Some(names)
+  }
+
+  /// Reapplies the last change undone by [Interpreter::undo], as long as no program has changed
+  /// the variables since.
+  ///
+  /// # Returns
+  /// Returns the names of the variables that were changed, or [None] if there's nothing to redo.
+  pub fn redo(&mut self) -> Option<Vec<String>> {
+    let change = self.undone.pop()?;
+
+    for (name, _, after) in &change.values {
+      self.variables.insert(name.clone(), after.clone());
+    }
+
+    self.order.extend(change.added.iter().cloned());
+
+    let names = change
+      .values
+      .iter()
+      .map(|(name, ..)| name.clone())
+      .collect();
+    self.history.push(change);
+
+    Some(names)
+  }
+
+  /// Returns the warnings reported by the last call to [Interpreter::evaluate].
+  pub fn warnings(&self) -> &[DiagnosticError] {
+    &self.warnings
+  }
+
+  /// Returns the number of operations performed by the last call to [Interpreter::evaluate].
+  pub fn operations(&self) -> usize {
+    self.operations
+  }
+
+  /// Returns the number of variables that are set.
+  pub fn variable_count(&self) -> usize {
+    self.variables.len()
+  }
 
-      // Doesn't really matter what number return in this case
-      0
+  /// Returns an estimate of the memory held by the set variables.
+  pub fn env_size(&self) -> MemoryUsage {
+    MemoryUsage {
+      nodes: 0,
+      string_bytes: self.variables.keys().map(String::len).sum(),
+      value_bytes: self.variables.values().map(memory::value_bytes).sum(),
     }
-    Node::Assignment(var_node, expr) => {
-      // Identifiers are the only possible Node here
-      if let Node::Identifier(ident_node) = &**var_node {
-        let rhs = evaluate_node(src, expr, variables, errors);
+  }
+
+  /// Returns the reduction steps of every assignment, eg `x = (2 + 3) * 4 → 5 * 4 → 20`.
+  ///
+  /// The assignments are replayed from scratch, so this can be called before or after
+  /// [Interpreter::evaluate].
+  pub fn reduction_steps(&self) -> Vec<String> {
+    let mut variables = HashMap::new();
+    let mut lines = Vec::new();
 
-        variables.insert(src.get(ident_node.range.clone()).unwrap(), rhs);
+    if let Node::Program(assignments) = &self.root {
+      for assignment in assignments {
+        // The values of a multiple assignment are each shown as their own assignment
+        let chains = match assignment {
+          Node::MultiAssignment(idents, values) => idents
+            .iter()
+            .zip(values)
+            .map(|(ident, value)| (vec![ident], value))
+            .collect(),
+          _ => assignment
+            .assignment_chain()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        };
+        let mut assigned = Vec::new();
+
+        for (idents, expr) in chains {
+          let steps = reduction_steps(expr, &variables, self.arithmetic);
+          let rendered = steps.iter().map(Node::to_string).collect::<Vec<_>>();
+          let names = idents
+            .iter()
+            .map(|ident| format!("{} = ", ident.literal))
+            .collect::<String>();
+
+          lines.push(format!("{}{}", names, rendered.join(" → ")));
+
+          // Stop replaying once a step can't be reduced to a value
+          match steps.last() {
+            Some(Node::Literal(lit)) => {
+              for ident in idents {
+                assigned.push((
+                  self.src.get(ident.range.clone()).unwrap(),
+                  lit.value.clone(),
+                ));
+              }
+            }
+            _ => return lines,
+          }
+        }
+
+        // Every value is reduced before any variable is set, like when evaluating them
+        variables.extend(assigned);
       }
+    }
+
+    lines
+  }
+
+  /// Returns the value of the variable, if it's been set.
+  pub fn variable(&self, name: &str) -> Option<Value> {
+    self.variables.get(name).cloned()
+  }
+
+  /// Writes the set variables in memory to `out`, one per line, in the order that they were
+  /// first assigned in.
+  ///
+  /// If `group_digits` is set, values are written with `_` between every group of three digits,
+  /// eg `1_234_567`.
+  pub fn dump(&self, out: &mut impl Write, group_digits: bool) -> io::Result<()> {
+    for name in &self.order {
+      let value = self.variables[name].clone();
+
+      writeln!(out, "{} => {}", name, format_value(value, group_digits))?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Overflow {
+  /// Applies a binary operator to both operands, returning the result and whether it
+  /// overflowed.
+  ///
+  /// Division truncates towards zero, and the remainder has the sign of the left operand.
+  /// Operations without a value, like dividing by zero, are treated as overflows, so callers
+  /// that report them differently have to check [Operator::is_undefined_for] first.
+  pub fn binary(self, op: Operator, lhs: isize, rhs: isize) -> (isize, bool) {
+    if op.is_undefined_for(&Value::Int(lhs), &Value::Int(rhs)) {
+      return (0, true);
+    }
 
-      // Doesn't really matter what number return in this case
-      0
+    if let Some(holds) = op.compare(lhs, rhs) {
+      return (holds as isize, false);
     }
-    Node::Expression(expr) => evaluate_node(src, expr, variables, errors),
-    Node::Term(lhs, op, rhs) => match op {
-      Operator::Plus => {
-        evaluate_node(src, lhs, variables, errors) + evaluate_node(src, rhs, variables, errors)
+
+    match op {
+      Operator::And => return ((lhs != 0 && rhs != 0) as isize, false),
+      Operator::Or => return ((lhs != 0 || rhs != 0) as isize, false),
+      _ => {}
+    }
+
+    let (wrapped, overflowed) = match op {
+      Operator::Plus => lhs.overflowing_add(rhs),
+      Operator::Minus => lhs.overflowing_sub(rhs),
+      Operator::Multiply => lhs.overflowing_mul(rhs),
+      Operator::Divide => lhs.overflowing_div(rhs),
+      // The only overflow, `isize::MIN % -1`, still has a remainder of 0
+      Operator::Modulo => (lhs.wrapping_rem(rhs), false),
+      Operator::Power => overflowing_pow(lhs, rhs),
+      // Shifting out any bit that differs from the sign changes the value, unlike multiplying
+      Operator::ShiftLeft => {
+        let shifted = lhs << rhs;
+
+        (shifted, shifted >> rhs != lhs)
       }
+      Operator::ShiftRight => (lhs >> rhs, false),
+      _ => unreachable!("comparisons and logic are handled separately"),
+    };
+
+    let value = match (self, op) {
+      (Overflow::Saturating, Operator::Plus) => lhs.saturating_add(rhs),
+      (Overflow::Saturating, Operator::Minus) => lhs.saturating_sub(rhs),
+      (Overflow::Saturating, Operator::Multiply) => lhs.saturating_mul(rhs),
+      (Overflow::Saturating, Operator::Divide) => lhs.saturating_div(rhs),
+      // Odd powers keep the sign of the base
+      (Overflow::Saturating, Operator::Power) if overflowed && lhs < 0 && rhs % 2 == 1 => {
+        isize::MIN
+      }
+      (Overflow::Saturating, Operator::Power) if overflowed => isize::MAX,
+      (Overflow::Saturating, Operator::ShiftLeft) if overflowed && lhs < 0 => isize::MIN,
+      (Overflow::Saturating, Operator::ShiftLeft) if overflowed => isize::MAX,
+      _ => wrapped,
+    };
+
+    (value, overflowed)
+  }
+
+  /// Applies a unary operator to the operand, returning the result and whether it overflowed.
+  pub fn unary(self, op: Operator, rhs: isize) -> (isize, bool) {
+    match op {
       Operator::Minus => {
-        evaluate_node(src, lhs, variables, errors) - evaluate_node(src, rhs, variables, errors)
-      }
-      Operator::Multiply => {
-        evaluate_node(src, lhs, variables, errors) * evaluate_node(src, rhs, variables, errors)
-      }
-    },
-    Node::Fact(fact) => evaluate_node(src, fact, variables, errors),
-    Node::UnaryOperator(op, rhs) => match op {
-      Operator::Minus => -evaluate_node(src, rhs, variables, errors),
-      Operator::Plus => evaluate_node(src, rhs, variables, errors),
-      // `* Fact` is not allowed in the grammar
-      Operator::Multiply => unreachable!("`* Fact` should be unreachable."),
-    },
-    Node::Identifier(var_node) => {
-      match variables.get(var_node.literal.as_str()).copied() {
-        Some(num) => num,
-        None => {
-          let node_range = var_node.range.clone();
-
-          errors.push(DiagnosticError::new(
-            format!(
-              "The identifier `{}`, has not yet been initialized.",
-              &var_node.literal
-            ),
-            var_node.line,
-            node_range.start + 1 - linebreak_index(src, node_range),
-          ));
+        let (wrapped, overflowed) = rhs.overflowing_neg();
+
+        match self {
+          Overflow::Saturating => (rhs.saturating_neg(), overflowed),
+          _ => (wrapped, overflowed),
+        }
+      }
+      Operator::Plus => (rhs, false),
+      Operator::Not => ((rhs == 0) as isize, false),
+      // `* Fact`, `/ Fact`, and `% Fact` aren't allowed in the grammar
+      _ => unreachable!("`* Fact` should be unreachable."),
+    }
+  }
+}
+
+// Raises the base to a non-negative power by squaring, returning the wrapped result and
+// whether it overflowed.
+fn overflowing_pow(mut base: isize, mut exponent: isize) -> (isize, bool) {
+  let mut result: isize = 1;
+  let mut overflowed = false;
+
+  while exponent > 0 {
+    if exponent & 1 == 1 {
+      let (value, overflow) = result.overflowing_mul(base);
+      result = value;
+      overflowed |= overflow;
+    }
+
+    exponent >>= 1;
+
+    // The squared base is only used if there are more bits left
+    if exponent > 0 {
+      let (value, overflow) = base.overflowing_mul(base);
+      base = value;
+      overflowed |= overflow;
+    }
+  }
+
+  (result, overflowed)
+}
+
+impl Arithmetic {
+  /// Applies a binary operator to both operands, returning the result and whether it
+  /// overflowed.
+  ///
+  /// If either operand is a float, both are promoted to floats, and the result overflows if it
+  /// isn't finite. Floats ignore the overflow mode and the modulus.
+  ///
+  /// Comparisons and logical operators result in `1` or `0`, and use the reduced values of
+  /// integers if there's a modulus, so `98 == 1` holds modulo 97.
+  ///
+  /// Strings are concatenated by `+`, and compared by their characters. Arrays are also
+  /// concatenated by `+`, and are equal if their elements are, like tuples, and structs are equal
+  /// if they're of the same struct and their fields are. `none` is only equal to itself, and `??`
+  /// results in its right operand if the left one is `none`. Like an overflow, an operator that
+  /// doesn't apply to its operands, like `"a" * 2`, has no value, so callers that report them
+  /// differently have to check [Operator::applies_to] first.
+  pub fn binary(self, op: Operator, lhs: &Value, rhs: &Value) -> (Value, bool) {
+    if !op.applies_to(lhs, rhs) {
+      return (Value::Int(0), true);
+    }
+
+    if matches!(op, Operator::Coalesce) {
+      return match lhs {
+        Value::None => (rhs.clone(), false),
+        _ => (lhs.clone(), false),
+      };
+    }
+
+    if op.is_comparison() || op.is_logical() {
+      let holds = match (op, self.normalize(lhs.clone()), self.normalize(rhs.clone())) {
+        (Operator::And, lhs, rhs) => !lhs.is_zero() && !rhs.is_zero(),
+        (Operator::Or, lhs, rhs) => !lhs.is_zero() || !rhs.is_zero(),
+        (_, Value::None, rhs) | (_, rhs, Value::None) => {
+          rhs.is_none() == matches!(op, Operator::Equal)
+        }
+        (_, Value::Int(lhs), Value::Int(rhs)) => op.compare(lhs, rhs).unwrap(),
+        (_, Value::Str(lhs), Value::Str(rhs)) => op.compare(lhs, rhs).unwrap(),
+        (_, Value::Array(lhs), Value::Array(rhs)) | (_, Value::Tuple(lhs), Value::Tuple(rhs)) => {
+          let equal = lhs.len() == rhs.len()
+            && lhs
+              .iter()
+              .zip(rhs.iter())
+              .all(|(l, r)| self.binary(Operator::Equal, l, r) == (Value::Int(1), false));
 
-          // Continue recursing to handle multiple errors at once
-          0
+          equal == matches!(op, Operator::Equal)
         }
+        (_, Value::Struct(lhs_name, lhs), Value::Struct(rhs_name, rhs)) => {
+          // Structs of the same name have the same fields, in the same order
+          let equal = lhs_name == rhs_name
+            && lhs
+              .iter()
+              .zip(rhs.iter())
+              .all(|((_, l), (_, r))| self.binary(Operator::Equal, l, r) == (Value::Int(1), false));
+
+          equal == matches!(op, Operator::Equal)
+        }
+        (_, lhs, rhs) => op.compare(lhs.as_float(), rhs.as_float()).unwrap(),
+      };
+
+      return (Value::Int(holds as isize), false);
+    }
+
+    match (lhs, rhs) {
+      (Value::Int(lhs), Value::Int(rhs)) => {
+        let (value, overflowed) = self.binary_int(op, *lhs, *rhs);
+
+        (Value::Int(value), overflowed)
+      }
+      (Value::Str(lhs), Value::Str(rhs)) => (Value::Str(format!("{}{}", lhs, rhs).into()), false),
+      (Value::Array(lhs), Value::Array(rhs)) => (
+        Value::Array(lhs.iter().chain(rhs.iter()).cloned().collect()),
+        false,
+      ),
+      _ if op.is_undefined_for(lhs, rhs) => (Value::Float(0.0), true),
+      _ => {
+        let (lhs, rhs) = (lhs.as_float(), rhs.as_float());
+        let value = match op {
+          Operator::Plus => lhs + rhs,
+          Operator::Minus => lhs - rhs,
+          Operator::Multiply => lhs * rhs,
+          Operator::Divide => lhs / rhs,
+          Operator::Modulo => lhs % rhs,
+          Operator::Power => lhs.powf(rhs),
+          // Floats are scaled by a power of two instead, like `/` doesn't truncate them
+          Operator::ShiftLeft => lhs * 2f64.powf(rhs),
+          Operator::ShiftRight => lhs / 2f64.powf(rhs),
+          _ => unreachable!("comparisons and logic are handled separately"),
+        };
+
+        (Value::Float(value), !value.is_finite())
+      }
+    }
+  }
+
+  // Applies a binary operator to two integers.
+  //
+  // Arithmetic with a modulus never overflows, other than operations without a value. Division
+  // truncates the reduced operands, rather than multiplying by an inverse.
+  fn binary_int(self, op: Operator, lhs: isize, rhs: isize) -> (isize, bool) {
+    match self.modulus {
+      Some(_) if self.is_undefined(op, &Value::Int(lhs), &Value::Int(rhs)) => (0, true),
+      Some(modulus) if matches!(op, Operator::Power) => (mod_pow(lhs, rhs, modulus), false),
+      Some(modulus) => {
+        // The result of any operation on two `isize`s fits in an `i128`
+        let (lhs, rhs) = (lhs as i128, rhs as i128);
+        let value = match op {
+          Operator::Plus => lhs + rhs,
+          Operator::Minus => lhs - rhs,
+          Operator::Multiply => lhs * rhs,
+          Operator::Divide => lhs.rem_euclid(modulus as i128) / rhs.rem_euclid(modulus as i128),
+          Operator::Modulo => lhs.rem_euclid(modulus as i128) % rhs.rem_euclid(modulus as i128),
+          Operator::Power => unreachable!("powers are handled separately"),
+          Operator::ShiftLeft => lhs * mod_pow(2, rhs as isize, modulus) as i128,
+          Operator::ShiftRight => lhs.rem_euclid(modulus as i128) >> rhs,
+          _ => unreachable!("comparisons and logic are handled separately"),
+        };
+
+        (value.rem_euclid(modulus as i128) as isize, false)
+      }
+      None => self.overflow.binary(op, lhs, rhs),
+    }
+  }
+
+  /// Applies a unary operator to the operand, returning the result and whether it overflowed.
+  ///
+  /// Only `!` applies to strings, arrays, tuples, structs, and `none`, which is `1` for an empty one
+  /// or `none`. Any other operator has no value, like an overflow.
+  pub fn unary(self, op: Operator, rhs: &Value) -> (Value, bool) {
+    if matches!(op, Operator::Not) {
+      return (
+        Value::Int(self.normalize(rhs.clone()).is_zero() as isize),
+        false,
+      );
+    }
+
+    let rhs = match rhs {
+      Value::Int(rhs) => *rhs,
+      Value::Float(rhs) => match op {
+        Operator::Minus => return (Value::Float(-rhs), false),
+        _ => return (Value::Float(*rhs), false),
+      },
+      Value::Str(_)
+      | Value::Array(_)
+      | Value::Tuple(_)
+      | Value::Struct(..)
+      | Value::Function(_)
+      | Value::None => return (Value::Int(0), true),
+    };
+
+    let (value, overflowed) = match self.modulus {
+      Some(_) => match op {
+        Operator::Minus => self.binary_int(Operator::Minus, 0, rhs),
+        Operator::Plus => self.binary_int(Operator::Plus, 0, rhs),
+        // `* Fact`, `/ Fact`, and `% Fact` aren't allowed in the grammar
+        _ => unreachable!("`* Fact` should be unreachable."),
+      },
+      None => self.overflow.unary(op, rhs),
+    };
+
+    (Value::Int(value), overflowed)
+  }
+
+  /// Calls the built-in function with the arguments, returning the result and whether it
+  /// overflowed.
+  ///
+  /// `abs` and `pow` are `-` and `**` of the arguments, so they overflow and use the modulus like
+  /// those do, and `min` and `max` compare the arguments with `<`. Like an operator, a function
+  /// that doesn't apply to its arguments has no value, so callers have to check
+  /// [Builtin::applies_to] first.
+  pub fn call(self, builtin: Builtin, args: &[Value]) -> (Value, bool) {
+    if !builtin.applies_to(args) {
+      return (Value::Int(0), true);
+    }
+
+    let less = |a: &Value, b: &Value| !self.binary(Operator::Less, a, b).0.is_zero();
+
+    match (builtin, args) {
+      (Builtin::Abs, [x]) if less(x, &Value::Int(0)) => self.unary(Operator::Minus, x),
+      (Builtin::Abs, [x]) => (x.clone(), false),
+      (Builtin::Min, [a, b]) => (if less(b, a) { b } else { a }.clone(), false),
+      (Builtin::Max, [a, b]) => (if less(a, b) { b } else { a }.clone(), false),
+      (Builtin::Pow, [a, b]) => self.binary(Operator::Power, a, b),
+      _ => unreachable!("functions only apply to as many arguments as they take"),
+    }
+  }
+
+  /// Returns whether the operator has no value for the right operand, like
+  /// [Operator::is_undefined_for], except that a divisor is reduced by the modulus first, so
+  /// dividing or taking a remainder by a multiple of the modulus divides by zero.
+  pub fn is_undefined(self, op: Operator, lhs: &Value, rhs: &Value) -> bool {
+    match op {
+      Operator::Divide | Operator::Modulo => op.is_undefined_for(lhs, &self.normalize(rhs.clone())),
+      _ => op.is_undefined_for(lhs, rhs),
+    }
+  }
+
+  /// Reduces the value into the range of the modulus, if there is one.
+  ///
+  /// Floats are left as is, since they can't be reduced, and arrays, tuples, and structs have each
+  /// of their elements reduced.
+  pub fn normalize(self, value: Value) -> Value {
+    match (&value, self.modulus) {
+      (Value::Int(value), Some(modulus)) => Value::Int(value.rem_euclid(modulus)),
+      (Value::Array(values), Some(_)) => Value::Array(
+        values
+          .iter()
+          .map(|value| self.normalize(value.clone()))
+          .collect(),
+      ),
+      (Value::Tuple(values), Some(_)) => Value::Tuple(
+        values
+          .iter()
+          .map(|value| self.normalize(value.clone()))
+          .collect(),
+      ),
+      (Value::Struct(name, fields), Some(_)) => Value::Struct(
+        name.clone(),
+        fields
+          .iter()
+          .map(|(field, value)| (field.clone(), self.normalize(value.clone())))
+          .collect(),
+      ),
+      _ => value,
+    }
+  }
+
+  /// Returns whether the value matches the pattern, comparing them like `==` does.
+  ///
+  /// A string never matches a number, or the other way around.
+  pub fn matches(self, pattern: &Pattern, value: &Value) -> bool {
+    match pattern {
+      Pattern::Literal(literal) if literal.is_str() != value.is_str() => false,
+      Pattern::Literal(literal) => !self.binary(Operator::Equal, value, literal).0.is_zero(),
+      Pattern::Wildcard => true,
+    }
+  }
+}
+
+// Raises the base to a non-negative power, modulo the modulus.
+fn mod_pow(base: isize, mut exponent: isize, modulus: isize) -> isize {
+  let modulus = modulus as i128;
+  let mut base = (base as i128).rem_euclid(modulus);
+  let mut result = 1 % modulus;
+
+  while exponent > 0 {
+    if exponent & 1 == 1 {
+      result = result * base % modulus;
+    }
+
+    exponent >>= 1;
+    base = base * base % modulus;
+  }
+
+  result as isize
+}
+
+/// Applies a binary operator to both operands, returning `None` if the result overflows.
+pub fn checked_binary(op: Operator, lhs: &Value, rhs: &Value) -> Option<Value> {
+  match Arithmetic::default().binary(op, lhs, rhs) {
+    (value, false) => Some(value),
+    (_, true) => None,
+  }
+}
+
+/// Applies a unary operator to the operand, returning `None` if the result overflows.
+pub fn checked_unary(op: Operator, rhs: &Value) -> Option<Value> {
+  match Arithmetic::default().unary(op, rhs) {
+    (value, false) => Some(value),
+    (_, true) => None,
+  }
+}
+
+impl Iterator for Statements<'_> {
+  type Item = StatementResult;
+
+  fn next(&mut self) -> Option<StatementResult> {
+    let Interpreter {
+      src,
+      root,
+      functions,
+      arithmetic,
+      warnings,
+      operations,
+      output,
+      input,
+      capabilities,
+      flags,
+      max_call_depth,
+      ..
+    } = &mut *self.interpreter;
+    let statements = match &*root {
+      Node::Program(statements) => statements.as_slice(),
+      root => std::slice::from_ref(root),
+    };
+
+    let Some(statement) = statements.get(self.next) else {
+      // The variables are only kept if every statement was evaluated without errors
+      if let Some(variables) = self.variables.take().filter(|_| !self.failed) {
+        self.interpreter.constants = std::mem::take(&mut self.constants);
+        self.interpreter.structs = std::mem::take(&mut self.structs);
+        self.interpreter.commit(variables);
+      }
+
+      return None;
+    };
+
+    self.next += 1;
+
+    let variables = self.variables.as_mut()?;
+    let mut evaluator = Evaluator {
+      src,
+      variables,
+      constants: std::mem::take(&mut self.constants),
+      structs: std::mem::take(&mut self.structs),
+      functions,
+      scopes: Vec::new(),
+      ended: HashMap::new(),
+      arithmetic: *arithmetic,
+      errors: Vec::new(),
+      warnings: Vec::new(),
+      statement: None,
+      overflowed: false,
+      operations: 0,
+      output: &mut **output,
+      input: &mut **input,
+      capabilities: *capabilities,
+      flags,
+      jump: None,
+      depth: 0,
+      max_depth: *max_call_depth,
+    };
+    let value = evaluator.evaluate(statement);
+    let Evaluator {
+      mut errors,
+      warnings: statement_warnings,
+      operations: statement_operations,
+      variables,
+      constants,
+      structs,
+      ..
+    } = evaluator;
+
+    self.constants = constants;
+    self.structs = structs;
+
+    *operations += statement_operations;
+    warnings.extend(statement_warnings.iter().cloned());
+
+    let target = match statement {
+      Node::Assignment(..) | Node::ConstAssignment(..) => {
+        statement.assignment_chain().map(|(idents, _)| idents[0])
       }
+      Node::Read(_, ident) => Some(ident),
+      _ => None,
+    };
+    let value = match (target, statement) {
+      _ if !errors.is_empty() => None,
+      (Some(ident), _) => variables.get(&ident.literal).cloned(),
+      (None, Node::Print(..)) => Some(value),
+      _ => None,
+    };
+    let next_start = statements[self.next..].iter().find_map(Node::start);
+
+    self.failed |= !errors.is_empty();
+    errors.extend(statement_warnings);
+
+    Some(StatementResult {
+      span: statement_span(src, statement.start().unwrap_or(0), next_start),
+      target: target.map(|ident| ident.literal.clone()),
+      value,
+      diagnostics: errors,
+    })
+  }
+}
+
+// Returns the range of a statement, from where it starts to the end of its last token before the
+// next statement, if there is one.
+fn statement_span(src: &str, start: usize, next_start: Option<usize>) -> Range<usize> {
+  let end = next_start.unwrap_or(src.len());
+  let last = Lexer::new(&src[start..end])
+    .lex()
+    .into_iter()
+    .rev()
+    .find(|token| !matches!(token.kind(), TokenKind::EndOfFile));
+
+  start..last.map_or(start, |token| start + token.range().end)
+}
+
+// Lists the names of fields for diagnostics, eg `` `x`, `y` ``.
+fn field_list(fields: &[Rc<str>]) -> String {
+  fields
+    .iter()
+    .map(|field| format!("`{}`", field))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+// Reads a line from the input a byte at a time, so that nothing after it is consumed, returning
+// `None` at the end of the input.
+fn read_line(input: &mut dyn Read) -> io::Result<Option<String>> {
+  let mut line = Vec::new();
+  let mut byte = [0];
+
+  loop {
+    match input.read(&mut byte) {
+      Ok(0) if line.is_empty() => return Ok(None),
+      Ok(0) => break,
+      Ok(_) if byte[0] == b'\n' => break,
+      Ok(_) => line.push(byte[0]),
+      Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+      Err(e) => return Err(e),
     }
-    Node::Literal(lit) => lit.value,
+  }
+
+  Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+// The state needed while evaluating nodes.
+struct Evaluator<'a> {
+  src: &'a str,
+  variables: &'a mut HashMap<String, Value>,
+  // The constants declared so far, with the line of their declaration
+  constants: HashMap<String, usize>,
+  structs: Structs,
+  // The functions registered by the embedder
+  functions: &'a HashMap<String, HostFunction>,
+  // The variables of each block being run, innermost last, with the line each was first assigned
+  // on. These shadow `variables`, which are the program's own
+  scopes: Vec<HashMap<String, (Value, usize)>>,
+  // The variables of blocks that have ended, with the line each was first assigned on
+  ended: HashMap<String, usize>,
+  arithmetic: Arithmetic,
+  errors: Vec<DiagnosticError>,
+  warnings: Vec<DiagnosticError>,
+  // The statement being evaluated, for diagnostics that aren't tied to a single token
+  statement: Option<Statement>,
+  // Whether the current statement has already overflowed, so it's only reported once
+  overflowed: bool,
+  // The number of unary and binary operations evaluated
+  operations: usize,
+  // Where print statements write to
+  output: &'a mut dyn Write,
+  // Where read statements read from
+  input: &'a mut dyn Read,
+  // What the program is allowed to do, like reading from the input
+  capabilities: Capabilities,
+  // The flags that the statements marked with `#[if: FLAG]` are run for
+  flags: &'a [String],
+  // The `break` or `continue` being run, which skips the rest of the statements up to its loop
+  jump: Option<Jump>,
+  // The number of calls of functions made by lambdas being run
+  depth: usize,
+  max_depth: usize,
+}
+
+// How a loop is left early.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Jump {
+  Break,
+  Continue,
+}
+
+// Where a statement is, and how diagnostics describe it.
+struct Statement {
+  // eg "The assignment to `x`"
+  subject: String,
+  line: usize,
+  column: usize,
+}
+
+impl Evaluator<'_> {
+  fn evaluate(&mut self, node: &Node) -> Value {
+    match node {
+      Node::Program(nodes) => {
+        for node in nodes {
+          self.evaluate(node);
+        }
+
+        // Doesn't really matter what number return in this case
+        Value::Int(0)
+      }
+      // The variables first assigned in a block only exist until its end
+      Node::Block(nodes) => {
+        self.scopes.push(HashMap::new());
+
+        for node in nodes {
+          self.evaluate(node);
+
+          if self.jump.is_some() {
+            break;
+          }
+        }
+
+        for (name, (_, line)) in self.scopes.pop().unwrap() {
+          self.constants.remove(&name);
+          self.ended.insert(name, line);
+        }
+
+        Value::Int(0)
+      }
+      Node::BlockStatement(_, block) => self.evaluate(block),
+      Node::Assignment(var_node, expr) => {
+        // Identifiers are the only possible Node here
+        if let Node::Identifier(ident_node) = &**var_node {
+          self.statement = Some(Statement {
+            subject: format!("The assignment to `{}`", ident_node.literal),
+            line: ident_node.line,
+            column: ident_node.range.start + 1
+              - linebreak_index(self.src, ident_node.range.clone()),
+          });
+          self.overflowed = false;
+
+          if self.report_constant(ident_node) {
+            return Value::Int(0);
+          }
+
+          let rhs = self.arithmetic.normalize(self.evaluate(expr));
+          self.assign(ident_node, rhs.clone());
+
+          // A chained assignment, like the `b = 1` in `a = b = 1;`, has the value it assigns
+          return rhs;
+        }
+
+        Value::Int(0)
+      }
+      Node::ConstAssignment(_, ident_node, expr) => {
+        self.statement = Some(Statement {
+          subject: format!("The declaration of `{}`", ident_node.literal),
+          line: ident_node.line,
+          column: ident_node.range.start + 1 - linebreak_index(self.src, ident_node.range.clone()),
+        });
+        self.overflowed = false;
+
+        if self.report_constant(ident_node) {
+          return Value::Int(0);
+        }
+
+        let value = self.arithmetic.normalize(self.evaluate(expr));
+
+        // Unlike an assignment, a constant shadows any variable of an outer block
+        match self.scopes.last_mut() {
+          Some(scope) => {
+            scope.insert(ident_node.literal.clone(), (value, ident_node.line));
+          }
+          None => {
+            self.variables.insert(ident_node.literal.clone(), value);
+          }
+        }
+
+        self
+          .constants
+          .insert(ident_node.literal.clone(), ident_node.line);
+
+        Value::Int(0)
+      }
+      Node::MultiAssignment(idents, exprs) => {
+        // Every value is evaluated before any variable is set, so `a, b = b, a;` swaps them
+        let mut values = Vec::with_capacity(exprs.len());
+
+        for (ident_node, expr) in idents.iter().zip(exprs) {
+          self.statement = Some(Statement {
+            subject: format!("The assignment to `{}`", ident_node.literal),
+            line: ident_node.line,
+            column: ident_node.range.start + 1
+              - linebreak_index(self.src, ident_node.range.clone()),
+          });
+          self.overflowed = false;
+
+          values.push(self.arithmetic.normalize(self.evaluate(expr)));
+        }
+
+        for (ident_node, value) in idents.iter().zip(values) {
+          if !self.report_constant(ident_node) {
+            self.assign(ident_node, value);
+          }
+        }
+
+        Value::Int(0)
+      }
+      Node::Print(keyword, expr) => {
+        let line = keyword.line();
+        let column = keyword.range().start + 1 - linebreak_index(self.src, keyword.range());
+        self.statement = Some(Statement {
+          subject: "The print statement".to_string(),
+          line,
+          column,
+        });
+        self.overflowed = false;
+
+        let errors = self.errors.len();
+        let value = self.arithmetic.normalize(self.evaluate(expr));
+
+        // The value is meaningless if evaluating it failed
+        if self.errors.len() == errors {
+          // Strings are printed as their characters, rather than quoted
+          if let Err(e) = writeln!(self.output, "{}", value.text()) {
+            self.errors.push(DiagnosticError::new(
+              format!("The print statement couldn't write its output: {}.", e),
+              line,
+              column,
+            ));
+          }
+        }
+
+        // The printed value, which batch evaluation reports for the statement
+        value
+      }
+      Node::Read(keyword, ident) => {
+        let line = keyword.line();
+        let column = keyword.range().start + 1 - linebreak_index(self.src, keyword.range());
+
+        if self.report_constant(ident) {
+          return Value::Int(0);
+        }
+
+        // Nothing is read without the capability, so that the input is left as is
+        let msg = if !self.capabilities.is_granted(Capability::Read) {
+          format!(
+            "The capability `{}` is not granted, so `{}` can't be read into. it needs `--allow={}`.",
+            Capability::Read,
+            ident.literal,
+            Capability::Read
+          )
+        } else {
+          match read_line(self.input) {
+            Ok(Some(input)) => match input.trim().parse::<isize>() {
+              Ok(value) => {
+                let value = self.arithmetic.normalize(Value::Int(value));
+                self.assign(ident, value);
+
+                return Value::Int(0);
+              }
+              Err(_) => format!(
+                "The input read into `{}`, `{}`, isn't an integer in the range [{}, {}].",
+                ident.literal,
+                input.trim(),
+                isize::MIN,
+                isize::MAX
+              ),
+            },
+            Ok(None) => format!("There's no input left to read into `{}`.", ident.literal),
+            Err(e) => format!("The input for `{}` couldn't be read: {}.", ident.literal, e),
+          }
+        };
+
+        self.errors.push(DiagnosticError::new(msg, line, column));
+        // Like a failed assignment, this still sets the variable, so its uses aren't reported
+        self.assign(ident, Value::Int(0));
+
+        Value::Int(0)
+      }
+      Node::If(keyword, condition, then, otherwise) => {
+        self.statement = Some(Statement {
+          subject: "The condition of the `if`".to_string(),
+          line: keyword.line(),
+          column: keyword.range().start + 1 - linebreak_index(self.src, keyword.range()),
+        });
+        self.overflowed = false;
+
+        let errors = self.errors.len();
+        let condition = self.arithmetic.normalize(self.evaluate(condition));
+
+        // Neither block is run if the condition doesn't have a value
+        if self.errors.len() == errors {
+          if !condition.is_zero() {
+            self.evaluate(then);
+          } else if let Some(otherwise) = otherwise {
+            self.evaluate(otherwise);
+          }
+        }
+
+        Value::Int(0)
+      }
+      Node::For(keyword, init, condition, step, body) => {
+        if let Some(init) = init {
+          self.evaluate(init);
+        }
+
+        loop {
+          self.statement = Some(Statement {
+            subject: "The condition of the `for`".to_string(),
+            line: keyword.line(),
+            column: keyword.range().start + 1 - linebreak_index(self.src, keyword.range()),
+          });
+          self.overflowed = false;
+
+          let errors = self.errors.len();
+          let condition = self.arithmetic.normalize(self.evaluate(condition));
+
+          if self.errors.len() != errors || condition.is_zero() {
+            break;
+          }
+
+          self.evaluate(body);
+
+          // A `continue` still runs the step, but a `break` doesn't
+          if self.jump.take() == Some(Jump::Break) {
+            break;
+          }
+
+          if let Some(step) = step {
+            self.evaluate(step);
+          }
+
+          // An error would most likely be reported again on every later iteration
+          if self.errors.len() != errors {
+            break;
+          }
+        }
+
+        Value::Int(0)
+      }
+      // Statements whose flags weren't set are skipped, like they are by `filter_statements`
+      Node::Conditional(flag, statement) => {
+        if self.flags.contains(flag) {
+          self.evaluate(statement);
+        }
+
+        Value::Int(0)
+      }
+      Node::Expression(expr) => self.evaluate(expr),
+      Node::Term(lhs, Operator::Coalesce, rhs) => {
+        let mut operand = &**lhs;
+
+        while let Node::Expression(inner) | Node::Fact(inner) = operand {
+          operand = inner;
+        }
+
+        // An uninitialized variable on the left is `none` instead of an error, which is how a
+        // default is opted into, eg `x ?? 0`
+        let lhs = match operand {
+          Node::Identifier(ident) if self.variable(&ident.literal).is_none() => Value::None,
+          _ => self.evaluate(lhs),
+        };
+        self.operations += 1;
+
+        if !lhs.is_none() {
+          return lhs;
+        }
+
+        self.evaluate(rhs)
+      }
+      Node::Term(lhs, op, rhs) if op.is_logical() => {
+        let lhs = self.arithmetic.normalize(self.evaluate(lhs));
+        self.operations += 1;
+
+        // The right operand isn't evaluated at all, so it can't report any errors
+        if op.short_circuits(&lhs) {
+          return Value::Int(!lhs.is_zero() as isize);
+        }
+
+        let rhs = self.evaluate(rhs);
+
+        self.arithmetic.binary(*op, &lhs, &rhs).0
+      }
+      Node::Term(lhs, op, rhs) => {
+        let lhs = self.evaluate(lhs);
+        let rhs = self.evaluate(rhs);
+        self.operations += 1;
+
+        if !op.applies_to(&lhs, &rhs) {
+          self.report_mismatch(*op, &lhs, Some(&rhs));
+
+          return Value::Int(0);
+        }
+
+        if self.arithmetic.is_undefined(*op, &lhs, &rhs) {
+          self.report_undefined(*op, &rhs);
+
+          return Value::Int(0);
+        }
+
+        let (value, overflowed) = self.arithmetic.binary(*op, &lhs, &rhs);
+
+        if overflowed {
+          self.report_overflow(&value);
+        }
+
+        value
+      }
+      Node::Fact(fact) => self.evaluate(fact),
+      Node::UnaryOperator(op, rhs) => {
+        let rhs = self.evaluate(rhs);
+        self.operations += 1;
+
+        if !rhs.is_number() && !matches!(op, Operator::Not) {
+          self.report_mismatch(*op, &rhs, None);
+
+          return Value::Int(0);
+        }
+
+        let (value, overflowed) = self.arithmetic.unary(*op, &rhs);
+
+        if overflowed {
+          self.report_overflow(&value);
+        }
+
+        value
+      }
+      Node::Identifier(var_node) => {
+        match self.variable(&var_node.literal).cloned() {
+          Some(num) => num,
+          None => {
+            let node_range = var_node.range.clone();
+            let msg = match self.ended.get(&var_node.literal) {
+              Some(line) => format!(
+                "The identifier `{}` is out of scope. it was assigned on line {}, in a block that \
+                 has ended.",
+                &var_node.literal, line
+              ),
+              None => format!(
+                "The identifier `{}`, has not yet been initialized.",
+                &var_node.literal
+              ),
+            };
+
+            self.errors.push(DiagnosticError::new(
+              msg,
+              var_node.line,
+              node_range.start + 1 - linebreak_index(self.src, node_range),
+            ));
+
+            // Continue recursing to handle multiple errors at once
+            Value::Int(0)
+          }
+        }
+      }
+      Node::Literal(lit) => {
+        if let (Value::Float(_), Some(modulus)) = (&lit.value, self.arithmetic.modulus) {
+          self.report_float_modulus(modulus);
+        }
+
+        lit.value.clone()
+      }
+      Node::Let(ident_node, value, body) => {
+        let value = self.arithmetic.normalize(self.evaluate(value));
+        let binding = (ident_node.literal.clone(), (value, ident_node.line));
+
+        // The binding only exists within the body
+        self.scopes.push(HashMap::from([binding]));
+        let result = self.evaluate(body);
+        self.scopes.pop();
+
+        result
+      }
+      Node::Match(keyword, value, arms) => {
+        let value = self.arithmetic.normalize(self.evaluate(value));
+
+        for (pattern, arm) in arms {
+          if let (Pattern::Literal(Value::Float(_)), Some(modulus)) =
+            (pattern, self.arithmetic.modulus)
+          {
+            self.report_float_modulus(modulus);
+          }
+
+          if self.arithmetic.matches(pattern, &value) {
+            return self.evaluate(arm);
+          }
+        }
+
+        // Expressions can only be evaluated within a statement
+        let statement = self.statement.as_ref().unwrap();
+
+        self.errors.push(DiagnosticError::new(
+          format!("{} has no `match` arm for `{}`.", statement.subject, value),
+          keyword.line(),
+          keyword.range().start + 1 - linebreak_index(self.src, keyword.range()),
+        ));
+
+        Value::Int(0)
+      }
+      Node::Interpolation(segments) => {
+        let mut text = String::new();
+
+        for segment in segments {
+          match segment {
+            Segment::Text(segment) => text.push_str(segment),
+            Segment::Expression(expr) => {
+              let value = self.arithmetic.normalize(self.evaluate(expr));
+
+              text.push_str(&value.text());
+            }
+          }
+        }
+
+        Value::Str(text.into())
+      }
+      Node::Array(elements) => Value::Array(
+        elements
+          .iter()
+          .map(|element| {
+            let value = self.evaluate(element);
+
+            self.arithmetic.normalize(value)
+          })
+          .collect(),
+      ),
+      Node::Tuple(elements) => Value::Tuple(
+        elements
+          .iter()
+          .map(|element| {
+            let value = self.evaluate(element);
+
+            self.arithmetic.normalize(value)
+          })
+          .collect(),
+      ),
+      Node::Field(dot, tuple, index) => {
+        let errors = self.errors.len();
+        let tuple = self.evaluate(tuple);
+
+        // The tuple not having a value was already reported
+        if self.errors.len() != errors {
+          return Value::Int(0);
+        }
+
+        if let Some(value) = tuple.field(*index) {
+          return value.clone();
+        }
+
+        // Expressions can only be evaluated within a statement
+        let subject = &self.statement.as_ref().unwrap().subject;
+        let msg = match &tuple {
+          Value::Tuple(values) => format!(
+            "{} accesses `.{}` out of range of a tuple of length {}.",
+            subject,
+            index,
+            values.len()
+          ),
+          _ => format!(
+            "{} accesses `.{}` of {}. only tuples have fields.",
+            subject,
+            index,
+            tuple.kind()
+          ),
+        };
+
+        self.errors.push(DiagnosticError::new(
+          msg,
+          dot.line(),
+          dot.range().start + 1 - linebreak_index(self.src, dot.range()),
+        ));
+
+        Value::Int(0)
+      }
+      Node::StructDeclaration(keyword, name, fields) => {
+        self.statement = Some(Statement {
+          subject: format!("The declaration of the struct `{}`", name.literal),
+          line: keyword.line(),
+          column: keyword.range().start + 1 - linebreak_index(self.src, keyword.range()),
+        });
+
+        if let Some(&(_, line)) = self.structs.get(&name.literal) {
+          self.report_at(
+            format!(
+              "The struct `{}` is already declared on line {}.",
+              name.literal, line
+            ),
+            name,
+          );
+
+          return Value::Int(0);
+        }
+
+        let fields = fields
+          .iter()
+          .map(|field| field.literal.as_str().into())
+          .collect();
+        self
+          .structs
+          .insert(name.literal.clone(), (fields, name.line));
+
+        Value::Int(0)
+      }
+      Node::Struct(name, fields) => {
+        let Some((declared, _)) = self.structs.get(&name.literal).cloned() else {
+          self.report_at(
+            format!("The struct `{}` isn't declared.", name.literal),
+            name,
+          );
+
+          return Value::Int(0);
+        };
+
+        let errors = self.errors.len();
+        let mut values = vec![None; declared.len()];
+
+        for (field, expr) in fields {
+          let value = self.evaluate(expr);
+
+          match declared.iter().position(|name| **name == field.literal) {
+            Some(i) if values[i].is_some() => self.report_at(
+              format!(
+                "The field `{}` of `{}` is given more than once.",
+                field.literal, name.literal
+              ),
+              field,
+            ),
+            Some(i) => values[i] = Some(value),
+            None => self.report_at(
+              format!(
+                "`{}` has no field `{}`. its fields are {}.",
+                name.literal,
+                field.literal,
+                field_list(&declared)
+              ),
+              field,
+            ),
+          }
+        }
+
+        let missing = declared
+          .iter()
+          .zip(&values)
+          .filter(|(_, value)| value.is_none())
+          .map(|(field, _)| field.clone())
+          .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+          self.report_at(
+            format!(
+              "The `{}` is missing a value for {}. every field needs one.",
+              name.literal,
+              field_list(&missing)
+            ),
+            name,
+          );
+        }
+
+        if self.errors.len() != errors {
+          return Value::Int(0);
+        }
+
+        Value::Struct(
+          name.literal.as_str().into(),
+          declared
+            .iter()
+            .cloned()
+            .zip(values.into_iter().flatten())
+            .collect(),
+        )
+      }
+      Node::NamedField(dot, value, field) => {
+        let errors = self.errors.len();
+        let value = self.evaluate(value);
+
+        // The struct not having a value was already reported
+        if self.errors.len() != errors {
+          return Value::Int(0);
+        }
+
+        if let Some(value) = value.named_field(&field.literal) {
+          return value.clone();
+        }
+
+        // Expressions can only be evaluated within a statement
+        let subject = &self.statement.as_ref().unwrap().subject;
+        let msg = match &value {
+          Value::Struct(name, fields) => format!(
+            "{} accesses `.{}` of a `{}`, which has no such field. its fields are {}.",
+            subject,
+            field.literal,
+            name,
+            field_list(
+              &fields
+                .iter()
+                .map(|(field, _)| field.clone())
+                .collect::<Vec<_>>()
+            )
+          ),
+          _ => format!(
+            "{} accesses `.{}` of {}. only structs have named fields.",
+            subject,
+            field.literal,
+            value.kind()
+          ),
+        };
+
+        self.errors.push(DiagnosticError::new(
+          msg,
+          dot.line(),
+          dot.range().start + 1 - linebreak_index(self.src, dot.range()),
+        ));
+
+        Value::Int(0)
+      }
+      Node::Index(bracket, array, index) => {
+        let errors = self.errors.len();
+        let array = self.evaluate(array);
+        let index = self.arithmetic.normalize(self.evaluate(index));
+
+        // Either side not having a value was already reported
+        if self.errors.len() != errors {
+          return Value::Int(0);
+        }
+
+        if let Some(value) = array.element(&index) {
+          return value.clone();
+        }
+
+        // Expressions can only be evaluated within a statement
+        let subject = &self.statement.as_ref().unwrap().subject;
+        let msg = match (&array, &index) {
+          (Value::Array(values), Value::Int(index)) => format!(
+            "{} indexes `{}` out of bounds of an array of length {}.",
+            subject,
+            index,
+            values.len()
+          ),
+          (Value::Array(_), _) => format!(
+            "{} indexes an array with {}. indices must be integers.",
+            subject,
+            index.kind()
+          ),
+          _ => format!(
+            "{} indexes into {}. only arrays can be indexed.",
+            subject,
+            array.kind()
+          ),
+        };
+
+        self.errors.push(DiagnosticError::new(
+          msg,
+          bracket.line(),
+          bracket.range().start + 1 - linebreak_index(self.src, bracket.range()),
+        ));
+
+        Value::Int(0)
+      }
+      Node::Lambda(_, params, body) => {
+        // The innermost value of each variable is kept, since later blocks shadow earlier ones
+        let mut captured = HashMap::new();
+
+        for scope in &self.scopes {
+          for (name, (value, _)) in scope {
+            captured.insert(name.clone(), value.clone());
+          }
+        }
+
+        Value::Function(Rc::new(Function {
+          params: params.clone(),
+          body: (**body).clone(),
+          captured: captured.into_iter().collect(),
+        }))
+      }
+      Node::Call(ident, args, _) => {
+        // A variable holding a function replaces any other function of the same name
+        if let Some(Value::Function(function)) = self.variable(&ident.literal) {
+          let function = Rc::clone(function);
+
+          return self.call(ident, &function, args);
+        }
+
+        let functions = self.functions;
+
+        // A registered function replaces the built-in one of the same name
+        if let Some(function) = functions.get(&ident.literal) {
+          let args = args
+            .iter()
+            .map(|arg| self.evaluate(arg))
+            .collect::<Vec<_>>();
+          self.operations += 1;
+
+          return match function(&args) {
+            Ok(value) => self.arithmetic.normalize(value),
+            Err(err) => {
+              // Calls can only happen within a statement
+              let statement = self.statement.as_ref().unwrap();
+
+              self.errors.push(DiagnosticError::new(
+                format!(
+                  "{} calls `{}`, which failed with `{}`.",
+                  statement.subject, ident.literal, err
+                ),
+                statement.line,
+                statement.column,
+              ));
+
+              Value::Int(0)
+            }
+          };
+        }
+
+        let Some(builtin) = Builtin::from_name(&ident.literal) else {
+          if let Some(value) = self.variable(&ident.literal) {
+            self.report_at(
+              format!(
+                "`{}` can't be called, since it's {}. only functions can be called.",
+                ident.literal,
+                value.kind()
+              ),
+              ident,
+            );
+
+            return Value::Int(0);
+          }
+
+          let mut registered = functions
+            .keys()
+            .filter(|name| Builtin::from_name(name).is_none())
+            .collect::<Vec<_>>();
+          registered.sort();
+
+          let names = Builtin::ALL
+            .iter()
+            .map(|builtin| builtin.name())
+            .chain(registered.into_iter().map(String::as_str))
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>();
+
+          self.report_at(
+            format!(
+              "The function `{}` doesn't exist. the built-in functions are {}, and {}.",
+              ident.literal,
+              names[..names.len() - 1].join(", "),
+              names[names.len() - 1]
+            ),
+            ident,
+          );
+
+          return Value::Int(0);
+        };
+        let args = args
+          .iter()
+          .map(|arg| self.evaluate(arg))
+          .collect::<Vec<_>>();
+
+        if args.len() != builtin.arity() {
+          self.report_at(
+            format!(
+              "The call to `{}` passes {} argument(s), but it takes {}.",
+              builtin,
+              args.len(),
+              builtin.arity()
+            ),
+            ident,
+          );
+
+          return Value::Int(0);
+        }
+
+        self.operations += 1;
+
+        if !builtin.applies_to(&args) {
+          // Calls can only happen within a statement
+          let statement = self.statement.as_ref().unwrap();
+          let kinds = args.iter().map(Value::kind).collect::<Vec<_>>();
+
+          self.errors.push(DiagnosticError::new(
+            format!(
+              "{} calls `{}` with {}. {}.",
+              statement.subject,
+              builtin,
+              kinds.join(" and "),
+              builtin.rule()
+            ),
+            statement.line,
+            statement.column,
+          ));
+
+          return Value::Int(0);
+        }
+
+        // Only `pow` can be undefined, for the same exponents as `**`
+        if builtin.is_undefined_for(&args) {
+          self.report_undefined(Operator::Power, &args[1]);
+
+          return Value::Int(0);
+        }
+
+        let (value, overflowed) = self.arithmetic.call(builtin, &args);
+
+        if overflowed {
+          self.report_overflow(&value);
+        }
+
+        value
+      }
+      Node::Break(_) => {
+        self.jump = Some(Jump::Break);
+
+        Value::Int(0)
+      }
+      Node::Continue(_) => {
+        self.jump = Some(Jump::Continue);
+
+        Value::Int(0)
+      }
+      Node::Return(keyword, _) => {
+        self.errors.push(DiagnosticError::new(
+          "The return statement isn't in a function.".to_string(),
+          keyword.line(),
+          keyword.range().start + 1 - linebreak_index(self.src, keyword.range()),
+        ));
+
+        Value::Int(0)
+      }
+      // Imports are run by whatever loaded the program, which splits it around them
+      Node::Import(keyword, path) => {
+        self.errors.push(DiagnosticError::new(
+          format!(
+            "The import of `{}` can't be run here. only a program run from a file can import \
+             others.",
+            path
+          ),
+          keyword.line(),
+          keyword.range().start + 1 - linebreak_index(self.src, keyword.range()),
+        ));
+
+        Value::Int(0)
+      }
+    }
+  }
+
+  // Calls a function made by a lambda, evaluating its body with its parameters bound to the
+  // arguments. Only the variables that it captured and the program's own are visible within it.
+  fn call(&mut self, ident: &IdentifierNode, function: &Function, args: &[Node]) -> Value {
+    let args = args
+      .iter()
+      .map(|arg| self.arithmetic.normalize(self.evaluate(arg)))
+      .collect::<Vec<_>>();
+
+    if args.len() != function.params.len() {
+      self.report_at(
+        format!(
+          "The call to `{}` passes {} argument(s), but it takes {}.",
+          ident.literal,
+          args.len(),
+          function.params.len()
+        ),
+        ident,
+      );
+
+      return Value::Int(0);
+    }
+
+    if self.depth >= self.max_depth {
+      // Calls can only happen within a statement
+      let statement = self.statement.as_ref().unwrap();
+
+      self.errors.push(DiagnosticError::new(
+        format!(
+          "{} calls `{}` too deeply. calls can only be nested {} deep.",
+          statement.subject, ident.literal, self.max_depth
+        ),
+        statement.line,
+        statement.column,
+      ));
+
+      return Value::Int(0);
+    }
+
+    let mut scope = function
+      .captured
+      .iter()
+      .map(|(name, value)| (name.clone(), (value.clone(), ident.line)))
+      .collect::<HashMap<_, _>>();
+
+    for (param, arg) in function.params.iter().zip(args) {
+      scope.insert(param.literal.clone(), (arg, param.line));
+    }
+
+    // The blocks of the caller aren't visible within the body
+    let scopes = std::mem::replace(&mut self.scopes, vec![scope]);
+    self.operations += 1;
+    self.depth += 1;
+    let value = self.evaluate(&function.body);
+    self.depth -= 1;
+    self.scopes = scopes;
+
+    value
+  }
+
+  // Returns the value of the variable in the innermost block that has it.
+  fn variable(&self, name: &str) -> Option<&Value> {
+    self
+      .scopes
+      .iter()
+      .rev()
+      .find_map(|scope| scope.get(name).map(|(value, _)| value))
+      .or_else(|| self.variables.get(name))
+  }
+
+  // Sets the variable in the innermost block that has it, or first assigns it in the innermost
+  // block being run if none does.
+  fn assign(&mut self, ident: &IdentifierNode, value: Value) {
+    let name = &ident.literal;
+
+    if let Some((slot, _)) = self
+      .scopes
+      .iter_mut()
+      .rev()
+      .find_map(|scope| scope.get_mut(name))
+    {
+      *slot = value;
+      return;
+    }
+
+    match self.scopes.last_mut() {
+      Some(scope) if !self.variables.contains_key(name) => {
+        scope.insert(name.clone(), (value, ident.line));
+      }
+      _ => {
+        self.variables.insert(name.clone(), value);
+      }
+    }
+  }
+
+  // Reports an assignment to a constant, returning whether the variable is one.
+  fn report_constant(&mut self, ident: &IdentifierNode) -> bool {
+    let Some(&line) = self.constants.get(&ident.literal) else {
+      return false;
+    };
+
+    self.errors.push(DiagnosticError::new(
+      format!(
+        "`{}` can't be assigned, since it's a constant. it's declared on line {}.",
+        ident.literal, line
+      ),
+      ident.line,
+      ident.range.start + 1 - linebreak_index(self.src, ident.range.clone()),
+    ));
+
+    true
+  }
+
+  // Reports an error at the identifier.
+  fn report_at(&mut self, msg: String, ident: &IdentifierNode) {
+    self.errors.push(DiagnosticError::new(
+      msg,
+      ident.line,
+      ident.range.start + 1 - linebreak_index(self.src, ident.range.clone()),
+    ));
+  }
+
+  // Reports that the current statement applies an operator to a value other than a number that
+  // it doesn't apply to, like `"a" * 2`. Unary operators don't have a right operand.
+  fn report_mismatch(&mut self, op: Operator, lhs: &Value, rhs: Option<&Value>) {
+    // Operations can only happen within a statement
+    let statement = self.statement.as_ref().unwrap();
+    let operands = match rhs {
+      Some(rhs) => format!("{} and {}", lhs.kind(), rhs.kind()),
+      None => lhs.kind().to_string(),
+    };
+    let rule = operator_rule(lhs, rhs);
+
+    self.errors.push(DiagnosticError::new(
+      format!(
+        "{} applies `{}` to {}. {}.",
+        statement.subject, op, operands, rule
+      ),
+      statement.line,
+      statement.column,
+    ));
+  }
+
+  // Reports that the current statement has an operation without a value, like dividing by zero.
+  fn report_undefined(&mut self, op: Operator, rhs: &Value) {
+    // Operations can only happen within a statement
+    let statement = self.statement.as_ref().unwrap();
+    let msg = match op {
+      Operator::Modulo => format!(
+        "{} takes the remainder of dividing by zero.",
+        statement.subject
+      ),
+      Operator::Power => format!(
+        "{} raises to a negative power, `{}`.",
+        statement.subject, rhs
+      ),
+      Operator::ShiftLeft | Operator::ShiftRight => format!(
+        "{} shifts by `{}`, which isn't an integer in the range [0, {}].",
+        statement.subject,
+        rhs,
+        isize::BITS - 1
+      ),
+      _ => format!("{} divides by zero.", statement.subject),
+    };
+
+    self
+      .errors
+      .push(DiagnosticError::new(msg, statement.line, statement.column));
+  }
+
+  // Reports that the current statement uses a float, which can't be reduced by the modulus.
+  fn report_float_modulus(&mut self, modulus: isize) {
+    // Literals can only be evaluated within a statement
+    let statement = self.statement.as_ref().unwrap();
+
+    self.errors.push(DiagnosticError::new(
+      format!(
+        "{} uses a float, which can't be reduced modulo `{}`.",
+        statement.subject, modulus
+      ),
+      statement.line,
+      statement.column,
+    ));
+  }
+
+  // Reports the first overflow of the current statement, according to the overflow mode.
+  fn report_overflow(&mut self, value: &Value) {
+    let is_float = matches!(value, Value::Float(_));
+
+    // Floats can't wrap, so they're always reported
+    if self.overflowed || (!is_float && matches!(self.arithmetic.overflow, Overflow::Wrapping)) {
+      return;
+    }
+
+    self.overflowed = true;
+
+    // Overflows can only happen within a statement
+    let statement = self.statement.as_ref().unwrap();
+
+    if is_float {
+      self.errors.push(DiagnosticError::new(
+        format!("{} doesn't have a finite value.", statement.subject),
+        statement.line,
+        statement.column,
+      ));
+    } else if matches!(self.arithmetic.overflow, Overflow::Saturating) {
+      self.warnings.push(DiagnosticError::warning(
+        format!(
+          "{} overflowed, so the result was clamped to `{}`.",
+          statement.subject, value
+        ),
+        statement.line,
+        statement.column,
+      ));
+    } else {
+      self.errors.push(DiagnosticError::new(
+        format!(
+          "{} overflows. integers must be in the range [{}, {}].",
+          statement.subject,
+          isize::MIN,
+          isize::MAX
+        ),
+        statement.line,
+        statement.column,
+      ));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    node::{IdentifierNode, LiteralNode},
+    parser::Parser,
+    token::{Token, TokenKind},
+  };
+
+  #[test]
+  fn load_keeps_variables() {
+    let mut interpreter = Interpreter::new("", Node::Program(Vec::new()));
+
+    for (src, result) in [
+      ("x = 5;", true),
+      ("y = z + x;", false),
+      ("y = x * 2;", true),
+    ] {
+      interpreter.load(src, Parser::new(src).parse().unwrap());
+
+      assert_eq!(interpreter.evaluate().is_ok(), result);
+    }
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(5)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(10)));
+  }
+
+  #[test]
+  fn conditional_statements() {
+    let src =
+      "#[if: A] x = 1;\n#[if: B] y = 2;\nz = 0;\nif (1) { #[if: A] #[if: B] z = 3; }\nw = 4;";
+    let evaluate = |flags: &[String]| {
+      let mut interpreter =
+        Interpreter::new(src, Parser::new(src).parse().unwrap()).with_flags(flags);
+      interpreter.evaluate().unwrap();
+
+      ["x", "y", "z", "w"].map(|name| interpreter.variable(name))
+    };
+
+    assert_eq!(
+      evaluate(&[]),
+      [None, None, Some(Value::Int(0)), Some(Value::Int(4))]
+    );
+    assert_eq!(
+      evaluate(&["A".to_string()]),
+      [
+        Some(Value::Int(1)),
+        None,
+        Some(Value::Int(0)),
+        Some(Value::Int(4))
+      ]
+    );
+    assert_eq!(
+      evaluate(&["A".to_string(), "B".to_string()]),
+      [
+        Some(Value::Int(1)),
+        Some(Value::Int(2)),
+        Some(Value::Int(3)),
+        Some(Value::Int(4))
+      ]
+    );
+  }
+
+  #[test]
+  fn dump_is_deterministic() {
+    let src = "d = 1;\nc = 2;\nif (c) { b = 3; }\nlet_ = let a = 1 in a;\nb = 4;";
+    let dumps = (0..32)
+      .map(|_| {
+        let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+        interpreter.evaluate().unwrap();
+        interpreter.load("a = 5;", Parser::new("a = 5;").parse().unwrap());
+        interpreter.evaluate().unwrap();
+
+        let mut out = Vec::new();
+        interpreter.dump(&mut out, false).unwrap();
+
+        String::from_utf8(out).unwrap()
+      })
+      .collect::<Vec<_>>();
+
+    assert!(dumps.iter().all(|dump| *dump == dumps[0]));
+    assert_eq!(dumps[0], "d => 1\nc => 2\nb => 4\nlet_ => 1\na => 5\n");
+  }
+
+  #[test]
+  fn division_by_zero() {
+    let src = "x = 7 / 2;\ny = -7 / 2;\nz = x / (y + 3);";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!((errors[0].line(), errors[0].column()), (3, 1));
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `z` divides by zero."
+    );
+  }
+
+  #[test]
+  fn power() {
+    let src = "x = (-3) ** 3;\ny = 2 ** 62 * 2;\nz = 2 ** -1;";
+    let mut interpreter =
+      Interpreter::new(src, Parser::new(src).parse().unwrap()).with_overflow(Overflow::Saturating);
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `z` raises to a negative power, `-1`."
+    );
+    assert_eq!(
+      Overflow::Checked.binary(Operator::Power, -3, 3),
+      (-27, false)
+    );
+    assert_eq!(
+      Overflow::Saturating.binary(Operator::Power, -3, 41),
+      (isize::MIN, true)
+    );
+
+    // Floats can be raised to negative powers
+    let src = "x = 2.0 ** -2;\ny = pow(0.5, -1);";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Float(0.25)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Float(2.0)));
+  }
+
+  #[test]
+  fn shifts() {
+    let src = "x = 1 << 4 + 1;\ny = -9 >> 1;\nz = 1.5 << 2;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(32)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(-5)));
+    assert_eq!(interpreter.variable("z"), Some(Value::Float(6.0)));
+
+    let src = "x = 1 << -1;\ny = 1 >> 64;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `x` shifts by `-1`, which isn't an integer in the range [0, 63]."
+    );
+    assert_eq!(
+      errors[1].to_string(),
+      "The assignment to `y` shifts by `64`, which isn't an integer in the range [0, 63]."
+    );
+    assert_eq!(
+      Overflow::Checked.binary(Operator::ShiftLeft, 1, 63),
+      (isize::MIN, true)
+    );
+    assert_eq!(
+      Overflow::Saturating.binary(Operator::ShiftLeft, -3, 62),
+      (isize::MIN, true)
+    );
+  }
+
+  #[test]
+  fn modulo() {
+    let src = "x = -7 % 3;\ny = 7 % -3;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(-1)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(1)));
+
+    let src = "x = 1 % 0;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+
+    assert_eq!(
+      interpreter.evaluate().unwrap_err()[0].to_string(),
+      "The assignment to `x` takes the remainder of dividing by zero."
+    );
+  }
+
+  #[test]
+  fn modular_division() {
+    // The operands are reduced before dividing, whether they're literals or variables
+    let src = "a = 10;\nx = 10 / 3;\ny = a / 3;\nz = 3 / 10;";
+    let mut interpreter =
+      Interpreter::new(src, Parser::new(src).parse().unwrap()).with_modulus(Some(7));
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("z"), Some(Value::Int(1)));
+
+    // A multiple of the modulus is zero
+    let src = "x = 1 / 7;\ny = 1 / -14;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `x` divides by zero."
+    );
+    assert_eq!(
+      errors[1].to_string(),
+      "The assignment to `y` divides by zero."
+    );
+
+    // So is the remainder
+    let src = "a = 10;\nx = 10 % 4;\ny = a % 4;\nz = 4 % 10;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(3)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(3)));
+    assert_eq!(interpreter.variable("z"), Some(Value::Int(1)));
+
+    let src = "b = 3;\nx = b % 7;\ny = 1 % -14;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `x` takes the remainder of dividing by zero."
+    );
+    assert_eq!(
+      errors[1].to_string(),
+      "The assignment to `y` takes the remainder of dividing by zero."
+    );
+  }
+
+  #[test]
+  fn overflow_modes() {
+    let (max, min) = (isize::MAX, isize::MIN);
+
+    for (op, lhs, rhs, wrapped, saturated) in [
+      (Operator::Plus, max, 1, min, max),
+      (Operator::Plus, min, -1, max, min),
+      (Operator::Minus, min, 1, max, min),
+      (Operator::Minus, max, -1, min, max),
+      (Operator::Multiply, max, 2, -2, max),
+      (Operator::Multiply, min, -1, min, max),
+      (Operator::Multiply, min, 2, 0, min),
+      (Operator::Divide, min, -1, min, max),
+    ] {
+      // Checked arithmetic wraps too, but the overflow is reported instead of the value
+      assert_eq!(Overflow::Checked.binary(op, lhs, rhs), (wrapped, true));
+      assert_eq!(Overflow::Wrapping.binary(op, lhs, rhs), (wrapped, true));
+      assert_eq!(Overflow::Saturating.binary(op, lhs, rhs), (saturated, true));
+    }
+
+    // Operations that stay within the bounds don't overflow in any mode
+    for overflow in [Overflow::Checked, Overflow::Wrapping, Overflow::Saturating] {
+      assert_eq!(overflow.binary(Operator::Minus, max, max), (0, false));
+      assert_eq!(overflow.binary(Operator::Plus, min, max), (-1, false));
+      assert_eq!(overflow.binary(Operator::Modulo, min, -1), (0, false));
+      assert_eq!(overflow.unary(Operator::Minus, max), (-max, false));
+    }
+
+    assert!(Overflow::Checked.unary(Operator::Minus, min).1);
+    assert_eq!(Overflow::Wrapping.unary(Operator::Minus, min), (min, true));
+    assert_eq!(
+      Overflow::Saturating.unary(Operator::Minus, min),
+      (max, true)
+    );
+
+    // With a modulus, the overflow mode doesn't matter, since nothing overflows
+    for overflow in [Overflow::Checked, Overflow::Wrapping, Overflow::Saturating] {
+      let arithmetic = Arithmetic {
+        overflow,
+        modulus: Some(7),
+      };
+      let int = |value: isize| Value::Int(value);
+
+      assert_eq!(
+        arithmetic.binary(Operator::Plus, &int(max), &int(1)),
+        (int(((max as i128 + 1) % 7) as isize), false)
+      );
+      assert_eq!(
+        arithmetic.binary(Operator::Multiply, &int(min), &int(-1)),
+        (int((-(min as i128)).rem_euclid(7) as isize), false)
+      );
+      assert_eq!(
+        arithmetic.unary(Operator::Minus, &int(min)),
+        (int((-(min as i128)).rem_euclid(7) as isize), false)
+      );
+    }
+
+    // The modes decide whether a program that overflows fails, wraps, or saturates with a warning
+    let src = "x = 9223372036854775807 + 1;\ny = -x;";
+    let run = |overflow| {
+      let mut interpreter =
+        Interpreter::new(src, Parser::new(src).parse().unwrap()).with_overflow(overflow);
+      let result = interpreter.evaluate();
+
+      (result, interpreter)
+    };
+
+    let (result, _) = run(Overflow::Checked);
+
+    assert!(result.unwrap_err()[0].to_string().contains("overflows"));
+
+    let (result, interpreter) = run(Overflow::Wrapping);
+    result.unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(min)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(min)));
+
+    let (result, interpreter) = run(Overflow::Saturating);
+    result.unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(max)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(-max)));
+    assert_eq!(interpreter.warnings().len(), 1);
+  }
+
+  // A writer whose contents can still be read after it's been given to an interpreter.
+  #[derive(Clone, Default)]
+  struct SharedBuffer(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+  impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn print() {
+    let src = "x = 2;\nprint x * 3;\nprint x / 0;\nprint 1.5;";
+    let buffer = SharedBuffer::default();
+    let mut interpreter =
+      Interpreter::new(src, Parser::new(src).parse().unwrap()).with_output(buffer.clone());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The print statement divides by zero."
+    );
+    assert_eq!((errors[0].line(), errors[0].column()), (3, 1));
+    assert_eq!(&*buffer.0.borrow(), b"6\n1.5\n");
+  }
+
+  #[test]
+  fn read() {
+    let read = Capabilities::default().with(Capability::Read);
+    let src = "read x;\ny = x * 2;\nread z;\nread w;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap())
+      .with_input(&b"21\n2.5\n"[..])
+      .with_capabilities(read);
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!((errors[0].line(), errors[0].column()), (3, 1));
+    assert_eq!(
+      errors[1].to_string(),
+      "There's no input left to read into `w`."
+    );
+
+    let src = "read x;\ny = x * 2;";
+    interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap())
+      .with_input(&b"21"[..])
+      .with_capabilities(read);
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(42)));
+
+    // Reading needs the capability, even with an input
+    interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap()).with_input(&b"21"[..]);
+
+    assert_eq!(
+      interpreter.evaluate().unwrap_err()[0].to_string(),
+      "The capability `read` is not granted, so `x` can't be read into. it needs `--allow=read`."
+    );
+  }
+
+  #[test]
+  fn if_statements() {
+    let src = "x = 0.0;\nif (x) { y = 1; } else { y = 2; }\nif (1 / x) { z = 1; }";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The condition of the `if` divides by zero."
+    );
+
+    // `y` has to exist before the `if`, or it would only exist within the block
+    let src = "x = 0.0;\ny = 0;\nif (x) { y = 1; } else { y = 2; }";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(2)));
+  }
+
+  #[test]
+  fn for_loops() {
+    let src = "x = 0;\nfor (i = 4; i; i = i - 1) { x = x + i; }\nfor (; 1 / x;) { }";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(10)));
+    assert_eq!(interpreter.variable("i"), Some(Value::Int(0)));
+
+    // The loop stops at the first error, rather than reporting it forever
+    let src = "for (i = 1; i; i = i + 1) { y = z; }";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+  }
+
+  #[test]
+  fn constants() {
+    let src = "const PI = 3;\nx = PI * 2;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(6)));
+
+    // Constants stay constant in later programs too
+    let src = "PI = 4;\nfor (i = 0; i < 1; i++) { PI++; }\na, PI = 1, 2;\nconst PI = 5;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter
+      .evaluate()
+      .unwrap_err()
+      .iter()
+      .map(|err| (err.line(), err.column()))
+      .collect::<Vec<_>>();
+
+    assert_eq!(errors, vec![(1, 1), (2, 27), (3, 4), (4, 7)]);
+    assert_eq!(interpreter.variable("PI"), Some(Value::Int(3)));
+  }
+
+  #[test]
+  fn block_scopes() {
+    let src = "x = 1;\n{\n  x = 2;\n  y = 3;\n  const x = 4;\n  z = x + y;\n}\nw = x;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    // Only the variable that existed before the block is still set, to its value before the
+    // constant shadowed it
+    assert_eq!(interpreter.variable("w"), Some(Value::Int(2)));
+    assert_eq!(interpreter.variable("y"), None);
+    assert_eq!(interpreter.variable("z"), None);
+
+    let src = "if (1) { y = 1; }\nv = y;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The identifier `y` is out of scope. it was assigned on line 1, in a block that has ended."
+    );
+  }
+
+  #[test]
+  fn break_and_continue() {
+    let src = "x = 0;\nfor (i = 0; i < 10; i++) { if (i % 2) { continue; } if (i == 6) { break; } x = x + i; }";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(6)));
+    assert_eq!(interpreter.variable("i"), Some(Value::Int(6)));
+  }
+
+  #[test]
+  fn none_and_coalescing() {
+    let src = "a = none;\nb = a ?? 1;\nc = u ?? 2;\nd = 3 ?? v;\ne = a == none;\nf = (a ?? 4) + 1;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("a"), Some(Value::None));
+    assert_eq!(interpreter.variable("b"), Some(Value::Int(1)));
+    // Only the left operand of `??` can be uninitialized, and the right one isn't evaluated if
+    // the left one isn't `none`
+    assert_eq!(interpreter.variable("c"), Some(Value::Int(2)));
+    assert_eq!(interpreter.variable("d"), Some(Value::Int(3)));
+    assert_eq!(interpreter.variable("e"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("f"), Some(Value::Int(5)));
+
+    let src = "x = none;\ny = x + 1;\nz = w ?? w;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0]
+      .to_string()
+      .contains("applies `+` to none and an integer"));
+  }
+
+  #[test]
+  fn structs() {
+    let src =
+      "struct Point { x, y }\np = Point { y: 2, x: 1 };\nx = p.x;\ne = p == Point { x: 1, y: 2 };";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("e"), Some(Value::Int(1)));
+    assert_eq!(
+      interpreter.variable("p").unwrap().to_string(),
+      "Point { x: 1, y: 2 }"
+    );
+
+    // The declaration outlives the program that made it
+    let src = "a = Point { x: 1 };\nb = p.z;\nc = Line { a: 1 };\nstruct Point { x }";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(errors.len(), 4);
+    assert!(errors[0].to_string().contains("missing a value for `y`"));
+    assert!(errors[1].to_string().contains("accesses `.z` of a `Point`"));
+    assert!(errors[2].to_string().contains("`Line` isn't declared"));
+    assert!(errors[3].to_string().contains("already declared on line 1"));
+  }
+
+  #[test]
+  fn builtin_functions() {
+    let src = "a = abs(-3);\nb = min(4, 2.5);\nc = max(\"ab\", \"b\");\nd = pow(2, 10);";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("a"), Some(Value::Int(3)));
+    assert_eq!(interpreter.variable("b"), Some(Value::Float(2.5)));
+    assert_eq!(interpreter.variable("c"), Some(Value::Str("b".into())));
+    assert_eq!(interpreter.variable("d"), Some(Value::Int(1024)));
+
+    let src = "e = min(1);\nf = abs(\"x\");\ng = pow(2, -1);";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+      vec![
+        "The call to `min` passes 1 argument(s), but it takes 2.",
+        "The assignment to `f` calls `abs` with a string. it takes a number.",
+        "The assignment to `g` raises to a negative power, `-1`.",
+      ]
+    );
+  }
+
+  #[test]
+  fn registered_functions() {
+    let src = "a = double(21);\nb = max(1, 2);";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+
+    interpreter.register_builtin("double", |args| match args {
+      [Value::Int(x)] => Ok(Value::Int(x * 2)),
+      _ => Err("expected one integer".to_string()),
+    });
+    // A registered function replaces the built-in one
+    interpreter.register_builtin("max", |_| Ok(Value::None));
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("a"), Some(Value::Int(42)));
+    assert_eq!(interpreter.variable("b"), Some(Value::None));
+
+    let src = "c = double(\"x\");\nd = half(2);";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+      vec![
+        "The assignment to `c` calls `double`, which failed with `expected one integer`.",
+        "The function `half` doesn't exist. the built-in functions are `abs`, `min`, `max`, \
+         `pow`, and `double`.",
+      ]
+    );
+  }
+
+  #[test]
+  fn lambdas() {
+    let src = "adder = |n| |x| x + n;\nk = 10;\nadd = adder(k);\nk = 0;\na = add(5);\n\
+               fact = |n| match n { 0 => 1, _ => n * fact(n - 1) };\nb = fact(5);";
+    // Tests run on threads with small stacks
+    let mut interpreter =
+      Interpreter::new(src, Parser::new(src).parse().unwrap()).with_max_call_depth(32);
+    interpreter.evaluate().unwrap();
+
+    // The function keeps the value that `n` had when it was made
+    assert_eq!(interpreter.variable("a"), Some(Value::Int(15)));
+    assert_eq!(interpreter.variable("b"), Some(Value::Int(120)));
+
+    let src = "c = add(1, 2);\nd = b(1);\nloop = |n| loop(n + 1);\ne = loop(0);";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+      vec![
+        "The call to `add` passes 2 argument(s), but it takes 1.",
+        "`b` can't be called, since it's an integer. only functions can be called.",
+        "The assignment to `e` calls `loop` too deeply. calls can only be nested 32 deep.",
+      ]
+    );
+  }
+
+  #[test]
+  fn call_depth() {
+    let src = "sum = |n| match n { 0 => 0, _ => n + sum(n - 1) };\na = sum(40);";
+
+    // The default depth is more than the stack of a test thread can hold
+    std::thread::Builder::new()
+      .stack_size(64 * 1024 * 1024)
+      .spawn(move || {
+        let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+        interpreter.evaluate().unwrap();
+
+        assert_eq!(interpreter.variable("a"), Some(Value::Int(820)));
+      })
+      .unwrap()
+      .join()
+      .unwrap();
+
+    let mut interpreter =
+      Interpreter::new(src, Parser::new(src).parse().unwrap()).with_max_call_depth(10);
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `a` calls `sum` too deeply. calls can only be nested 10 deep."
+    );
+  }
+
+  #[test]
+  fn calls_and_returns() {
+    // `return` isn't parsed yet, so the tree is built by hand
+    let src = "return f(x, 1);";
+    let call = Node::Call(
+      IdentifierNode {
+        literal: "f".to_string(),
+        range: 7..8,
+        line: 1,
+      },
+      vec![
+        Node::Identifier(IdentifierNode {
+          literal: "x".to_string(),
+          range: 9..10,
+          line: 1,
+        }),
+        Node::Literal(LiteralNode {
+          value: Value::Int(1),
+        }),
+      ],
+      7..14,
+    );
+    // `return` isn't a keyword yet, so it's lexed as an identifier
+    let ret = Node::Return(
+      Token::new(TokenKind::Identifier, 0..6, 1),
+      Some(Box::new(call.clone())),
+    );
+
+    assert_eq!(ret.to_string(), src);
+    assert_eq!(ret.sexpr().to_string(), "(return (call f x 1))");
+
+    let mut interpreter = Interpreter::new(src, Node::Program(vec![ret]));
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The return statement isn't in a function."
+    );
+
+    let mut interpreter = Interpreter::new(
+      src,
+      Node::Program(vec![Node::Print(
+        Token::new(TokenKind::Print, 0..6, 1),
+        Box::new(call),
+      )]),
+    );
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      (errors[0].line(), errors[0].column(), errors[0].to_string()),
+      (
+        1,
+        8,
+        "The function `f` doesn't exist. the built-in functions are `abs`, `min`, `max`, and \
+         `pow`."
+          .to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn comparisons() {
+    let src = "a = 2 < 3;\nb = 2.5 >= 3;\nc = 1 + 1 == 2.0;\nd = (1 != 1) - 1;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("a"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("b"), Some(Value::Int(0)));
+    assert_eq!(interpreter.variable("c"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("d"), Some(Value::Int(-1)));
+
+    // Integers are compared as their reduced values
+    let src = "x = 9 == 2;\ny = 6 < 8;";
+    let mut interpreter =
+      Interpreter::new(src, Parser::new(src).parse().unwrap()).with_modulus(Some(7));
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(0)));
+  }
+
+  #[test]
+  fn logical_operators() {
+    let src = "a = 2 && 0.5;\nb = 0 || 0;\nc = !3 + !0;\nd = 1 < 2 && 3 >= 3;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("a"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("b"), Some(Value::Int(0)));
+    assert_eq!(interpreter.variable("c"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("d"), Some(Value::Int(1)));
+
+    // The right operands would report errors if they were evaluated
+    let src = "x = 0 && 1 / 0;\ny = 1 || undefined;\nz = 0 || 1 / 0;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `z` divides by zero."
+    );
+  }
+
+  #[test]
+  fn match_expressions() {
+    let src = "x = match 4 % 3 { 0 => 10, 1.0 => 20, _ => 30 };\ny = match -1 { -1 => 1, _ => 2 };";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(20)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(1)));
+
+    // Only the arm that's picked is evaluated
+    let src = "x = match 2 { 1 => 1 / 0, 2 => 3 };\ny = match 5 { 0 => 1 };";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `y` has no `match` arm for `5`."
+    );
+    assert_eq!((errors[0].line(), errors[0].column()), (2, 5));
+  }
+
+  #[test]
+  fn chained_assignments() {
+    let src = "a = b = c = 2 + 3;\nd = e = a * 2;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    for (name, value) in [("a", 5), ("b", 5), ("c", 5), ("d", 10), ("e", 10)] {
+      assert_eq!(interpreter.variable(name), Some(Value::Int(value)));
+    }
+
+    assert_eq!(
+      interpreter.reduction_steps(),
+      vec!["a = b = c = 2 + 3 → 5", "d = e = a * 2 → 5 * 2 → 10"]
+    );
+
+    // The expression belongs to the last assignment of the chain
+    let src = "x = y = 1 / 0;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+
+    assert_eq!(
+      interpreter.evaluate().unwrap_err()[0].to_string(),
+      "The assignment to `y` divides by zero."
+    );
+  }
+
+  #[test]
+  fn multiple_assignments() {
+    let src = "a, b = 1, 2;\na, b = b, a;\nc, d = a * 10, c;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    // `d` uses `c` before the assignment sets it
+    assert_eq!(
+      errors[0].to_string(),
+      "The identifier `c`, has not yet been initialized."
+    );
+
+    let src = "a, b = 1, 2;\na, b = b, a;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("a"), Some(Value::Int(2)));
+    assert_eq!(interpreter.variable("b"), Some(Value::Int(1)));
+    assert_eq!(
+      interpreter.reduction_steps(),
+      vec!["a = 1", "b = 2", "a = b → 2", "b = a → 1"]
+    );
+  }
+
+  #[test]
+  fn floats() {
+    let src = "x = 12.5 * 2;\ny = 7 / 2 + 0.5;\nz = 2 ** -1.0;";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::Float(25.0)));
+    assert_eq!(interpreter.variable("y"), Some(Value::Float(3.5)));
+    assert_eq!(interpreter.variable("z"), Some(Value::Float(0.5)));
+
+    let src = "x = 1.5 / 0;\ny = 10.0 ** 400;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `x` divides by zero."
+    );
+    assert_eq!(
+      errors[1].to_string(),
+      "The assignment to `y` doesn't have a finite value."
+    );
+  }
+
+  #[test]
+  fn strings() {
+    let src = "x = \"a\" + \"b\";\ny = x == \"ab\";\nz = match x { \"ab\" => 1, _ => 0 };";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.variable("x"), Some(Value::from("ab")));
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("z"), Some(Value::Int(1)));
+
+    let src = "x = \"a\" + 1;\ny = -\"a\";";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `x` applies `+` to a string and an integer. strings can only be \
+       concatenated with `+` and compared with other strings."
+    );
+    assert_eq!(
+      errors[1].to_string(),
+      "The assignment to `y` applies `-` to a string. strings can only be concatenated with `+` \
+       and compared with other strings."
+    );
+  }
+
+  #[test]
+  fn interpolations() {
+    let src = "x = 2;\ny = \"x is ${x + 1}, ${\"not \" + \"${x}\"} ${1.5}!\";";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(
+      interpreter.variable("y"),
+      Some(Value::from("x is 3, not 2 1.5!"))
+    );
+  }
+
+  #[test]
+  fn arrays() {
+    let src = "a = [1, [2, \"b\"]];\nb = a[1][0] + a[0];\nc = a + [3] == [1, [2, \"b\"], 3];";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(
+      interpreter.variable("a").unwrap().to_string(),
+      "[1, [2, \"b\"]]"
+    );
+    assert_eq!(interpreter.variable("b"), Some(Value::Int(3)));
+    assert_eq!(interpreter.variable("c"), Some(Value::Int(1)));
+
+    let src = "x = a[2];\ny = a[-1];\nz = b[0];";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `x` indexes `2` out of bounds of an array of length 2."
+    );
+    assert_eq!(
+      errors[1].to_string(),
+      "The assignment to `y` indexes `-1` out of bounds of an array of length 2."
+    );
+    assert_eq!(
+      errors[2].to_string(),
+      "The assignment to `z` indexes into an integer. only arrays can be indexed."
+    );
+  }
+
+  #[test]
+  fn tuples() {
+    let src = "t = (1, (2, \"a\"),);\nu = t.1.0 + t.0;\nv = (1,) == (1,) && (1, 2) != (2, 1);";
+    let mut interpreter = Interpreter::new(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(
+      interpreter.variable("t").unwrap().to_string(),
+      "(1, (2, \"a\"))"
+    );
+    assert_eq!(interpreter.variable("u"), Some(Value::Int(3)));
+    assert_eq!(interpreter.variable("v"), Some(Value::Int(1)));
+
+    let src = "x = t.2;\ny = u.0;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    let errors = interpreter.evaluate().unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "The assignment to `x` accesses `.2` out of range of a tuple of length 2."
+    );
+    assert_eq!(
+      errors[1].to_string(),
+      "The assignment to `y` accesses `.0` of an integer. only tuples have fields."
+    );
+  }
+
+  #[test]
+  fn undo() {
+    let mut interpreter = Interpreter::new("", Parser::new("").parse().unwrap());
+
+    for src in ["x = 1;", "x = 2; y = 3;", "print x;"] {
+      interpreter.load(src, Parser::new(src).parse().unwrap());
+      interpreter.evaluate().unwrap();
+    }
+
+    assert_eq!(
+      interpreter.undo(),
+      Some(vec!["x".to_string(), "y".to_string()])
+    );
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(1)));
+    assert_eq!(interpreter.variable("y"), None);
+    assert_eq!(interpreter.undo(), Some(vec!["x".to_string()]));
+    assert_eq!(interpreter.undo(), None);
+    assert_eq!(interpreter.variable_count(), 0);
+
+    assert_eq!(interpreter.redo(), Some(vec!["x".to_string()]));
+    assert_eq!(interpreter.variable("x"), Some(Value::Int(1)));
+
+    // A new change discards what was undone
+    let src = "z = 4;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+    interpreter.evaluate().unwrap();
+
+    assert_eq!(interpreter.redo(), None);
+  }
+
+  #[test]
+  fn evaluate_statements() {
+    let src = "x = y = 2; /* note */\nprint x * 3;\nif (x) { z = 1; }\nw = x / 0;";
+    let mut interpreter =
+      Interpreter::new(src, Parser::new(src).parse().unwrap()).with_output(SharedBuffer::default());
+    let results = interpreter.evaluate_statements().collect::<Vec<_>>();
+    let spans = results
+      .iter()
+      .map(|result| &src[result.span.clone()])
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      spans,
+      [
+        "x = y = 2;",
+        "print x * 3;",
+        "if (x) { z = 1; }",
+        "w = x / 0;"
+      ]
+    );
+    assert_eq!(results[0].target.as_deref(), Some("x"));
+    assert_eq!(results[0].value, Some(Value::Int(2)));
+    assert_eq!(results[1].value, Some(Value::Int(6)));
+    assert_eq!(results[2].value, None);
+    assert_eq!(results[3].value, None);
+    assert_eq!(
+      results[3].diagnostics[0].to_string(),
+      "The assignment to `w` divides by zero."
+    );
+    // Like `evaluate`, nothing is set since a statement had an error
+    assert_eq!(interpreter.variable("x"), None);
+
+    let src = "x = 1;\ny = x + 1;";
+    interpreter.load(src, Parser::new(src).parse().unwrap());
+
+    assert_eq!(interpreter.evaluate_statements().count(), 2);
+    assert_eq!(interpreter.variable("y"), Some(Value::Int(2)));
   }
 }
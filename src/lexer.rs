@@ -1,10 +1,22 @@
-use crate::token::{Token, TokenKind};
+use crate::{
+  error::DiagnosticError,
+  pragma,
+  token::{LiteralError, LiteralValue, Token, TokenKind},
+  util::{linebreak_index, token_info},
+  value::Value,
+  version::{Features, LangVersion},
+};
 
+/// Turns the source of a program into [Token]s.
 pub struct Lexer<'a> {
   src: &'a [u8],
   curr: usize,
   is_eof: bool,
   line_number: usize,
+  features: Features,
+  // The number of unclosed `{`s within each `${...}` of a string that's being lexed, innermost
+  // last, so that the `}` ending it continues the string
+  interpolations: Vec<usize>,
 }
 
 impl<'a> Lexer<'a> {
@@ -20,17 +32,28 @@ impl<'a> Lexer<'a> {
       curr: 0,
       is_eof: false,
       line_number: 1,
+      features: Features::default(),
+      interpolations: Vec::new(),
     }
   }
 
+  /// Sets the version of the language to lex, which decides the syntax that's recognized.
+  ///
+  /// A `#[lang: N]` pragma changes the version for the rest of the source.
+  pub fn with_version(mut self, version: LangVersion) -> Self {
+    self.features = version.features();
+    self
+  }
+
   /// Lexes the input source into a [`Vec<Token>`].
   ///
-  /// Note: This **does not** preserve whitespace tokens! If whitespace is necessary, use [Lexer::lex_with_whitespace].
+  /// Note: This **does not** preserve whitespace or comment tokens! If they're necessary, use
+  /// [Lexer::lex_with_whitespace].
   pub fn lex(&mut self) -> Vec<Token> {
     let mut tokens = Vec::new();
 
     while let Some(token) = self.lex_token() {
-      if !matches!(token.kind(), TokenKind::Whitespace) {
+      if !matches!(token.kind(), TokenKind::Whitespace | TokenKind::Comment) {
         tokens.push(token);
       }
     }
@@ -40,8 +63,7 @@ impl<'a> Lexer<'a> {
 
   /// Lexes the input source into a [`Vec<Token>`].
   ///
-  /// This function preserves whitespace.
-  #[allow(dead_code)]
+  /// This function preserves whitespace and comments.
   pub fn lex_with_whitespace(&mut self) -> Vec<Token> {
     let mut tokens = Vec::new();
 
@@ -80,32 +102,250 @@ impl<'a> Lexer<'a> {
 
     let token_kind = match token_type {
       // Single character tokens
-      ByteTokenType::EQUAL => self.advance_and_return(Equal),
+      ByteTokenType::EQUAL => match self.next_byte() {
+        Some(b'=') => self.advance_and_return(EqualEqual),
+        Some(b'>') => self.advance_and_return(FatArrow),
+        _ => Equal,
+      },
+      ByteTokenType::COMMA => self.advance_and_return(Comma),
       ByteTokenType::L_PAREN => self.advance_and_return(LeftParen),
       ByteTokenType::R_PAREN => self.advance_and_return(RightParen),
-      ByteTokenType::STAR => self.advance_and_return(Star),
-      ByteTokenType::PLUS => self.advance_and_return(Plus),
-      ByteTokenType::MINUS => self.advance_and_return(Minus),
+      // Tokens that older versions of the language don't have
+      ByteTokenType::L_BRACE | ByteTokenType::R_BRACE if !self.features.operators => {
+        self.advance_and_return(Unknown)
+      }
+      ByteTokenType::L_BRACKET | ByteTokenType::R_BRACKET if !self.features.arrays => {
+        self.advance_and_return(Unknown)
+      }
+      ByteTokenType::L_BRACKET => self.advance_and_return(LeftBracket),
+      ByteTokenType::R_BRACKET => self.advance_and_return(RightBracket),
+      ByteTokenType::DOT if !self.features.tuples => self.advance_and_return(Unknown),
+      ByteTokenType::DOT => self.advance_and_return(Dot),
+      ByteTokenType::COLON if !self.features.type_annotations => self.advance_and_return(Unknown),
+      ByteTokenType::COLON => self.advance_and_return(Colon),
+      ByteTokenType::L_BRACE => {
+        if let Some(depth) = self.interpolations.last_mut() {
+          *depth += 1;
+        }
+
+        self.advance_and_return(LeftBrace)
+      }
+      // The `}` that closes a `${...}` continues its string
+      ByteTokenType::R_BRACE if self.interpolations.last() == Some(&0) => {
+        self.interpolations.pop();
+        self.lex_string(StringEnd)
+      }
+      ByteTokenType::R_BRACE => {
+        if let Some(depth) = self.interpolations.last_mut() {
+          *depth -= 1;
+        }
+
+        self.advance_and_return(RightBrace)
+      }
+      ByteTokenType::STAR => match self.next_byte() {
+        Some(b'*') => self.advance_and_return(StarStar),
+        _ => Star,
+      },
+      ByteTokenType::SLASH => match self.next_byte() {
+        Some(b'*') if self.features.block_comments => self.lex_block_comment(),
+        _ => Slash,
+      },
+      ByteTokenType::PERCENT => self.advance_and_return(Percent),
+      ByteTokenType::PLUS => match self.next_byte() {
+        Some(b'+') => self.advance_and_return(PlusPlus),
+        _ => Plus,
+      },
+      ByteTokenType::MINUS => match self.next_byte() {
+        Some(b'-') => self.advance_and_return(MinusMinus),
+        _ => Minus,
+      },
       ByteTokenType::SEMICOLON => self.advance_and_return(Semicolon),
+      ByteTokenType::LESS => match self.next_byte() {
+        Some(b'=') => self.advance_and_return(LessEqual),
+        Some(b'<') => self.advance_and_return(LessLess),
+        _ => Less,
+      },
+      ByteTokenType::GREATER => match self.next_byte() {
+        Some(b'=') => self.advance_and_return(GreaterEqual),
+        Some(b'>') => self.advance_and_return(GreaterGreater),
+        _ => Greater,
+      },
+      ByteTokenType::BANG => match self.next_byte() {
+        Some(b'=') => self.advance_and_return(BangEqual),
+        _ => Bang,
+      },
+      // A single `&` or `|` is reserved, which the parser reports
+      ByteTokenType::AMPERSAND => match self.next_byte() {
+        Some(b'&') => self.advance_and_return(AmpersandAmpersand),
+        _ => Ampersand,
+      },
+      ByteTokenType::PIPE => match self.next_byte() {
+        Some(b'|') => self.advance_and_return(PipePipe),
+        _ => Pipe,
+      },
+      ByteTokenType::QUESTION => match self.next_byte() {
+        Some(b'?') => self.advance_and_return(QuestionQuestion),
+        _ => Unknown,
+      },
       ByteTokenType::LINEBREAK => {
         self.line_number += 1;
         self.advance_and_return(Whitespace)
       }
       ByteTokenType::WHITESPACE => self.advance_and_return(Whitespace),
-      ByteTokenType::INVALID => self.advance_and_return(Unknown),
+      ByteTokenType::QUOTE if !self.features.strings => self.advance_and_return(Unknown),
+      ByteTokenType::QUOTE => self.lex_string(StringLiteral),
+      ByteTokenType::APOSTROPHE if !self.features.chars => self.advance_and_return(Unknown),
+      ByteTokenType::APOSTROPHE => self.lex_char(),
+      // The continuation bytes of a character are part of the same token, so that it's reported
+      // once rather than once per byte
+      ByteTokenType::INVALID => self.consume_and_return(|b| b & 0xC0 == 0x80, Unknown),
 
       // Multi-character tokens
-      ByteTokenType::NUMBER => self.consume_and_return(|b| b.is_ascii_digit(), Literal),
+      ByteTokenType::NUMBER
+        if self.features.radix_literals
+          && self.current_byte() == Some(b'0')
+          && matches!(self.src.get(self.curr + 1), Some(b'x' | b'o' | b'b')) =>
+      {
+        self.curr += 1;
+        // Any letters and digits are part of the literal, so that invalid digits are reported
+        // rather than lexed as an identifier
+        self.consume_and_return(|b| b.is_ascii_alphanumeric() || b == b'_', Literal)
+      }
+      ByteTokenType::NUMBER => {
+        self.consume_and_return(|b| b.is_ascii_digit(), Literal);
+
+        // A fraction needs digits on both sides of the `.`, eg `12.5`. The index of a tuple
+        // field can't have one, so that `t.0.1` is two fields rather than a float
+        let fraction = self.src.get(self.curr + 1).is_some_and(u8::is_ascii_digit)
+          && !(self.features.tuples && self.src[..starting_index].ends_with(b"."));
+
+        if self.current_byte() == Some(b'.') && fraction && self.features.floats {
+          self.consume_and_return(|b| b.is_ascii_digit(), Literal)
+        } else {
+          Literal
+        }
+      }
       ByteTokenType::LETTER => {
-        self.consume_and_return(|b| b.is_ascii_alphanumeric() || b == b'_', Identifier)
+        self.consume_and_return(|b| b.is_ascii_alphanumeric() || b == b'_', Identifier);
+
+        keyword(&self.src[starting_index..self.curr])
+          .filter(|_| self.features.keywords)
+          .unwrap_or(Identifier)
+      }
+      ByteTokenType::HASH => {
+        let kind = self.lex_pragma();
+        let src = std::str::from_utf8(&self.src[starting_index..self.curr]).unwrap_or("");
+
+        // The version applies from here on, so that the pragma can come before the syntax it
+        // enables
+        if let (Pragma, Ok(pragma::Pragma::Lang(version))) = (kind, pragma::Pragma::parse(src)) {
+          self.features = version.features();
+        }
+
+        kind
       }
     };
 
-    Some(Token::new(
-      token_kind,
-      starting_index..self.curr,
-      line_number,
-    ))
+    let token = Token::new(token_kind, starting_index..self.curr, line_number);
+
+    // Literals are validated once here, so the parser and any tooling agree on them
+    match token_kind {
+      Literal => {
+        let value = parse_literal(&self.src[token.range()]);
+
+        Some(token.with_value(value))
+      }
+      StringLiteral | StringStart | StringMiddle | StringEnd => {
+        let value = parse_string(&self.src[token.range()]);
+
+        Some(token.with_value(value))
+      }
+      CharLiteral => {
+        let value = parse_char(&self.src[token.range()]);
+
+        Some(token.with_value(value))
+      }
+      _ => Some(token),
+    }
+  }
+
+  // Lexes a pragma, `#[...]`, which has to end on the same line that it starts on.
+  fn lex_pragma(&mut self) -> TokenKind {
+    if self.next_byte() != Some(b'[') {
+      return TokenKind::Unknown;
+    }
+
+    loop {
+      match self.next_byte() {
+        Some(b']') => return self.advance_and_return(TokenKind::Pragma),
+        Some(b'\n' | b'\r') | None => return TokenKind::Unknown,
+        Some(_) => {}
+      }
+    }
+  }
+
+  // Lexes a string, `"..."`, or the rest of one after a `${...}`, which has to end on the same
+  // line that it starts on. `closed` is the kind of token it is if it ends with a `"`.
+  //
+  // A `${` ends the token, so that the expression within it is lexed as tokens of its own.
+  //
+  // A string that isn't closed runs to the end of its line, so that the linebreak is still lexed.
+  fn lex_string(&mut self, closed: TokenKind) -> TokenKind {
+    loop {
+      match self.next_byte() {
+        Some(b'"') => return self.advance_and_return(closed),
+        Some(b'$') if self.src.get(self.curr + 1) == Some(&b'{') => {
+          self.curr += 2;
+          self.interpolations.push(0);
+
+          return match closed {
+            TokenKind::StringLiteral => TokenKind::StringStart,
+            _ => TokenKind::StringMiddle,
+          };
+        }
+        // The escaped character can't end the string, but a linebreak still can't be in it
+        Some(b'\\') if !matches!(self.src.get(self.curr + 1), Some(b'\n' | b'\r') | None) => {
+          self.curr += 1;
+        }
+        Some(b'\n' | b'\r') | None => return TokenKind::Unknown,
+        Some(_) => {}
+      }
+    }
+  }
+
+  // Lexes a character literal, `'...'`, which has to end on the same line that it starts on.
+  //
+  // Any number of characters are part of the token, so that the parser can report the literal as a
+  // whole rather than its pieces.
+  fn lex_char(&mut self) -> TokenKind {
+    loop {
+      match self.next_byte() {
+        Some(b'\'') => return self.advance_and_return(TokenKind::CharLiteral),
+        Some(b'\\') if !matches!(self.src.get(self.curr + 1), Some(b'\n' | b'\r') | None) => {
+          self.curr += 1;
+        }
+        Some(b'\n' | b'\r') | None => return TokenKind::Unknown,
+        Some(_) => {}
+      }
+    }
+  }
+
+  // Lexes a block comment, `/* ... */`, after its opening `/*`, counting the lines within it.
+  //
+  // A comment that's never closed runs to the end of the source.
+  fn lex_block_comment(&mut self) -> TokenKind {
+    loop {
+      match self.next_byte() {
+        Some(b'*') if self.src.get(self.curr + 1) == Some(&b'/') => {
+          self.curr += 2;
+
+          return TokenKind::Comment;
+        }
+        Some(b'\n') => self.line_number += 1,
+        Some(_) => {}
+        None => return TokenKind::Unknown,
+      }
+    }
   }
 
   // Consumes while the provided function is true and return the specified `TokenKind`
@@ -113,7 +353,7 @@ impl<'a> Lexer<'a> {
   where
     F: Fn(u8) -> bool,
   {
-    while self.next_byte().map_or(false, &func) {}
+    while self.next_byte().is_some_and(&func) {}
 
     ret_token
   }
@@ -147,6 +387,263 @@ impl<'a> Lexer<'a> {
   }
 }
 
+/// Returns an error for every [TokenKind::Unknown] token.
+pub fn invalid_tokens(src: &str, tokens: &[Token]) -> Vec<DiagnosticError> {
+  let mut errors = Vec::new();
+
+  for tok in tokens {
+    if matches!(tok.kind(), TokenKind::Unknown) {
+      let info = token_info(src, tok);
+
+      // A string that isn't closed runs to the end of its line, unlike a `"` in a version of the
+      // language without strings
+      let line_end =
+        src[tok.range().end..].starts_with(['\n', '\r']) || tok.range().end == src.len();
+
+      if (info.literal.starts_with('"') || info.literal.starts_with('}')) && line_end {
+        errors.push(DiagnosticError::new(
+          "The string is never closed. it needs a `\"` on the same line to end it.".to_string(),
+          info.line,
+          tok.range().start + 1 - linebreak_index(src, tok.range()),
+        ));
+      } else if info.literal.starts_with('\'') && line_end {
+        errors.push(DiagnosticError::new(
+          "The character is never closed. it needs a `'` on the same line to end it.".to_string(),
+          info.line,
+          tok.range().start + 1 - linebreak_index(src, tok.range()),
+        ));
+      } else if info.literal.starts_with("/*") {
+        // Point to the opening delimiter, rather than the end of the source
+        errors.push(DiagnosticError::new(
+          "The block comment is never closed. it needs a `*/` to end it.".to_string(),
+          info.line,
+          tok.range().start + 1 - linebreak_index(src, tok.range()),
+        ));
+      } else {
+        let c = info.literal.chars().next().unwrap();
+        let suggestion = match confusable(c) {
+          Some(intended) => format!(" did you mean `{}`?", intended),
+          None => String::new(),
+        };
+
+        errors.push(DiagnosticError::new(
+          format!(
+            "The token, `{}` (U+{:04X}), is invalid.{}",
+            info.literal, c as u32, suggestion
+          ),
+          info.line,
+          tok.range().start + 1 - linebreak_index(src, tok.range()),
+        ))
+      }
+    }
+  }
+
+  errors
+}
+
+// Characters that look like a token of this language, and the character that was likely meant,
+// eg from text that was copied out of a word processor.
+const CONFUSABLES: [(char, char); 14] = [
+  ('\u{00A0}', ' '),
+  ('\u{00D7}', '*'),
+  ('\u{00F7}', '/'),
+  ('\u{037E}', ';'),
+  ('\u{2010}', '-'),
+  ('\u{2011}', '-'),
+  ('\u{2012}', '-'),
+  ('\u{2013}', '-'),
+  ('\u{2014}', '-'),
+  ('\u{2018}', '\''),
+  ('\u{2019}', '\''),
+  ('\u{201C}', '"'),
+  ('\u{201D}', '"'),
+  ('\u{2212}', '-'),
+];
+
+// Returns the ASCII character that an invalid character was likely meant to be, if it looks
+// like one.
+fn confusable(c: char) -> Option<char> {
+  match c {
+    // The full-width forms of the printable ASCII characters, eg `＝`
+    '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0),
+    _ => CONFUSABLES
+      .iter()
+      .find(|&&(confusable, _)| confusable == c)
+      .map(|&(_, intended)| intended),
+  }
+}
+
+// Returns the keyword spelled by an identifier, if it is one.
+fn keyword(ident: &[u8]) -> Option<TokenKind> {
+  match ident {
+    b"let" => Some(TokenKind::Let),
+    b"in" => Some(TokenKind::In),
+    b"print" => Some(TokenKind::Print),
+    b"read" => Some(TokenKind::Read),
+    b"if" => Some(TokenKind::If),
+    b"else" => Some(TokenKind::Else),
+    b"for" => Some(TokenKind::For),
+    b"match" => Some(TokenKind::Match),
+    b"break" => Some(TokenKind::Break),
+    b"continue" => Some(TokenKind::Continue),
+    b"const" => Some(TokenKind::Const),
+    b"none" => Some(TokenKind::NoneLiteral),
+    b"struct" => Some(TokenKind::Struct),
+    b"import" => Some(TokenKind::Import),
+    _ => None,
+  }
+}
+
+// Converts the digits of a literal into an integer, or a float if it has a fraction.
+//
+// Leading zeros don't change the value, but are recorded so they can be linted.
+fn parse_literal(literal: &[u8]) -> Result<LiteralValue, LiteralError> {
+  if let Some(radix) = literal_radix(literal) {
+    let digits = &literal[2..];
+
+    if digits.is_empty() {
+      return Err(LiteralError::MissingDigits);
+    }
+
+    let value = digits
+      .iter()
+      .enumerate()
+      .try_fold(Some(0isize), |acc, (i, &digit)| {
+        let digit = (digit as char)
+          .to_digit(radix)
+          .ok_or(LiteralError::InvalidDigit(i + 2))?;
+
+        // Keep looking for invalid digits after an overflow, since they're the likelier mistake
+        Ok(acc.and_then(|acc| {
+          acc
+            .checked_mul(radix as isize)
+            .and_then(|acc| acc.checked_add(digit as isize))
+        }))
+      })?
+      .ok_or(LiteralError::Overflow)?;
+
+    return Ok(LiteralValue {
+      value: Value::Int(value),
+      leading_zeros: false,
+    });
+  }
+
+  let digits = match literal.iter().position(|&b| b == b'.') {
+    Some(dot) => &literal[..dot],
+    None => literal,
+  };
+  let leading_zeros = digits.len() > 1 && digits[0] == b'0';
+
+  if digits.len() != literal.len() {
+    // The literal is only ASCII digits and a `.`, so it's always a valid float
+    let value = std::str::from_utf8(literal)
+      .unwrap()
+      .parse::<f64>()
+      .unwrap();
+
+    if !value.is_finite() {
+      return Err(LiteralError::Overflow);
+    }
+
+    return Ok(LiteralValue {
+      value: Value::Float(value),
+      leading_zeros,
+    });
+  }
+
+  let value = digits
+    .iter()
+    .try_fold(0isize, |acc, &digit| {
+      acc
+        .checked_mul(10)
+        .and_then(|acc| acc.checked_add((digit - b'0') as isize))
+    })
+    .ok_or(LiteralError::Overflow)?;
+
+  Ok(LiteralValue {
+    value: Value::Int(value),
+    leading_zeros,
+  })
+}
+
+// Parses a string literal, or a piece of one around a `${...}`, including its delimiters, replacing
+// its escapes with the characters they stand for.
+fn parse_string(literal: &[u8]) -> Result<LiteralValue, LiteralError> {
+  let literal = String::from_utf8_lossy(literal);
+  let mut value = String::with_capacity(literal.len());
+  // Every piece starts with a `"` or `}`, and ends with a `"` or `${`
+  let end = literal.len() - if literal.ends_with("${") { 2 } else { 1 };
+  let mut chars = literal[1..end].char_indices();
+
+  while let Some((i, c)) = chars.next() {
+    if c != '\\' {
+      value.push(c);
+      continue;
+    }
+
+    match chars.next() {
+      Some((_, escaped)) => match unescape(escaped) {
+        Some(c) => value.push(c),
+        // Offset past the opening delimiter, to the `\`
+        None => return Err(LiteralError::InvalidEscape(i + 1)),
+      },
+      // The lexer only ends a string with a `"` that isn't escaped
+      None => unreachable!("string literal ending in an escape"),
+    }
+  }
+
+  Ok(LiteralValue {
+    value: Value::Str(value.into()),
+    leading_zeros: false,
+  })
+}
+
+// Parses a character literal, including its `'`s, into its code point.
+fn parse_char(literal: &[u8]) -> Result<LiteralValue, LiteralError> {
+  let literal = String::from_utf8_lossy(literal);
+  let mut chars = literal[1..literal.len() - 1].chars();
+
+  let c = match chars.next() {
+    Some('\\') => chars
+      .next()
+      .and_then(unescape)
+      .ok_or(LiteralError::InvalidEscape(1))?,
+    Some(c) => c,
+    None => return Err(LiteralError::InvalidLength),
+  };
+
+  if chars.next().is_some() {
+    return Err(LiteralError::InvalidLength);
+  }
+
+  Ok(LiteralValue {
+    value: Value::Int(c as isize),
+    leading_zeros: false,
+  })
+}
+
+/// Returns the character that the escape, `\` followed by the given character, stands for.
+pub fn unescape(escaped: char) -> Option<char> {
+  match escaped {
+    'n' => Some('\n'),
+    'r' => Some('\r'),
+    't' => Some('\t'),
+    '0' => Some('\0'),
+    '\\' | '"' | '\'' | '$' => Some(escaped),
+    _ => None,
+  }
+}
+
+// Returns the base of a literal with a `0x`, `0o`, or `0b` prefix.
+fn literal_radix(literal: &[u8]) -> Option<u32> {
+  match literal {
+    [b'0', b'x', ..] => Some(16),
+    [b'0', b'o', ..] => Some(8),
+    [b'0', b'b', ..] => Some(2),
+    _ => None,
+  }
+}
+
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[allow(clippy::upper_case_acronyms, non_camel_case_types)]
@@ -154,14 +651,32 @@ enum ByteTokenType {
   NUMBER,
   LETTER,
   SEMICOLON,
+  COMMA,
   EQUAL,
   L_PAREN,
   R_PAREN,
+  L_BRACE,
+  R_BRACE,
+  L_BRACKET,
+  R_BRACKET,
+  DOT,
+  COLON,
   STAR,
+  SLASH,
+  PERCENT,
   PLUS,
   MINUS,
+  LESS,
+  GREATER,
+  BANG,
+  AMPERSAND,
+  PIPE,
+  QUESTION,
   LINEBREAK,
   WHITESPACE,
+  HASH,
+  QUOTE,
+  APOSTROPHE,
   INVALID,
 }
 
@@ -179,15 +694,36 @@ const BYTE_TOKEN_LOOKUP: [ByteTokenType; 256] = {
   default[b' ' as usize] = ByteTokenType::WHITESPACE;
   // Semicolon
   default[b';' as usize] = ByteTokenType::SEMICOLON;
+  default[b',' as usize] = ByteTokenType::COMMA;
   // Arithmetic
   default[b'*' as usize] = ByteTokenType::STAR;
+  default[b'/' as usize] = ByteTokenType::SLASH;
+  default[b'%' as usize] = ByteTokenType::PERCENT;
   default[b'-' as usize] = ByteTokenType::MINUS;
   default[b'+' as usize] = ByteTokenType::PLUS;
+  // Comparisons, shifts, and logic
+  default[b'<' as usize] = ByteTokenType::LESS;
+  default[b'>' as usize] = ByteTokenType::GREATER;
+  default[b'!' as usize] = ByteTokenType::BANG;
+  default[b'&' as usize] = ByteTokenType::AMPERSAND;
+  default[b'|' as usize] = ByteTokenType::PIPE;
+  default[b'?' as usize] = ByteTokenType::QUESTION;
   // Assignment
   default[b'=' as usize] = ByteTokenType::EQUAL;
   // Parenthesis
   default[b'(' as usize] = ByteTokenType::L_PAREN;
   default[b')' as usize] = ByteTokenType::R_PAREN;
+  default[b'{' as usize] = ByteTokenType::L_BRACE;
+  default[b'}' as usize] = ByteTokenType::R_BRACE;
+  default[b'[' as usize] = ByteTokenType::L_BRACKET;
+  default[b']' as usize] = ByteTokenType::R_BRACKET;
+  default[b'.' as usize] = ByteTokenType::DOT;
+  default[b':' as usize] = ByteTokenType::COLON;
+  // Pragmas
+  default[b'#' as usize] = ByteTokenType::HASH;
+  // Strings
+  default[b'"' as usize] = ByteTokenType::QUOTE;
+  default[b'\'' as usize] = ByteTokenType::APOSTROPHE;
 
   // Numbers
   let mut i = b'0';
@@ -212,6 +748,8 @@ const BYTE_TOKEN_LOOKUP: [ByteTokenType; 256] = {
     i += 1;
   }
 
+  default[b'_' as usize] = ByteTokenType::LETTER;
+
   default
 };
 
@@ -221,7 +759,7 @@ mod tests {
 
   macro_rules! are_tokens_equal {
   ($src:literal, $($token:tt),*) => {
-    let tokens = get_tokens!(include_str!(concat!("../sample_input/", $src, ".txt")));
+    let tokens = get_tokens!(include_str!(concat!("../sample_files/", $src, ".txt")));
     let expected = vec![$(TokenKind::$token),*];
 
     assert_eq!(tokens, expected);
@@ -251,16 +789,189 @@ mod tests {
     assert_eq!(
       tokens,
       vec![
+        TokenKind::Identifier,
         TokenKind::Unknown,
+        TokenKind::Greater,
+        TokenKind::Less,
+        TokenKind::Dot,
+        TokenKind::Comma,
+        TokenKind::Dot,
         TokenKind::Unknown,
+        TokenKind::Comma,
+        TokenKind::Dot,
+      ]
+    );
+  }
+
+  #[test]
+  fn literal_values() {
+    let values = Lexer::new("0 42 007 99999999999999999999 12.5 007.0")
+      .lex()
+      .iter()
+      .filter_map(Token::value)
+      .map(|value| value.map(|lit| (lit.value, lit.leading_zeros)))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      values,
+      vec![
+        Ok((Value::Int(0), false)),
+        Ok((Value::Int(42), false)),
+        Ok((Value::Int(7), true)),
+        Err(LiteralError::Overflow),
+        Ok((Value::Float(12.5), false)),
+        Ok((Value::Float(7.0), true)),
+      ]
+    );
+  }
+
+  #[test]
+  fn radix_literals() {
+    let values = Lexer::new("0x1F 0o17 0b1010 0x 0b102 0x8000000000000000")
+      .lex()
+      .iter()
+      .filter_map(Token::value)
+      .map(|value| value.map(|lit| lit.value))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Value::Int(31)),
+        Ok(Value::Int(15)),
+        Ok(Value::Int(10)),
+        Err(LiteralError::MissingDigits),
+        Err(LiteralError::InvalidDigit(4)),
+        Err(LiteralError::Overflow),
+      ]
+    );
+
+    // The prefixes are a `0` followed by an identifier in version 1
+    assert_eq!(
+      Lexer::new("0x1F")
+        .with_version(LangVersion::V1)
+        .lex()
+        .iter()
+        .map(Token::kind)
+        .collect::<Vec<_>>(),
+      vec![
+        TokenKind::Literal,
+        TokenKind::Identifier,
+        TokenKind::EndOfFile
+      ]
+    );
+  }
+
+  #[test]
+  fn confusables() {
+    let src = "x \u{FF1D} 1 \u{2013} 2;\ny = $;";
+    let errors = super::invalid_tokens(src, &Lexer::new(src).lex())
+      .iter()
+      .map(|err| (err.line(), err.column(), err.to_string()))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      errors,
+      vec![
+        (
+          1,
+          3,
+          "The token, `\u{FF1D}` (U+FF1D), is invalid. did you mean `=`?".to_string()
+        ),
+        (
+          1,
+          9,
+          "The token, `\u{2013}` (U+2013), is invalid. did you mean `-`?".to_string()
+        ),
+        (2, 5, "The token, `$` (U+0024), is invalid.".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn keywords() {
+    let tokens = get_tokens!("let in letter inn")
+      .into_iter()
+      .filter(|kind| !matches!(kind, TokenKind::Whitespace))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      tokens,
+      vec![
+        TokenKind::Let,
+        TokenKind::In,
+        TokenKind::Identifier,
+        TokenKind::Identifier
+      ]
+    );
+  }
+
+  #[test]
+  fn block_comments() {
+    let tokens = Lexer::new("x /* a\n * b */ = 1;\n/* c\n\ny").lex();
+    let kinds = tokens.iter().map(Token::kind).collect::<Vec<_>>();
+
+    assert_eq!(
+      kinds,
+      vec![
+        TokenKind::Identifier,
+        TokenKind::Equal,
+        TokenKind::Literal,
+        TokenKind::Semicolon,
         TokenKind::Unknown,
+        TokenKind::EndOfFile
+      ]
+    );
+    assert_eq!(tokens[1].line(), 2);
+    assert_eq!(tokens[5].line(), 5);
+
+    let errors = super::invalid_tokens("x /* a\n * b */ = 1;\n/* c\n\ny", &tokens);
+
+    assert_eq!((errors[0].line(), errors[0].column()), (3, 1));
+  }
+
+  #[test]
+  fn pragmas() {
+    let tokens = get_tokens!("#[modulus: 97]\n#[oops\n#a");
+
+    assert_eq!(
+      tokens,
+      vec![
+        TokenKind::Pragma,
         TokenKind::Unknown,
         TokenKind::Unknown,
+        TokenKind::Identifier
+      ]
+    );
+  }
+
+  #[test]
+  fn lang_versions() {
+    let src = "print 1.5 / 2;\n#[lang: 2]\nprint 1.5 / 2;";
+    let kinds = Lexer::new(src)
+      .with_version(LangVersion::V1)
+      .lex()
+      .iter()
+      .map(Token::kind)
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      kinds,
+      vec![
+        TokenKind::Identifier,
+        TokenKind::Literal,
         TokenKind::Unknown,
-        TokenKind::Unknown,
-        TokenKind::Unknown,
-        TokenKind::Unknown,
-        TokenKind::Unknown,
+        TokenKind::Literal,
+        TokenKind::Slash,
+        TokenKind::Literal,
+        TokenKind::Semicolon,
+        TokenKind::Pragma,
+        TokenKind::Print,
+        TokenKind::Literal,
+        TokenKind::Slash,
+        TokenKind::Literal,
+        TokenKind::Semicolon,
+        TokenKind::EndOfFile
       ]
     );
   }
@@ -290,7 +1001,7 @@ mod tests {
       "3",
        Identifier, Equal, Literal,
        Identifier, Equal, Identifier, Semicolon,
-       Identifier, Equal, Minus, Minus, Minus, LeftParen, Identifier, Plus, Identifier, RightParen, Semicolon
+       Identifier, Equal, MinusMinus, Minus, LeftParen, Identifier, Plus, Identifier, RightParen, Semicolon
     );
   }
 
@@ -301,7 +1012,87 @@ mod tests {
       "4",
       Identifier, Equal, Literal, Semicolon,
       Identifier, Equal, Literal, Semicolon,
-      Identifier, Equal, Minus, Minus, Minus, LeftParen, Identifier, Plus, Identifier, RightParen, Star, LeftParen, Identifier, Plus, Minus, Identifier, RightParen, Semicolon
+      Identifier, Equal, MinusMinus, Minus, LeftParen, Identifier, Plus, Identifier, RightParen, Star, LeftParen, Identifier, Plus, Minus, Identifier, RightParen, Semicolon
+    );
+  }
+
+  #[test]
+  fn strings() {
+    let values = Lexer::new(r#""" "a \"b\" \\n" "\q""#)
+      .lex()
+      .iter()
+      .filter_map(Token::value)
+      .map(|value| value.map(|lit| lit.value))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Value::from("")),
+        Ok(Value::from("a \"b\" \\n")),
+        Err(LiteralError::InvalidEscape(1)),
+      ]
+    );
+
+    // A string that isn't closed ends at its line, so the next line is still lexed
+    assert_eq!(
+      get_tokens!("\"a\nb"),
+      vec![TokenKind::Unknown, TokenKind::Identifier]
+    );
+  }
+
+  #[test]
+  fn chars() {
+    let values = Lexer::new(r"'a' '\n' '\'' '' 'ab' '\q'")
+      .lex()
+      .iter()
+      .filter_map(Token::value)
+      .map(|value| value.map(|lit| lit.value))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      values,
+      vec![
+        Ok(Value::Int(97)),
+        Ok(Value::Int(10)),
+        Ok(Value::Int(39)),
+        Err(LiteralError::InvalidLength),
+        Err(LiteralError::InvalidLength),
+        Err(LiteralError::InvalidEscape(1)),
+      ]
+    );
+  }
+
+  #[test]
+  fn fields() {
+    // The `.` after a field isn't a fraction, so `t.0.1` is two field accesses
+    assert_eq!(
+      get_tokens!("t.0.1 1.5"),
+      vec![
+        TokenKind::Identifier,
+        TokenKind::Dot,
+        TokenKind::Literal,
+        TokenKind::Dot,
+        TokenKind::Literal,
+        TokenKind::Literal,
+      ]
+    );
+  }
+
+  #[test]
+  fn interpolations() {
+    assert_eq!(
+      get_tokens!(r#""a ${x} b ${ {y} }!" "\${x}""#),
+      vec![
+        TokenKind::StringStart,
+        TokenKind::Identifier,
+        TokenKind::StringMiddle,
+        TokenKind::LeftBrace,
+        TokenKind::Identifier,
+        TokenKind::RightBrace,
+        TokenKind::StringEnd,
+        TokenKind::StringLiteral,
+      ]
     );
   }
 }
@@ -0,0 +1,45 @@
+//! The lexer, parser, and interpreter of the toy language, for embedding in other tools. The
+//! `toy_language` binary is a command line interface over this library.
+//!
+//! ```
+//! use toy_language::{Interpreter, Parser, Value};
+//!
+//! let src = "x = 2 * 3;";
+//! let ast = Parser::new(src).parse().unwrap();
+//! let mut interpreter = Interpreter::new(src, ast);
+//!
+//! interpreter.evaluate().unwrap();
+//! assert_eq!(interpreter.variable("x"), Some(Value::Int(6)));
+//! ```
+
+pub mod builtin;
+mod canonical;
+pub mod capability;
+pub mod derive;
+pub mod error;
+pub mod include;
+pub mod interpreter;
+pub mod lexer;
+pub mod limits;
+pub mod lint;
+pub mod memory;
+pub mod node;
+pub mod parser;
+pub mod pragma;
+pub mod run;
+pub mod snippet;
+pub mod steps;
+pub mod token;
+pub mod typeck;
+pub(crate) mod util;
+pub mod value;
+pub mod version;
+
+pub use error::DiagnosticError;
+pub use interpreter::Interpreter;
+pub use lexer::Lexer;
+pub use node::Node;
+pub use parser::Parser;
+pub use token::Token;
+pub use util::LineIndex;
+pub use value::Value;
@@ -0,0 +1,280 @@
+use crate::{error::DiagnosticError, node::Node, util::LineIndex};
+
+/// Caps on the size of a program, so that untrusted input can be rejected before it's run.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+  /// The most nodes that the whole program can have.
+  pub max_nodes: Option<usize>,
+  /// The most nodes that the expression of a single statement can have.
+  pub max_expression_nodes: Option<usize>,
+  /// The most levels that expressions and blocks can nest, which is checked while parsing, so
+  /// that deeply nested input can't overflow the stack. It's [DEFAULT_MAX_DEPTH] by default.
+  pub max_depth: usize,
+  /// How many calls of functions made by lambdas can be nested while the program runs. It's
+  /// [DEFAULT_MAX_CALL_DEPTH] by default.
+  pub max_call_depth: usize,
+}
+
+/// The most levels that expressions and blocks can nest unless [Limits::with_max_depth] is used.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// How many calls of functions can be nested unless [Limits::with_max_call_depth] is used.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 256;
+
+impl Default for Limits {
+  fn default() -> Self {
+    Self {
+      max_nodes: None,
+      max_expression_nodes: None,
+      max_depth: DEFAULT_MAX_DEPTH,
+      max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+    }
+  }
+}
+
+impl Limits {
+  /// Sets the most nodes that the whole program can have.
+  pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+    self.max_nodes = Some(max_nodes);
+    self
+  }
+
+  /// Sets the most nodes that the expression of a single statement can have.
+  pub fn with_max_expression_nodes(mut self, max_expression_nodes: usize) -> Self {
+    self.max_expression_nodes = Some(max_expression_nodes);
+    self
+  }
+
+  /// Sets the most levels that expressions and blocks can nest, eg `((1))` nests 3 levels.
+  pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+    self.max_depth = max_depth;
+    self
+  }
+
+  /// Sets how many calls of functions made by lambdas can be nested while the program runs.
+  pub fn with_max_call_depth(mut self, max_call_depth: usize) -> Self {
+    self.max_call_depth = max_call_depth;
+    self
+  }
+
+  /// Checks the parsed program against the limits.
+  ///
+  /// # Returns
+  /// Returns an error for every statement whose expression is too large, and for the statement
+  /// that makes the whole program too large.
+  pub fn check(&self, src: &str, program: &Node) -> Result<(), Vec<DiagnosticError>> {
+    let Node::Program(statements) = program else {
+      return Ok(());
+    };
+
+    let line_index = LineIndex::new(src);
+    let mut errors = Vec::new();
+    let mut total = 1;
+
+    for statement in statements {
+      let nodes = statement.node_count();
+      let (subject, start, _) = describe(statement);
+      let (line, column) = line_index.line_col(start);
+
+      if let Some(max) = self.max_expression_nodes {
+        check_expressions(statement, max, &line_index, &mut errors);
+      }
+
+      if let Some(max) = self.max_nodes {
+        // Only the statement that crosses the limit is reported
+        if total <= max && total + nodes > max {
+          errors.push(DiagnosticError::new(
+            format!(
+              "The program has more than {} nodes, the most allowed, by {}.",
+              max, subject
+            ),
+            line,
+            column,
+          ));
+        }
+      }
+
+      total += nodes;
+    }
+
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(errors)
+    }
+  }
+}
+
+// Reports every expression of the statement, including those in its blocks, with more nodes than
+// the most allowed.
+fn check_expressions(
+  statement: &Node,
+  max: usize,
+  line_index: &LineIndex,
+  errors: &mut Vec<DiagnosticError>,
+) {
+  let (subject, start, expr) = describe(statement);
+
+  if let Some(expr_nodes) = expr.map(Node::node_count).filter(|&nodes| nodes > max) {
+    let (line, column) = line_index.line_col(start);
+
+    errors.push(DiagnosticError::new(
+      format!(
+        "The expression of {} has {} nodes, but at most {} are allowed.",
+        subject, expr_nodes, max
+      ),
+      line,
+      column,
+    ));
+  }
+
+  match statement {
+    Node::Conditional(_, statement) => check_expressions(statement, max, line_index, errors),
+    Node::BlockStatement(_, block) => {
+      if let Node::Block(statements) = &**block {
+        for statement in statements {
+          check_expressions(statement, max, line_index, errors);
+        }
+      }
+    }
+    Node::If(_, _, then, otherwise) => {
+      for block in std::iter::once(then).chain(otherwise) {
+        if let Node::Block(statements) = &**block {
+          for statement in statements {
+            check_expressions(statement, max, line_index, errors);
+          }
+        }
+      }
+    }
+    Node::MultiAssignment(idents, values) => {
+      for (ident, value) in idents.iter().zip(values) {
+        let assignment = Node::Assignment(
+          Box::new(Node::Identifier(ident.clone())),
+          Box::new(value.clone()),
+        );
+
+        check_expressions(&assignment, max, line_index, errors);
+      }
+    }
+    Node::For(_, init, _, step, body) => {
+      for clause in init.iter().chain(step) {
+        check_expressions(clause, max, line_index, errors);
+      }
+
+      if let Node::Block(statements) = &**body {
+        for statement in statements {
+          check_expressions(statement, max, line_index, errors);
+        }
+      }
+    }
+    _ => {}
+  }
+}
+
+// Returns how a statement is described in diagnostics, where it starts, and its expression, if
+// it has one.
+fn describe(statement: &Node) -> (String, usize, Option<&Node>) {
+  match statement {
+    // The statement itself is described, rather than its flags
+    Node::Conditional(_, statement) => {
+      let (subject, start, _) = describe(statement);
+
+      (subject, start, None)
+    }
+    Node::If(keyword, condition, ..) => (
+      "the `if` statement".to_string(),
+      keyword.range().start,
+      Some(condition),
+    ),
+    Node::For(keyword, _, condition, ..) => (
+      "the `for` loop".to_string(),
+      keyword.range().start,
+      Some(condition),
+    ),
+    Node::Assignment(ident, expr) => match &**ident {
+      Node::Identifier(ident) => (
+        format!("the assignment to `{}`", ident.literal),
+        ident.range.start,
+        Some(expr),
+      ),
+      _ => unreachable!("assignments are always to identifiers"),
+    },
+    Node::ConstAssignment(keyword, ident, expr) => (
+      format!("the declaration of `{}`", ident.literal),
+      keyword.range().start,
+      Some(expr),
+    ),
+    // Each value is checked on its own, as part of the assignment to its variable
+    Node::MultiAssignment(idents, _) => {
+      let names = idents
+        .iter()
+        .map(|ident| ident.literal.as_str())
+        .collect::<Vec<_>>();
+
+      (
+        format!("the assignment to `{}`", names.join(", ")),
+        idents[0].range.start,
+        None,
+      )
+    }
+    Node::Print(keyword, expr) => (
+      "the print statement".to_string(),
+      keyword.range().start,
+      Some(expr),
+    ),
+    Node::Return(keyword, value) => (
+      "the return statement".to_string(),
+      keyword.range().start,
+      value.as_deref(),
+    ),
+    Node::Read(keyword, ident) => (
+      format!("the read into `{}`", ident.literal),
+      keyword.range().start,
+      None,
+    ),
+    Node::BlockStatement(keyword, _) => ("the block".to_string(), keyword.range().start, None),
+    Node::Break(keyword) => ("the `break`".to_string(), keyword.range().start, None),
+    Node::Continue(keyword) => ("the `continue`".to_string(), keyword.range().start, None),
+    Node::StructDeclaration(keyword, name, _) => (
+      format!("the declaration of the struct `{}`", name.literal),
+      keyword.range().start,
+      None,
+    ),
+    Node::Import(keyword, path) => (
+      format!("the import of `{}`", path),
+      keyword.range().start,
+      None,
+    ),
+    _ => unreachable!("programs only contain statements"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Parser;
+
+  #[test]
+  fn reports_statements_over_the_limits() {
+    let src = "x = 1;\ny = 1 + 2 * 3;\nz = x;";
+    let program = Parser::new(src).parse().unwrap();
+
+    assert!(Limits::default().check(src, &program).is_ok());
+
+    let errors = Limits::default()
+      .with_max_expression_nodes(3)
+      .check(src, &program)
+      .unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line(), 2);
+
+    let errors = Limits::default()
+      .with_max_nodes(8)
+      .check(src, &program)
+      .unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].to_string().contains("`y`"));
+  }
+}
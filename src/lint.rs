@@ -0,0 +1,76 @@
+/// The lints that can be configured with `--lint=<name>:<level>`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Lint {
+  /// Integer literals written with leading zeros, eg `007`.
+  LeadingZeros,
+  /// `match` expressions without a `_` arm, eg `match x { 0 => 1 }`.
+  NonExhaustiveMatch,
+}
+
+/// What to do when a [Lint] is triggered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LintLevel {
+  /// Silently accept the code.
+  Allow,
+  /// Report a warning, but still run the program.
+  Warn,
+  /// Report an error.
+  Deny,
+}
+
+/// The configured [LintLevel] of every [Lint].
+#[derive(Clone, Debug)]
+pub struct LintLevels {
+  leading_zeros: LintLevel,
+  non_exhaustive_match: LintLevel,
+}
+
+impl Lint {
+  /// Returns the lint with the given command line name.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "leading-zeros" => Some(Lint::LeadingZeros),
+      "non-exhaustive-match" => Some(Lint::NonExhaustiveMatch),
+      _ => None,
+    }
+  }
+}
+
+impl LintLevel {
+  /// Returns the level with the given command line name.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "allow" => Some(LintLevel::Allow),
+      "warn" => Some(LintLevel::Warn),
+      "deny" => Some(LintLevel::Deny),
+      _ => None,
+    }
+  }
+}
+
+impl LintLevels {
+  /// Returns the configured level of the lint.
+  pub fn level(&self, lint: Lint) -> LintLevel {
+    match lint {
+      Lint::LeadingZeros => self.leading_zeros,
+      Lint::NonExhaustiveMatch => self.non_exhaustive_match,
+    }
+  }
+
+  /// Sets the level of the lint.
+  pub fn set(&mut self, lint: Lint, level: LintLevel) {
+    match lint {
+      Lint::LeadingZeros => self.leading_zeros = level,
+      Lint::NonExhaustiveMatch => self.non_exhaustive_match = level,
+    }
+  }
+}
+
+impl Default for LintLevels {
+  fn default() -> Self {
+    Self {
+      leading_zeros: LintLevel::Warn,
+      non_exhaustive_match: LintLevel::Warn,
+    }
+  }
+}
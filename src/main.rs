@@ -1,34 +1,222 @@
-mod error;
-mod interpreter;
-mod lexer;
-mod node;
-mod parser;
-mod token;
-mod util;
-
-use error::DiagnosticError;
-use interpreter::Interpreter;
-use lexer::Lexer;
-use parser::Parser;
-use std::{env, fs, path::Path};
-use token::{Token, TokenKind};
-use util::token_info;
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+mod ice;
+mod minimize;
+mod refactor;
+mod summary;
+mod ui;
+
+use minimize::{minimize, Predicate};
+use refactor::inline;
+use std::{
+  env,
+  error::Error,
+  fs,
+  fs::File,
+  io::{self, BufWriter, Write},
+  path::Path,
+  thread,
+};
+use summary::Summary;
+use toy_language::{
+  capability::{Capabilities, Capability},
+  derive::derivative,
+  error::Severity,
+  include::Expansion,
+  interpreter::Overflow,
+  limits::Limits,
+  lint::{Lint, LintLevel, LintLevels},
+  parser::Terminator,
+  pragma::{filter_statements, Pragmas},
+  run::{Observer, Phase, Runner, Settings},
+  snippet,
+  steps::{substitute_variable, Unsubstituted},
+  token::Token,
+  value::format_value,
+  version::LangVersion,
+  DiagnosticError, Interpreter, Node,
+};
+
+/// How the AST is printed with `--print-ast`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum AstFormat {
+  /// The derived `Debug` output.
+  Debug,
+  /// Nested s-expressions, eg `(assign x (+ 2 (* 3 y)))`.
+  SExpr,
+}
+
+// The size of the stack that programs are parsed and run on.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  ice::install_hook();
+
+  // Every level of nesting, and every call of a function, recurses while the program is parsed
+  // and run, so it gets a larger stack than the main thread's to reach the limits on them
+  let program = thread::Builder::new().stack_size(STACK_SIZE).spawn(run)?;
+
+  match program.join() {
+    Ok(result) => result,
+    // The panic has already been reported by the hook
+    Err(_) => std::process::exit(101),
+  }
+}
+
+// Runs the command given by the arguments.
+fn run() -> Result<(), Box<dyn Error + Send + Sync>> {
   let mut args = env::args();
 
   // The first argument is usually the executable name
   let exec = args.next().unwrap();
+  let args = args.collect::<Vec<_>>();
+
+  match args.first().map(String::as_str) {
+    Some("why") => return why(&args[1..]),
+    Some("derive") => return derive(&args[1..]),
+    Some("minimize") => return minimize_file(&args[1..]),
+    Some("test") => return test_files(&args[1..]),
+    Some("refactor") => return refactor(&args[1..]),
+    _ => {}
+  }
 
   let mut print_lexed_tokens = false;
   let mut print_ast = false;
+  let mut show_steps = false;
+  let mut group_digits = false;
+  let mut overflow = Overflow::Checked;
+  let mut ast_format = AstFormat::Debug;
+  let mut lints = LintLevels::default();
+  let mut capabilities = Capabilities::default();
+  let mut flags = Vec::new();
+  let mut repl = false;
+  let mut show_summary = false;
+  let mut output = None;
+  let mut limits = Limits::default();
+  let mut terminator = Terminator::default();
+  let mut version = LangVersion::default();
+  let mut context = None;
   let mut file_name = None;
+  let mut args = args.into_iter();
 
-  for arg in args {
+  while let Some(arg) = args.next() {
     if arg == "--print-ast" || arg == "-a" {
       print_ast = true;
+    } else if let Some(format) = arg.strip_prefix("--ast-format=") {
+      ast_format = match format {
+        "debug" => AstFormat::Debug,
+        "sexpr" => AstFormat::SExpr,
+        _ => {
+          eprintln!(
+            "unknown AST format `{}`, expected `debug` or `sexpr`.",
+            format
+          );
+          std::process::exit(1);
+        }
+      };
+      print_ast = true;
+    } else if let Some(lint) = arg.strip_prefix("--lint=") {
+      let parsed = lint
+        .split_once(':')
+        .and_then(|(name, level)| Some((Lint::from_name(name)?, LintLevel::from_name(level)?)));
+
+      match parsed {
+        Some((lint, level)) => lints.set(lint, level),
+        None => {
+          eprintln!(
+            "invalid lint `{}`, expected `<name>:<allow|warn|deny>`.",
+            lint
+          );
+          std::process::exit(1);
+        }
+      }
+    } else if let Some(names) = arg.strip_prefix("--allow=") {
+      for name in names.split(',') {
+        match Capability::from_name(name) {
+          Some(capability) => capabilities.grant(capability),
+          None => {
+            eprintln!(
+              "unknown capability `{}`, expected `read`, `import`, or `include`.",
+              name
+            );
+            std::process::exit(1);
+          }
+        }
+      }
+    } else if let Some(flag) = arg.strip_prefix("--flag=") {
+      flags.push(flag.to_string());
+    } else if arg == "--flag" {
+      match args.next() {
+        Some(flag) => flags.push(flag),
+        None => {
+          eprintln!("expected a flag after `--flag`.");
+          std::process::exit(1);
+        }
+      }
+    } else if arg == "--wrapping" {
+      overflow = Overflow::Wrapping;
+    } else if arg == "--saturating" {
+      overflow = Overflow::Saturating;
+    } else if arg == "--group-digits" {
+      group_digits = true;
+    } else if arg == "--show-steps" {
+      show_steps = true;
     } else if arg == "--print-tokens" || arg == "-t" {
       print_lexed_tokens = true;
+    } else if arg == "--repl" {
+      repl = true;
+    } else if let Some(path) = arg.strip_prefix("--output=") {
+      output = Some(path.to_string());
+    } else if arg == "--output" || arg == "-o" {
+      match args.next() {
+        Some(path) => output = Some(path),
+        None => {
+          eprintln!("expected a file after `--output`.");
+          std::process::exit(1);
+        }
+      }
+    } else if let Some(max) = arg.strip_prefix("--max-nodes=") {
+      limits = limits.with_max_nodes(parse_limit("--max-nodes", max));
+    } else if let Some(max) = arg.strip_prefix("--max-expression-nodes=") {
+      limits = limits.with_max_expression_nodes(parse_limit("--max-expression-nodes", max));
+    } else if let Some(max) = arg.strip_prefix("--max-depth=") {
+      limits = limits.with_max_depth(parse_limit("--max-depth", max));
+    } else if let Some(max) = arg.strip_prefix("--max-call-depth=") {
+      limits = limits.with_max_call_depth(parse_limit("--max-call-depth", max));
+    } else if arg == "--asi" {
+      terminator = Terminator::Inferred;
+    } else if let Some(dialect) = arg.strip_prefix("--dialect=") {
+      terminator = match dialect {
+        "semicolons" => Terminator::Semicolon,
+        "lines" => Terminator::Lines,
+        _ => {
+          eprintln!(
+            "unknown dialect `{}`, expected `semicolons` or `lines`.",
+            dialect
+          );
+          std::process::exit(1);
+        }
+      };
+    } else if let Some(number) = arg.strip_prefix("--lang-version=") {
+      version = parse_version(number);
+    } else if arg == "--lang-version" {
+      match args.next() {
+        Some(number) => version = parse_version(&number),
+        None => {
+          eprintln!("expected a version after `--lang-version`.");
+          std::process::exit(1);
+        }
+      }
+    } else if let Some(lines) = arg.strip_prefix("--context=") {
+      context = Some(parse_limit("--context", lines));
+    } else if arg == "--context" {
+      match args.next() {
+        Some(lines) => context = Some(parse_limit("--context", &lines)),
+        None => {
+          eprintln!("expected a number of lines after `--context`.");
+          std::process::exit(1);
+        }
+      }
+    } else if arg == "--summary" {
+      show_summary = true;
     } else if arg == "--help" || arg == "-h" {
       print_help(&exec);
     } else if file_name.is_none() {
@@ -36,58 +224,545 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
   }
 
-  let file_name = file_name.unwrap_or_else(|| {
-    println!("expected a file to be passed.");
-    std::process::exit(1);
-  });
+  let renderer = Renderer {
+    context,
+    ..Renderer::default()
+  };
+  let settings = Settings {
+    lints,
+    terminator,
+    version,
+    flags,
+    limits,
+    capabilities,
+  };
+  let file_name = match file_name {
+    Some(file_name) if !repl => file_name,
+    // Without a file, statements are read from stdin instead
+    _ => {
+      let interpreter = Interpreter::new("", Node::Program(Vec::new()))
+        .with_overflow(overflow)
+        .with_capabilities(capabilities)
+        .with_max_call_depth(limits.max_call_depth);
+
+      return run_repl(
+        interpreter,
+        Runner::new(
+          settings,
+          Reporter {
+            renderer,
+            ..Reporter::default()
+          },
+        ),
+        group_digits,
+      );
+    }
+  };
   let src = fs::read_to_string(&file_name)?;
+  let mut runner = Runner::new(
+    settings,
+    Reporter {
+      summary: Summary {
+        files: 1,
+        ..Summary::default()
+      },
+      print_tokens: print_lexed_tokens,
+      ast_format: print_ast.then_some(ast_format),
+      renderer,
+    },
+  );
+  let interpreter = Interpreter::new(&src, Node::Program(Vec::new()))
+    .with_overflow(overflow)
+    .with_capabilities(capabilities);
+  let result = runner.run_file(interpreter, &file_name, &src);
+  let mut summary = std::mem::take(&mut runner.observer_mut().summary);
+
+  match result {
+    Ok(interpreter) => {
+      summary.variables = interpreter.variable_count();
+      summary.variable_bytes = interpreter.env_size().bytes();
+
+      if show_steps {
+        println!("The reduction steps of the program are:\n");
 
-  // Lex the input, handling invalid tokens
-  let mut lexer = Lexer::new(&src);
-  let tokens = lexer.lex();
-  let lex_errors = get_lexer_errors(&src, &tokens);
+        for line in interpreter.reduction_steps() {
+          println!("{}", line);
+        }
 
-  if !lex_errors.is_empty() {
-    handle_error(&file_name, lex_errors);
+        println!();
+      }
+
+      match &output {
+        Some(path) => {
+          let mut file = BufWriter::new(File::create(path)?);
+
+          interpreter.dump(&mut file, group_digits)?;
+          file.flush()?;
+        }
+        None => {
+          println!("The result of the program is:\n");
+
+          interpreter.dump(&mut io::stdout().lock(), group_digits)?;
+        }
+      }
+
+      if show_summary {
+        // Separate the summary from the result, if it was printed
+        if output.is_none() {
+          println!();
+        }
+
+        println!("The summary of the run is:\n\n{}", summary);
+      }
+    }
+    Err(failure) => fail(
+      &runner.observer().renderer,
+      &failure.file_name,
+      &failure.src,
+      failure.errors,
+      show_summary.then_some(summary),
+    ),
+  }
+
+  Ok(())
+}
+
+// Prints the warnings of the files that are run as they're reported, and counts what happens for
+// `--summary`.
+#[derive(Default)]
+struct Reporter {
+  summary: Summary,
+  // Whether to print the tokens of the file that's run, for `--print-tokens`
+  print_tokens: bool,
+  // The format to print the AST of the file that's run in, for `--print-ast`
+  ast_format: Option<AstFormat>,
+  renderer: Renderer,
+}
+
+impl Observer for Reporter {
+  fn phase(&mut self, file_name: &str, src: &str, phase: Phase) {
+    ice::set_source(file_name, src);
+    ice::set_phase(phase);
   }
 
-  if print_lexed_tokens {
-    println!("The lexed tokens of the program are:\n{:#?}", &tokens);
+  // Only the file that's run is printed, which is lexed and parsed before any that it imports
+  fn lexed(&mut self, tokens: &[Token]) {
+    ice::set_tokens(tokens);
+
+    if std::mem::take(&mut self.print_tokens) {
+      println!("The lexed tokens of the program are:\n{:#?}", tokens);
+    }
   }
 
-  // Parse the program using the lexed tokens
-  let mut parser = Parser::from_tokens(&src, tokens);
-  let ast = parser
-    .parse()
-    .unwrap_or_else(|err| handle_error(&file_name, err));
+  // The diagnostics of a file that included others are printed at the lines that they came from
+  fn expanded(&mut self, file_name: &str, expansion: &Expansion) {
+    self.summary.files += expansion.file_count() - 1;
 
-  if print_ast {
-    println!("The AST of the program is:\n{:#?}", &ast);
+    if expansion.has_includes() {
+      self
+        .renderer
+        .expansions
+        .push((file_name.to_string(), expansion.clone()));
+    }
   }
 
-  // Run the program
-  let mut interpreter = Interpreter::new(&src, ast);
+  fn imported(&mut self, _file_name: &str) {
+    self.summary.files += 1;
+  }
 
-  match interpreter.evaluate() {
-    Ok(()) => {
-      println!("The result of the program is:\n");
+  fn parsed(&mut self, ast: &Node) {
+    if let Node::Program(statements) = ast {
+      self.summary.statements += statements.len();
+    }
 
-      interpreter.dump();
+    self.summary.ast_bytes += ast.deep_size().bytes();
+
+    match self.ast_format.take() {
+      Some(AstFormat::Debug) => println!("The AST of the program is:\n{:#?}", ast),
+      Some(AstFormat::SExpr) => println!("The AST of the program is:\n{}", ast.sexpr()),
+      None => {}
     }
-    Err(errors) => handle_error(&file_name, errors),
   }
 
+  fn warned(&mut self, file_name: &str, src: &str, warnings: &[DiagnosticError]) {
+    self.summary.record_diagnostics(warnings);
+    self.renderer.print(file_name, src, warnings);
+    eprintln!();
+  }
+
+  fn evaluated(&mut self, operations: usize) {
+    self.summary.operations += operations;
+  }
+}
+
+// Parses the value of a limit passed on the command line, exiting if it's invalid.
+fn parse_limit(flag: &str, value: &str) -> usize {
+  value.parse().unwrap_or_else(|_| {
+    eprintln!(
+      "invalid value `{}` for `{}`, expected a number.",
+      value, flag
+    );
+    std::process::exit(1);
+  })
+}
+
+// Parses the value of `--lang-version`, exiting if it isn't a version.
+fn parse_version(value: &str) -> LangVersion {
+  LangVersion::parse(value).unwrap_or_else(|| {
+    eprintln!("invalid version `{}`, expected `1` or `2`.", value);
+    std::process::exit(1);
+  })
+}
+
+// Runs each line read from stdin in the same interpreter, printing the variables that it
+// assigned. Errors are reported without ending the session.
+fn run_repl(
+  mut interpreter: Interpreter,
+  mut runner: Runner<Reporter>,
+  group_digits: bool,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+  const FILE_NAME: &str = "<repl>";
+
+  let stdin = io::stdin();
+  let mut line = String::new();
+
+  loop {
+    print!("> ");
+    io::stdout().flush()?;
+
+    line.clear();
+
+    if stdin.read_line(&mut line)? == 0 {
+      println!();
+
+      return Ok(());
+    }
+
+    if line.trim().is_empty() {
+      continue;
+    }
+
+    if let Some(command) = line.trim().strip_prefix(':') {
+      let changed = match command {
+        "undo" => interpreter.undo(),
+        "redo" => interpreter.redo(),
+        _ => {
+          eprintln!(
+            "unknown command `:{}`, expected `:undo` or `:redo`.",
+            command
+          );
+          continue;
+        }
+      };
+
+      match changed {
+        Some(names) => {
+          for name in names {
+            match interpreter.variable(&name) {
+              Some(value) => println!("{} => {}", name, format_value(value, group_digits)),
+              None => println!("{} is unset", name),
+            }
+          }
+        }
+        None => eprintln!("there's nothing to {}.", command),
+      }
+
+      continue;
+    }
+
+    let parsed = runner
+      .lex(FILE_NAME, &line)
+      .and_then(|tokens| runner.parse(FILE_NAME, &line, tokens));
+    let (ast, pragmas) = match parsed {
+      Ok(parsed) => parsed,
+      Err(errors) => {
+        runner.observer().renderer.print(FILE_NAME, &line, &errors);
+        continue;
+      }
+    };
+    let ast = filter_statements(ast, &runner.settings().flags);
+
+    if pragmas.modulus.is_some() {
+      interpreter = interpreter.with_modulus(pragmas.modulus);
+    }
+
+    let assigned = ast
+      .assigned_variables()
+      .into_iter()
+      .map(str::to_string)
+      .collect::<Vec<_>>();
+
+    ice::set_phase(Phase::Evaluating);
+
+    interpreter.load(&line, ast);
+    let result = interpreter.evaluate();
+
+    if !interpreter.warnings().is_empty() {
+      runner
+        .observer()
+        .renderer
+        .print(FILE_NAME, &line, interpreter.warnings());
+    }
+
+    match result {
+      Ok(()) => {
+        for name in assigned {
+          let value = interpreter.variable(&name).unwrap();
+
+          println!("{} => {}", name, format_value(value, group_digits));
+        }
+      }
+      Err(errors) => runner.observer().renderer.print(FILE_NAME, &line, &errors),
+    }
+  }
+}
+
+// Prints the fully-substituted expression of a variable, for `why <file> <variable>`.
+fn why(args: &[String]) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let (file_name, name) = match args {
+    [file_name, name] => (file_name, name),
+    _ => {
+      eprintln!("expected `why <file> <variable>`.");
+      std::process::exit(1);
+    }
+  };
+  let src = fs::read_to_string(file_name)?;
+  let (ast, pragmas) = parse_file(file_name, &src);
+  let ast = filter_statements(ast, &[]);
+  let expr = substitute_variable(&ast, name, &[]);
+
+  ice::set_phase(Phase::Evaluating);
+
+  let mut interpreter = Interpreter::new(&src, ast).with_modulus(pragmas.modulus);
+
+  if let Err(errors) = interpreter.evaluate() {
+    handle_error(file_name, &src, errors);
+  }
+
+  match (expr, interpreter.variable(name)) {
+    (Ok(expr), Some(value)) => println!("{} = {}\n{} = {}", name, expr, name, value),
+    (Err(reason), _) => unsubstituted(name, reason),
+    (Ok(_), None) => unsubstituted(name, Unsubstituted::Unassigned),
+  }
+
+  Ok(())
+}
+
+// Prints the derivative of a variable, for `derive <file> <variable> <with respect to>`.
+fn derive(args: &[String]) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let (file_name, name, wrt) = match args {
+    [file_name, name, wrt] => (file_name, name, wrt),
+    _ => {
+      eprintln!("expected `derive <file> <variable> <variable>`.");
+      std::process::exit(1);
+    }
+  };
+  let src = fs::read_to_string(file_name)?;
+  let (ast, _) = parse_file(file_name, &src);
+  let ast = filter_statements(ast, &[]);
+
+  ice::set_phase(Phase::Evaluating);
+
+  match substitute_variable(&ast, name, &[wrt]) {
+    Ok(expr) => match derivative(&expr, wrt) {
+      Some(derivative) => println!("d{}/d{} = {}", name, wrt, derivative),
+      None => {
+        eprintln!(
+          "the derivative of `{}` can't be taken, since it uses a function, or an exponent, \
+           shift, `match` value, or index depends on `{}`.",
+          name, wrt
+        );
+        std::process::exit(1);
+      }
+    },
+    Err(reason) => unsubstituted(name, reason),
+  }
+
+  Ok(())
+}
+
+// Reports why the expression of a variable couldn't be found, and exits.
+fn unsubstituted(name: &str, reason: Unsubstituted) -> ! {
+  match reason {
+    Unsubstituted::Unassigned => eprintln!("the variable `{}` is never assigned.", name),
+    Unsubstituted::Read => eprintln!(
+      "the value of `{}` is read in, so it isn't known ahead of time.",
+      name
+    ),
+    Unsubstituted::ControlFlow => eprintln!(
+      "the value of `{}` depends on control flow, since it's last assigned in an `if`, a `for` \
+       loop, or a block.",
+      name
+    ),
+  }
+
+  std::process::exit(1);
+}
+
+// Prints the source with the variable inlined into its uses, for
+// `refactor inline <file> <variable>`.
+fn refactor(args: &[String]) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let (file_name, name) = match args {
+    [refactor, file_name, name] if refactor == "inline" => (file_name, name),
+    _ => {
+      eprintln!("expected `refactor inline <file> <variable>`.");
+      std::process::exit(1);
+    }
+  };
+  let src = fs::read_to_string(file_name)?;
+  let mut runner = Runner::new(Settings::default(), Reporter::default());
+  let tokens = runner
+    .lex(file_name, &src)
+    .unwrap_or_else(|err| handle_error(file_name, &src, err));
+  let (ast, _) = runner
+    .parse(file_name, &src, tokens.clone())
+    .unwrap_or_else(|err| handle_error(file_name, &src, err));
+
+  match inline(&src, &tokens, &ast, name) {
+    Ok(inlined) => print!("{}", inlined),
+    Err(err) => handle_error(file_name, &src, vec![err]),
+  }
+
+  Ok(())
+}
+
+// Prints the smallest input that still satisfies the predicate, for
+// `minimize <file> --predicate <predicate>`.
+fn minimize_file(args: &[String]) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut file_name = None;
+  let mut predicate = None;
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    if let Some(pred) = arg.strip_prefix("--predicate=") {
+      predicate = Some(pred.to_string());
+    } else if arg == "--predicate" {
+      predicate = args.next().cloned();
+    } else if file_name.is_none() {
+      file_name = Some(arg);
+    }
+  }
+
+  let (file_name, predicate) = match (file_name, predicate.as_deref().and_then(Predicate::parse)) {
+    (Some(file_name), Some(predicate)) => (file_name, predicate),
+    _ => {
+      eprintln!("expected `minimize <file> --predicate <exit-code=<code>|stderr=<text>>`.");
+      std::process::exit(1);
+    }
+  };
+  let src = fs::read_to_string(file_name)?;
+  let exec_path = env::current_exe()?;
+
+  if !predicate.holds(&exec_path, &src) {
+    eprintln!("the predicate doesn't hold for `{}`.", file_name);
+    std::process::exit(1);
+  }
+
+  println!(
+    "{}",
+    minimize(&src, |candidate| predicate.holds(&exec_path, candidate))
+  );
+
   Ok(())
 }
 
+// Checks that the diagnostics of each file match its annotations, for `test --ui <file>...`.
+fn test_files(args: &[String]) -> Result<(), Box<dyn Error + Send + Sync>> {
+  let files = match args {
+    [mode, files @ ..] if mode == "--ui" && !files.is_empty() => files,
+    _ => {
+      eprintln!("expected `test --ui <file>...`.");
+      std::process::exit(1);
+    }
+  };
+  let mut failed = 0;
+
+  for file_name in files {
+    let src = fs::read_to_string(file_name)?;
+    let mismatches = ui::check(file_name, &src);
+
+    if mismatches.is_empty() {
+      println!("test {} ... ok", file_name);
+      continue;
+    }
+
+    failed += 1;
+    println!("test {} ... FAILED", file_name);
+
+    for mismatch in mismatches {
+      println!("  {}:{}", file_name, mismatch);
+    }
+  }
+
+  println!(
+    "\ntest result: {} passed, {} failed.",
+    files.len() - failed,
+    failed
+  );
+
+  if failed != 0 {
+    std::process::exit(1);
+  }
+
+  Ok(())
+}
+
+// Lexes and parses the file with the default settings, exiting if it has errors.
+fn parse_file(file_name: &str, src: &str) -> (Node, Pragmas) {
+  let mut runner = Runner::new(Settings::default(), Reporter::default());
+
+  runner
+    .lex(file_name, src)
+    .and_then(|tokens| runner.parse(file_name, src, tokens))
+    .unwrap_or_else(|err| handle_error(file_name, src, err))
+}
+
 fn print_help(exec_path: &str) -> ! {
   let path = Path::new(exec_path);
 
   println!(
     "An interpreter for a toy language.\n\n\
-USAGE: {} [OPTIONS] <file>\n\nOPTIONS:\n\
+USAGE: {0} [OPTIONS] <file>\n       {0} [OPTIONS] --repl\n       {0} why <file> <variable>\n       {0} derive <file> <variable> <variable>\n       \
+{0} minimize <file> --predicate <exit-code=<code>|stderr=<text>>\n       {0} test --ui <file>...\n       \
+{0} refactor inline <file> <variable>\n\n\
+COMMANDS:\n\
+\twhy\n\t\tPrints a variable's expression with every variable substituted by its definition.\n\n\
+\tderive\n\t\tPrints the derivative of the first variable with respect to the second.\n\n\
+\tminimize\n\t\tShrinks the file to the smallest input that still satisfies the predicate.\n\n\
+\ttest --ui\n\t\tChecks that each file reports exactly the diagnostics its `/*~ ERROR <text> */` and \
+`/*~ WARNING <text> */` comments expect, on their lines. Each `^` after the `~` expects it a line higher.\n\n\
+\trefactor inline\n\t\tPrints the file with the variable's uses replaced by its definition, which is removed. \
+The variable has to be assigned once, outside of any block, and nothing it reads can be assigned after it.\n\n\
+OPTIONS:\n\
+\t--repl\n\t\tReads statements from stdin a line at a time, printing the variables each one assigns. \
+This is the default when no file is passed. `:undo` reverts the last line that changed a variable, and `:redo` \
+reapplies it.\n\n\
 \t--print-tokens, -a\n\t\tPrints the lexed tokens of the source file.\n\n\
 \t--print-ast, -t\n\t\tPrints the AST of the source file.\n\n\
+\t--ast-format=<debug|sexpr>\n\t\tPrints the AST of the source file in the given format.\n\n\
+\t--show-steps\n\t\tPrints how each assignment's expression is reduced to its value.\n\n\
+\t--wrapping\n\t\tWraps around on overflow, instead of reporting an error.\n\n\
+\t--saturating\n\t\tClamps to the integer bounds on overflow, instead of reporting an error.\n\n\
+\t--output <file>, -o <file>\n\t\tWrites the variables to the file, instead of printing them.\n\n\
+\t--max-nodes=<count>\n\t\tRejects programs with more AST nodes than the count, before running them.\n\n\
+\t--max-expression-nodes=<count>\n\t\tRejects programs with an expression that has more AST nodes than the count.\n\n\
+\t--max-depth=<count>\n\t\tRejects programs with expressions or blocks nested more levels deep than the count, while parsing them. Defaults to 128.\n\n\
+\t--max-call-depth=<count>\n\t\tReports calls of functions nested more levels deep than the count while running. Defaults to 256.\n\n\
+\t--asi\n\t\tInfers a missing `;` at the end of a line, with a warning, when the next line \
+can't continue the statement.\n\n\
+\t--dialect=<semicolons|lines>\n\t\tSets what ends a statement. With `lines`, a line break ends a statement \
+without a warning, so `;` is optional.\n\n\
+\t--lang-version <1|2>, --lang-version=<1|2>\n\t\tSets the version of the language. Version 1 doesn't have \
+comments, keywords, floats, or the `/`, `%`, and `**` operators. A `#[lang: <1|2>]` pragma overrides it.\n\n\
+\t--summary\n\t\tPrints counts of what happened during the run, like the number of statements and \
+diagnostics.\n\n\
+\t--context <lines>, --context=<lines>\n\t\tPrints the source around each diagnostic, with the given number of lines \
+before and after its line.\n\n\
+\t--group-digits\n\t\tPrints values with `_` between every group of three digits, eg `1_234_567`.\n\n\
+\t--allow=<capability>,...\n\t\tGrants the program capabilities, which it has none of by default. `read` \
+is needed by `read` statements, `import` by imports, and `include` by `#include` lines.\n\n\
+\t--flag <flag>, --flag=<flag>\n\t\tRuns the statements marked with `#[if: <flag>]`.\n\n\
+\t--lint=<name>:<allow|warn|deny>\n\t\tSets the level of a lint. The lints are `leading-zeros`\n\t\tand `non-exhaustive-match`.\n\n\
 \t--print-help, -h\n\t\tPrints this message.",
     path.file_name().unwrap().to_string_lossy()
   );
@@ -95,45 +770,78 @@ USAGE: {} [OPTIONS] <file>\n\nOPTIONS:\n\
   std::process::exit(0)
 }
 
-fn get_lexer_errors(src: &str, tokens: &[Token]) -> Vec<DiagnosticError> {
-  let mut errors = Vec::new();
+fn handle_error(file_name: &str, src: &str, errors: Vec<DiagnosticError>) -> ! {
+  fail(&Renderer::default(), file_name, src, errors, None)
+}
 
-  for tok in tokens {
-    if matches!(tok.kind(), TokenKind::Unknown) {
-      let info = token_info(src, tok);
+// Prints the errors, along with the summary of the run if there is one, and exits.
+fn fail(
+  renderer: &Renderer,
+  file_name: &str,
+  src: &str,
+  errors: Vec<DiagnosticError>,
+  summary: Option<Summary>,
+) -> ! {
+  renderer.print(file_name, src, &errors);
 
-      errors.push(DiagnosticError::new(
-        format!("The token, `{}`, is invalid.", info.literal),
-        info.line,
-        info.column,
-      ))
-    }
+  if let Some(mut summary) = summary {
+    summary.record_diagnostics(&errors);
+    eprintln!("\nThe summary of the run is:\n\n{}", summary);
   }
 
-  errors
+  std::process::exit(1)
 }
 
-fn handle_error(file_name: &str, errors: Vec<DiagnosticError>) -> ! {
-  let mut index = 1;
-  let num_errors = errors.len();
-  eprintln!("The program has {} error(s):\n", num_errors);
+// Prints diagnostics, along with the lines of the source around them if `--context` was passed.
+#[derive(Default)]
+struct Renderer {
+  // The number of lines around a diagnostic's line to print it with. Diagnostics are printed
+  // without their source when it isn't passed
+  context: Option<usize>,
+  // The files that had others included into them, by the name that they're reported with, so
+  // that their diagnostics are printed at the lines that they came from
+  expansions: Vec<(String, Expansion)>,
+}
 
-  for err in errors.into_iter() {
-    eprintln!(
-      "{:>2}) {}:{}:{}\n\t{}",
-      index,
-      file_name,
-      err.line(),
-      err.column(),
-      err
-    );
+impl Renderer {
+  // Prints the diagnostics, which are expected to all have the same severity. The diagnostics of
+  // a source that included other files are printed at the file and line that they came from.
+  fn print(&self, file_name: &str, src: &str, diagnostics: &[DiagnosticError]) {
+    let expansion = self
+      .expansions
+      .iter()
+      .find(|(name, _)| name == file_name)
+      .map(|(_, expansion)| expansion);
+    let num_diagnostics = diagnostics.len();
+    let severity = diagnostics
+      .first()
+      .map_or(Severity::Error, DiagnosticError::severity);
+    eprintln!("The program has {} {}(s):\n", num_diagnostics, severity);
 
-    if index != num_errors {
-      eprintln!();
-    }
+    for (index, diagnostic) in (1..).zip(diagnostics) {
+      let (file_name, src, line) = match expansion {
+        Some(expansion) => expansion.locate(diagnostic.line()),
+        None => (file_name, src, diagnostic.line()),
+      };
 
-    index += 1;
-  }
+      eprintln!(
+        "{:>2}) {}:{}:{}\n\t{}",
+        index,
+        file_name,
+        line,
+        diagnostic.column(),
+        diagnostic
+      );
 
-  std::process::exit(1)
+      if let Some(context) = self.context {
+        let rendered = snippet::render(src, line, diagnostic.column(), context);
+
+        eprintln!("\n{}", rendered);
+      }
+
+      if index != num_diagnostics {
+        eprintln!();
+      }
+    }
+  }
 }
@@ -0,0 +1,203 @@
+use crate::{
+  node::{Node, Pattern, Segment},
+  value::Value,
+};
+use std::mem::size_of;
+
+/// An estimate of the memory held by a tree or by the variables of an interpreter.
+///
+/// This only counts what the crate stores, not the overhead of the allocator or of spare
+/// capacity, so it's meant for enforcing quotas rather than exact accounting.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+  /// The number of nodes.
+  pub nodes: usize,
+  /// The bytes of the strings, like the names of identifiers.
+  pub string_bytes: usize,
+  /// The bytes of the values, like literals or the values of variables, including the
+  /// characters of strings.
+  pub value_bytes: usize,
+}
+
+impl MemoryUsage {
+  /// Returns the estimated number of bytes in total.
+  pub fn bytes(&self) -> usize {
+    self.nodes * size_of::<Node>() + self.string_bytes + self.value_bytes
+  }
+}
+
+/// Returns the bytes of the value, including the characters of a string, the elements of an
+/// array or tuple, the names and values of a struct's fields, and the body and captured variables
+/// of a function.
+pub fn value_bytes(value: &Value) -> usize {
+  match value {
+    Value::Str(value) => size_of::<Value>() + value.len(),
+    Value::Array(values) | Value::Tuple(values) => {
+      size_of::<Value>() + values.iter().map(value_bytes).sum::<usize>()
+    }
+    Value::Struct(name, fields) => {
+      size_of::<Value>()
+        + name.len()
+        + fields
+          .iter()
+          .map(|(field, value)| field.len() + value_bytes(value))
+          .sum::<usize>()
+    }
+    Value::Function(function) => {
+      size_of::<Value>()
+        + function.body.deep_size().bytes()
+        + function
+          .captured
+          .iter()
+          .map(|(name, value)| name.len() + value_bytes(value))
+          .sum::<usize>()
+    }
+    _ => size_of::<Value>(),
+  }
+}
+
+impl Node {
+  /// Returns an estimate of the memory held by this tree, including this node.
+  pub fn deep_size(&self) -> MemoryUsage {
+    let mut usage = MemoryUsage::default();
+    add_size(self, &mut usage);
+
+    usage
+  }
+}
+
+fn add_size(node: &Node, usage: &mut MemoryUsage) {
+  usage.nodes += 1;
+
+  match node {
+    Node::Program(nodes) | Node::Block(nodes) => {
+      for node in nodes {
+        add_size(node, usage);
+      }
+    }
+    Node::If(_, condition, then, otherwise) => {
+      add_size(condition, usage);
+      add_size(then, usage);
+
+      if let Some(otherwise) = otherwise {
+        add_size(otherwise, usage);
+      }
+    }
+    Node::For(_, init, condition, step, body) => {
+      for node in init.iter().chain([condition, body]).chain(step) {
+        add_size(node, usage);
+      }
+    }
+    Node::ConstAssignment(_, ident, expr) => {
+      usage.string_bytes += ident.literal.len();
+      add_size(expr, usage);
+    }
+    Node::MultiAssignment(idents, values) => {
+      for ident in idents {
+        usage.string_bytes += ident.literal.len();
+      }
+
+      for value in values {
+        add_size(value, usage);
+      }
+    }
+    Node::Assignment(lhs, rhs) | Node::Term(lhs, _, rhs) | Node::Index(_, lhs, rhs) => {
+      add_size(lhs, usage);
+      add_size(rhs, usage);
+    }
+    Node::BlockStatement(_, block) => add_size(block, usage),
+    Node::Conditional(flag, statement) => {
+      usage.string_bytes += flag.len();
+      add_size(statement, usage);
+    }
+    Node::Print(_, node)
+    | Node::Expression(node)
+    | Node::Fact(node)
+    | Node::UnaryOperator(_, node)
+    | Node::Field(_, node, _) => add_size(node, usage),
+    Node::NamedField(_, node, field) => {
+      usage.string_bytes += field.literal.len();
+      add_size(node, usage);
+    }
+    Node::Import(_, path) => usage.string_bytes += path.len(),
+    Node::StructDeclaration(_, name, fields) => {
+      for ident in std::iter::once(name).chain(fields) {
+        usage.string_bytes += ident.literal.len();
+      }
+    }
+    Node::Struct(name, fields) => {
+      usage.string_bytes += name.literal.len();
+
+      for (field, value) in fields {
+        usage.string_bytes += field.literal.len();
+        add_size(value, usage);
+      }
+    }
+    Node::Read(_, ident) | Node::Identifier(ident) => usage.string_bytes += ident.literal.len(),
+    Node::Literal(lit) => usage.value_bytes += value_bytes(&lit.value),
+    Node::Let(ident, value, body) => {
+      usage.string_bytes += ident.literal.len();
+      add_size(value, usage);
+      add_size(body, usage);
+    }
+    Node::Lambda(_, params, body) => {
+      for param in params {
+        usage.string_bytes += param.literal.len();
+      }
+
+      add_size(body, usage);
+    }
+    Node::Call(ident, args, _) => {
+      usage.string_bytes += ident.literal.len();
+
+      for arg in args {
+        add_size(arg, usage);
+      }
+    }
+    Node::Match(_, value, arms) => {
+      add_size(value, usage);
+
+      for (pattern, arm) in arms {
+        if let Pattern::Literal(value) = pattern {
+          usage.value_bytes += value_bytes(value);
+        }
+
+        add_size(arm, usage);
+      }
+    }
+    Node::Interpolation(segments) => {
+      for segment in segments {
+        match segment {
+          Segment::Text(text) => usage.string_bytes += text.len(),
+          Segment::Expression(expr) => add_size(expr, usage),
+        }
+      }
+    }
+    Node::Array(elements) | Node::Tuple(elements) => {
+      for element in elements {
+        add_size(element, usage);
+      }
+    }
+    Node::Return(_, value) => {
+      if let Some(value) = value {
+        add_size(value, usage);
+      }
+    }
+    Node::Break(_) | Node::Continue(_) => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Parser;
+
+  #[test]
+  fn counts_strings_and_values() {
+    let program = Parser::new("abc = 1 + xy;").parse().unwrap();
+    let usage = program.deep_size();
+
+    assert_eq!(usage.nodes, program.node_count());
+    assert_eq!(usage.string_bytes, 5);
+    assert_eq!(usage.value_bytes, std::mem::size_of::<crate::Value>());
+  }
+}
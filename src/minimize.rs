@@ -0,0 +1,179 @@
+use std::{
+  env, fs,
+  path::Path,
+  process::{Command, Stdio},
+};
+use toy_language::{lexer::Lexer, token::TokenKind};
+
+/// The environment variable that stops the interpreter from writing bug reports when it panics.
+///
+/// Minimizing a panic runs the interpreter many times, which would otherwise leave a report
+/// behind for every run.
+pub const NO_BUG_REPORT_VAR: &str = "TOY_LANGUAGE_NO_BUG_REPORT";
+
+/// A condition that the input has to keep satisfying while it's minimized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Predicate {
+  /// The interpreter exits with this code, eg `exit-code=101` for a panic.
+  ExitCode(i32),
+  /// The interpreter's stderr contains this text, eg `stderr=has not yet been initialized`.
+  Stderr(String),
+}
+
+impl Predicate {
+  /// Parses a predicate of the form `exit-code=<code>` or `stderr=<text>`.
+  pub fn parse(predicate: &str) -> Option<Self> {
+    match predicate.split_once('=')? {
+      ("exit-code", code) => code.parse().ok().map(Predicate::ExitCode),
+      ("stderr", text) => Some(Predicate::Stderr(text.to_string())),
+      _ => None,
+    }
+  }
+
+  /// Runs the interpreter at `exec_path` on the source and returns whether this predicate holds.
+  pub fn holds(&self, exec_path: &Path, src: &str) -> bool {
+    let path = env::temp_dir().join(format!("toy_language-minimize-{}.txt", std::process::id()));
+
+    if fs::write(&path, src).is_err() {
+      return false;
+    }
+
+    let output = Command::new(exec_path)
+      .arg(&path)
+      .env(NO_BUG_REPORT_VAR, "1")
+      .stdin(Stdio::null())
+      .output();
+
+    let _ = fs::remove_file(&path);
+
+    match (self, output) {
+      (Predicate::ExitCode(code), Ok(output)) => output.status.code() == Some(*code),
+      (Predicate::Stderr(text), Ok(output)) => {
+        String::from_utf8_lossy(&output.stderr).contains(text.as_str())
+      }
+      (_, Err(_)) => false,
+    }
+  }
+}
+
+/// Shrinks the source while `test` keeps returning `true` for it.
+///
+/// Whole statements are removed first, then individual tokens of what's left. The source
+/// itself is expected to pass `test`.
+pub fn minimize(src: &str, mut test: impl FnMut(&str) -> bool) -> String {
+  let statements = split_statements(src);
+  let statements = ddmin(statements, |units| test(&units.join("\n")));
+  let src = statements.join("\n");
+
+  let tokens = Lexer::new(&src)
+    .lex()
+    .iter()
+    .filter(|tok| !matches!(tok.kind(), TokenKind::EndOfFile))
+    .map(|tok| src[tok.range()].to_string())
+    .collect::<Vec<_>>();
+
+  ddmin(tokens, |units| test(&units.join(" "))).join(" ")
+}
+
+// Splits the source into statements, each ending with its `;`.
+fn split_statements(src: &str) -> Vec<String> {
+  let tokens = Lexer::new(src).lex();
+  let mut statements = Vec::new();
+  let mut start = None;
+
+  for token in tokens
+    .iter()
+    .filter(|tok| !matches!(tok.kind(), TokenKind::EndOfFile))
+  {
+    let statement_start = *start.get_or_insert(token.range().start);
+
+    if matches!(token.kind(), TokenKind::Semicolon) {
+      statements.push(src[statement_start..token.range().end].to_string());
+      start = None;
+    }
+  }
+
+  // Keep anything after the last semicolon too
+  if let Some(start) = start {
+    statements.push(src[start..].trim_end().to_string());
+  }
+
+  statements
+}
+
+// The delta debugging algorithm: tries removing ever smaller chunks of the units, keeping any
+// removal that still passes the test, until no single unit can be removed.
+fn ddmin(mut units: Vec<String>, mut test: impl FnMut(&[String]) -> bool) -> Vec<String> {
+  let mut chunks = 2;
+
+  while units.len() >= 2 {
+    let chunk_size = units.len().div_ceil(chunks);
+    let mut reduced = false;
+
+    for start in (0..units.len()).step_by(chunk_size) {
+      let end = (start + chunk_size).min(units.len());
+      let subset = units[start..end].to_vec();
+      let complement = [&units[..start], &units[end..]].concat();
+
+      if test(&subset) {
+        units = subset;
+        chunks = 2;
+        reduced = true;
+        break;
+      }
+
+      if test(&complement) {
+        units = complement;
+        chunks = (chunks - 1).max(2);
+        reduced = true;
+        break;
+      }
+    }
+
+    if !reduced {
+      if chunks >= units.len() {
+        break;
+      }
+
+      chunks = (chunks * 2).min(units.len());
+    }
+  }
+
+  units
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keeps_failing_statement() {
+    let src = "a = 1;\nb = 2;\nc = d + 3;\ne = 4;";
+    let minimized = minimize(src, |candidate| candidate.contains('d'));
+
+    assert_eq!(minimized, "d");
+  }
+
+  #[test]
+  fn keeps_statement_shape() {
+    let src = "a = 1;\nb = 2;\nc = (1 + 2) * 3;\ne = 4;";
+    let minimized = minimize(src, |candidate| {
+      candidate.contains('(') && candidate.contains(')')
+    });
+
+    assert_eq!(minimized, "( )");
+  }
+
+  #[test]
+  fn predicates() {
+    assert_eq!(
+      Predicate::parse("exit-code=101"),
+      Some(Predicate::ExitCode(101))
+    );
+    assert_eq!(
+      Predicate::parse("stderr=invalid"),
+      Some(Predicate::Stderr("invalid".to_string()))
+    );
+    assert_eq!(Predicate::parse("exit-code=abc"), None);
+  }
+}
@@ -1,12 +1,62 @@
+use crate::{
+  token::Token,
+  value::{escape, Value},
+};
 use std::ops::Range;
 
 /// The nodes of this language.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Node {
   /// Vec of `Assignment` nodes.
   Program(Vec<Node>),
   /// An `Identifier` node and an `Expression` node.
+  ///
+  /// The expression can be another assignment, whose value it has, eg `a = b = 1;`.
   Assignment(Box<Node>, Box<Node>),
+  /// A declaration of a constant, eg `const PI = 3;`, which is assigned like a variable but can't
+  /// be assigned again.
+  ///
+  /// The `const` keyword is kept for diagnostics.
+  ConstAssignment(Token, IdentifierNode, Box<Node>),
+  /// An assignment to several variables at once, eg `a, b = b, a;`, where every value is
+  /// evaluated before any variable is set.
+  ///
+  /// There's a value for each variable, in the same order.
+  MultiAssignment(Vec<IdentifierNode>, Vec<Node>),
+  /// A statement that writes the value of its expression, eg `print x + 1;`.
+  ///
+  /// The `print` keyword is kept for diagnostics.
+  Print(Token, Box<Node>),
+  /// A statement that reads an integer from the input into the variable, eg `read x;`.
+  ///
+  /// The `read` keyword is kept for diagnostics.
+  Read(Token, IdentifierNode),
+  /// An `if (Exp) { ... } else { ... }` statement, which runs the first block if its expression
+  /// isn't zero, and the second block otherwise, if there is one.
+  ///
+  /// The `if` keyword is kept for diagnostics.
+  If(Token, Box<Node>, Box<Node>, Option<Box<Node>>),
+  /// A `for (Assignment; Exp; Assignment) { ... }` loop, which runs the first assignment once,
+  /// then the block and the second assignment for as long as its expression isn't zero.
+  ///
+  /// Either assignment can be left out. The `for` keyword is kept for diagnostics.
+  For(
+    Token,
+    Option<Box<Node>>,
+    Box<Node>,
+    Option<Box<Node>>,
+    Box<Node>,
+  ),
+  /// A list of statements surrounded by braces.
+  ///
+  /// The variables first assigned within it, and its constants, only exist until its end.
+  Block(Vec<Node>),
+  /// A [Node::Block] used as a statement on its own, eg `{ t = x; x = y; y = t; }`.
+  ///
+  /// The `{` is kept for diagnostics.
+  BlockStatement(Token, Box<Node>),
+  /// A statement that's only kept if the flag is passed with `--flag`, eg `#[if: DEBUG] x = 1;`.
+  Conditional(String, Box<Node>),
   /// A node containing a `Term` node.
   Expression(Box<Node>),
   /// A node applying an operation to two other nodes.
@@ -19,6 +69,68 @@ pub enum Node {
   Identifier(IdentifierNode),
   /// A node containing a `Literal` node.
   Literal(LiteralNode),
+  /// A `let` expression, whose `Identifier` is bound to the first node only within the second.
+  Let(IdentifierNode, Box<Node>, Box<Node>),
+  /// A call of a function with its arguments, eg `f(x, 1)`.
+  ///
+  /// The range covers the whole call, from the function's name to the `)`. The function is
+  /// either a built-in one or one held by a variable.
+  Call(IdentifierNode, Vec<Node>, Range<usize>),
+  /// A function with its parameters and body, eg `|x, y| x * y`, which captures the values of
+  /// the variables that it uses when it's evaluated.
+  ///
+  /// The opening `|` or `||` is kept for diagnostics.
+  Lambda(Token, Vec<IdentifierNode>, Box<Node>),
+  /// A `match` expression, whose value is that of the first arm whose pattern matches the
+  /// value of its expression, eg `match x { 0 => 1, _ => x }`.
+  ///
+  /// The `match` keyword is kept for diagnostics.
+  Match(Token, Box<Node>, Vec<(Pattern, Node)>),
+  /// A string with expressions spliced into it, eg `"x is ${x}!"`, whose value is its pieces
+  /// joined together.
+  Interpolation(Vec<Segment>),
+  /// An array of the values of its expressions, eg `[1, x + 1]`.
+  Array(Vec<Node>),
+  /// An element of an array, eg `a[i]`, with the array and then the index.
+  ///
+  /// The `[` is kept for diagnostics.
+  Index(Token, Box<Node>, Box<Node>),
+  /// A tuple of the values of its expressions, eg `(1, x + 1)` or `(x,)`.
+  Tuple(Vec<Node>),
+  /// A field of a tuple, eg `t.0`, with the tuple and then the index of the field.
+  ///
+  /// The `.` is kept for diagnostics.
+  Field(Token, Box<Node>, usize),
+  /// A declaration of a struct with the names of its fields, eg `struct Point { x, y }`.
+  ///
+  /// The `struct` keyword is kept for diagnostics.
+  StructDeclaration(Token, IdentifierNode, Vec<IdentifierNode>),
+  /// A value of a struct, eg `Point { x: 1, y: 2 }`, with the name of the struct and the value of
+  /// each field, in the order that they're written.
+  Struct(IdentifierNode, Vec<(IdentifierNode, Node)>),
+  /// A field of a struct, eg `p.x`, with the struct and then the name of the field.
+  ///
+  /// The `.` is kept for diagnostics.
+  NamedField(Token, Box<Node>, IdentifierNode),
+  /// A statement that runs another file where it's written, with the path of the file, eg
+  /// `import "other.toy";`.
+  ///
+  /// The `import` keyword is kept for diagnostics. These are run by whatever loads the files, so
+  /// evaluating one on its own is an error.
+  Import(Token, String),
+  /// A statement that returns from a function, with a value if it has one, eg `return x;`.
+  ///
+  /// The `return` keyword is kept for diagnostics. Like calls, these aren't parsed yet.
+  Return(Token, Option<Box<Node>>),
+  /// A statement that ends the innermost loop, eg `break;`.
+  ///
+  /// The `break` keyword is kept for diagnostics.
+  Break(Token),
+  /// A statement that skips the rest of the innermost loop's body, going on to its step, eg
+  /// `continue;`.
+  ///
+  /// The `continue` keyword is kept for diagnostics.
+  Continue(Token),
 }
 
 /// The operators of this language.
@@ -27,6 +139,41 @@ pub enum Operator {
   Plus,
   Minus,
   Multiply,
+  Divide,
+  Modulo,
+  Power,
+  ShiftLeft,
+  ShiftRight,
+  And,
+  Or,
+  Not,
+  Equal,
+  NotEqual,
+  Less,
+  LessEqual,
+  Greater,
+  GreaterEqual,
+  Coalesce,
+}
+
+/// The pattern of an arm of a [Node::Match].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+  /// Matches values equal to the literal, eg `0`, `-1.5`, or `"yes"`.
+  Literal(Value),
+  /// `_`, which matches every value.
+  Wildcard,
+}
+
+/// A piece of a [Node::Interpolation].
+#[derive(Debug, Clone)]
+pub enum Segment {
+  /// Text that's written as is, with its escapes already replaced.
+  Text(String),
+  /// An expression within `${...}`, whose value is spliced into the string.
+  ///
+  /// A string is spliced as its characters, and a number as it's printed.
+  Expression(Node),
 }
 
 /// An identifier node.
@@ -45,5 +192,958 @@ pub struct IdentifierNode {
 #[derive(Debug, Clone)]
 pub struct LiteralNode {
   /// The number for this node.
-  pub value: isize,
+  pub value: Value,
+}
+
+impl Node {
+  /// Returns a wrapper that formats this node as an s-expression, eg `(assign x (+ 2 (* 3 y)))`.
+  pub fn sexpr(&self) -> SExpr<'_> {
+    SExpr(self)
+  }
+
+  /// Returns the number of nodes in this tree, including this one.
+  pub fn node_count(&self) -> usize {
+    1 + match self {
+      Node::Program(nodes) | Node::Block(nodes) => nodes.iter().map(Node::node_count).sum(),
+      Node::MultiAssignment(idents, values) => {
+        idents.len() + values.iter().map(Node::node_count).sum::<usize>()
+      }
+      Node::If(_, condition, then, otherwise) => {
+        condition.node_count()
+          + then.node_count()
+          + otherwise.as_ref().map_or(0, |node| node.node_count())
+      }
+      Node::For(_, init, condition, step, body) => {
+        init.as_ref().map_or(0, |node| node.node_count())
+          + condition.node_count()
+          + step.as_ref().map_or(0, |node| node.node_count())
+          + body.node_count()
+      }
+      Node::Assignment(lhs, rhs)
+      | Node::Term(lhs, _, rhs)
+      | Node::Let(_, lhs, rhs)
+      | Node::Index(_, lhs, rhs) => lhs.node_count() + rhs.node_count(),
+      Node::Conditional(_, node)
+      | Node::BlockStatement(_, node)
+      | Node::Print(_, node)
+      | Node::ConstAssignment(_, _, node)
+      | Node::Expression(node)
+      | Node::Fact(node)
+      | Node::UnaryOperator(_, node)
+      | Node::Lambda(_, _, node) => node.node_count(),
+      Node::Call(_, args, _) | Node::Array(args) | Node::Tuple(args) => {
+        args.iter().map(Node::node_count).sum()
+      }
+      Node::Field(_, tuple, _) | Node::NamedField(_, tuple, _) => tuple.node_count(),
+      Node::Struct(_, fields) => fields.iter().map(|(_, value)| value.node_count()).sum(),
+      Node::Match(_, value, arms) => {
+        value.node_count() + arms.iter().map(|(_, arm)| arm.node_count()).sum::<usize>()
+      }
+      Node::Interpolation(segments) => segments
+        .iter()
+        .map(|segment| match segment {
+          Segment::Text(_) => 0,
+          Segment::Expression(expr) => expr.node_count(),
+        })
+        .sum(),
+      Node::Return(_, value) => value.as_ref().map_or(0, |node| node.node_count()),
+      Node::Read(..)
+      | Node::StructDeclaration(..)
+      | Node::Import(..)
+      | Node::Identifier(_)
+      | Node::Literal(_)
+      | Node::Break(_)
+      | Node::Continue(_) => 0,
+    }
+  }
+
+  /// Returns the names of the variables that the statements can set, in the order that they
+  /// first appear, including those in blocks.
+  pub fn assigned_variables(&self) -> Vec<&str> {
+    fn collect<'n>(node: &'n Node, names: &mut Vec<&'n str>) {
+      match node {
+        Node::Assignment(..) | Node::ConstAssignment(..) => {
+          let (idents, _) = node.assignment_chain().unwrap();
+
+          for ident in idents {
+            add(&ident.literal, names);
+          }
+        }
+        Node::Read(_, ident) => add(&ident.literal, names),
+        Node::MultiAssignment(idents, _) => {
+          for ident in idents {
+            add(&ident.literal, names);
+          }
+        }
+        Node::Program(nodes) | Node::Block(nodes) => {
+          for node in nodes {
+            collect(node, names);
+          }
+        }
+        Node::If(_, _, then, otherwise) => {
+          collect(then, names);
+
+          if let Some(otherwise) = otherwise {
+            collect(otherwise, names);
+          }
+        }
+        Node::For(_, init, _, step, body) => {
+          for node in init.iter().chain([body]).chain(step) {
+            collect(node, names);
+          }
+        }
+        Node::Conditional(_, statement) | Node::BlockStatement(_, statement) => {
+          collect(statement, names)
+        }
+        _ => {}
+      }
+    }
+
+    fn add<'n>(name: &'n str, names: &mut Vec<&'n str>) {
+      if !names.contains(&name) {
+        names.push(name);
+      }
+    }
+
+    let mut names = Vec::new();
+    collect(self, &mut names);
+
+    names
+  }
+
+  /// Returns the byte offset that the statement starts at, or `None` if this isn't a statement.
+  ///
+  /// The `#[if: ...]` pragmas of a statement aren't part of it.
+  pub fn start(&self) -> Option<usize> {
+    match self {
+      Node::Conditional(_, statement) => statement.start(),
+      Node::Assignment(ident, _) => match &**ident {
+        Node::Identifier(ident) => Some(ident.range.start),
+        _ => None,
+      },
+      Node::MultiAssignment(idents, _) => idents.first().map(|ident| ident.range.start),
+      Node::Print(keyword, _)
+      | Node::ConstAssignment(keyword, ..)
+      | Node::Read(keyword, _)
+      | Node::If(keyword, ..)
+      | Node::For(keyword, ..)
+      | Node::Return(keyword, _)
+      | Node::Break(keyword)
+      | Node::Continue(keyword)
+      | Node::StructDeclaration(keyword, ..)
+      | Node::Import(keyword, _)
+      | Node::BlockStatement(keyword, _) => Some(keyword.range().start),
+      _ => None,
+    }
+  }
+
+  /// Returns the variables that an assignment sets, from the outermost, along with the
+  /// expression that's assigned to all of them, eg `a` and `b` with `1` for `a = b = 1;`.
+  ///
+  /// A constant's declaration is a chain of just the constant. Returns `None` if the node isn't
+  /// an assignment.
+  pub fn assignment_chain(&self) -> Option<(Vec<&IdentifierNode>, &Node)> {
+    if let Node::ConstAssignment(_, ident, expr) = self {
+      return Some((vec![ident], expr));
+    }
+
+    let mut idents = Vec::new();
+    let mut node = self;
+
+    while let Node::Assignment(ident, expr) = node {
+      if let Node::Identifier(ident) = &**ident {
+        idents.push(ident);
+      }
+
+      node = expr;
+    }
+
+    (!idents.is_empty()).then_some((idents, node))
+  }
+
+  /// Removes the [Node::Expression] and [Node::Fact] wrappers of an expression, leaving only
+  /// operations and values.
+  ///
+  /// This makes rewriting expressions easier, but the result can't be printed as source until
+  /// it's passed through [Node::parenthesize].
+  pub fn strip(&self) -> Node {
+    match self {
+      Node::Expression(inner) | Node::Fact(inner) => inner.strip(),
+      Node::Term(lhs, op, rhs) => Node::Term(Box::new(lhs.strip()), *op, Box::new(rhs.strip())),
+      Node::UnaryOperator(op, rhs) => Node::UnaryOperator(*op, Box::new(rhs.strip())),
+      // Bindings are inlined, since they don't change the value of the expression
+      Node::Let(ident, value, body) => body.strip().replace(&ident.literal, &value.strip()),
+      Node::Lambda(pipe, params, body) => {
+        Node::Lambda(pipe.clone(), params.clone(), Box::new(body.strip()))
+      }
+      Node::Call(ident, args, parens) => Node::Call(
+        ident.clone(),
+        args.iter().map(Node::strip).collect(),
+        parens.clone(),
+      ),
+      Node::Match(keyword, value, arms) => Node::Match(
+        keyword.clone(),
+        Box::new(value.strip()),
+        arms
+          .iter()
+          .map(|(pattern, arm)| (pattern.clone(), arm.strip()))
+          .collect(),
+      ),
+      Node::Interpolation(segments) => Node::Interpolation(
+        segments
+          .iter()
+          .map(|segment| segment.map(Node::strip))
+          .collect(),
+      ),
+      Node::Array(elements) => Node::Array(elements.iter().map(Node::strip).collect()),
+      Node::Tuple(elements) => Node::Tuple(elements.iter().map(Node::strip).collect()),
+      Node::Field(dot, tuple, index) => Node::Field(dot.clone(), Box::new(tuple.strip()), *index),
+      Node::Struct(name, fields) => Node::Struct(
+        name.clone(),
+        fields
+          .iter()
+          .map(|(field, value)| (field.clone(), value.strip()))
+          .collect(),
+      ),
+      Node::NamedField(dot, value, field) => {
+        Node::NamedField(dot.clone(), Box::new(value.strip()), field.clone())
+      }
+      Node::Index(bracket, array, index) => Node::Index(
+        bracket.clone(),
+        Box::new(array.strip()),
+        Box::new(index.strip()),
+      ),
+      _ => self.clone(),
+    }
+  }
+
+  // Replaces the variable in a stripped expression with the given node.
+  fn replace(&self, name: &str, with: &Node) -> Node {
+    match self {
+      Node::Identifier(ident) if ident.literal == name => with.clone(),
+      Node::Term(lhs, op, rhs) => Node::Term(
+        Box::new(lhs.replace(name, with)),
+        *op,
+        Box::new(rhs.replace(name, with)),
+      ),
+      Node::UnaryOperator(op, rhs) => Node::UnaryOperator(*op, Box::new(rhs.replace(name, with))),
+      // A parameter with the same name shadows the variable within the body
+      Node::Lambda(pipe, params, body) if !params.iter().any(|param| param.literal == name) => {
+        Node::Lambda(
+          pipe.clone(),
+          params.clone(),
+          Box::new(body.replace(name, with)),
+        )
+      }
+      Node::Call(ident, args, parens) => Node::Call(
+        ident.clone(),
+        args.iter().map(|arg| arg.replace(name, with)).collect(),
+        parens.clone(),
+      ),
+      Node::Match(keyword, value, arms) => Node::Match(
+        keyword.clone(),
+        Box::new(value.replace(name, with)),
+        arms
+          .iter()
+          .map(|(pattern, arm)| (pattern.clone(), arm.replace(name, with)))
+          .collect(),
+      ),
+      Node::Interpolation(segments) => Node::Interpolation(
+        segments
+          .iter()
+          .map(|segment| segment.map(|expr| expr.replace(name, with)))
+          .collect(),
+      ),
+      Node::Array(elements) => Node::Array(
+        elements
+          .iter()
+          .map(|element| element.replace(name, with))
+          .collect(),
+      ),
+      Node::Index(bracket, array, index) => Node::Index(
+        bracket.clone(),
+        Box::new(array.replace(name, with)),
+        Box::new(index.replace(name, with)),
+      ),
+      Node::Tuple(elements) => Node::Tuple(
+        elements
+          .iter()
+          .map(|element| element.replace(name, with))
+          .collect(),
+      ),
+      Node::Field(dot, tuple, index) => {
+        Node::Field(dot.clone(), Box::new(tuple.replace(name, with)), *index)
+      }
+      Node::Struct(struct_name, fields) => Node::Struct(
+        struct_name.clone(),
+        fields
+          .iter()
+          .map(|(field, value)| (field.clone(), value.replace(name, with)))
+          .collect(),
+      ),
+      Node::NamedField(dot, value, field) => Node::NamedField(
+        dot.clone(),
+        Box::new(value.replace(name, with)),
+        field.clone(),
+      ),
+      _ => self.clone(),
+    }
+  }
+
+  /// Adds parentheses to a stripped expression wherever printing it would otherwise change its
+  /// meaning.
+  pub fn parenthesize(self) -> Node {
+    fn wrap_if(node: Node, predicate: impl Fn(&Node) -> bool) -> Node {
+      if predicate(&node) {
+        Node::Fact(Box::new(node))
+      } else {
+        node
+      }
+    }
+
+    // How tightly the node binds, if it's an operation
+    fn binding(node: &Node) -> Option<u8> {
+      match node {
+        Node::Expression(inner) => binding(inner),
+        Node::Term(_, op, _) => Some(op.precedence()),
+        _ => None,
+      }
+    }
+
+    // Whether the node has to be parenthesized before an index or field, eg `(a + b)[0]`
+    fn binds_looser_than_postfix(node: &Node) -> bool {
+      matches!(
+        node,
+        Node::Expression(_) | Node::UnaryOperator(..) | Node::Let(..) | Node::Lambda(..)
+      )
+    }
+
+    // Powers bind tighter than every other operation, other than unary ones on their left
+    fn is_operation(node: &Node) -> bool {
+      match node {
+        Node::Expression(inner) => !matches!(**inner, Node::Term(_, Operator::Power, _)),
+        _ => false,
+      }
+    }
+
+    match self {
+      Node::Term(lhs, op, rhs) => {
+        let lhs = lhs.parenthesize();
+        let rhs = rhs.parenthesize();
+        let binds = op.precedence();
+
+        let (lhs, rhs) = match op {
+          Operator::Power => (
+            wrap_if(lhs, |n| {
+              matches!(n, Node::Expression(_) | Node::UnaryOperator(..))
+            }),
+            wrap_if(rhs, is_operation),
+          ),
+          // Comparisons can't be chained, so neither side can be another comparison
+          _ if op.is_comparison() => (
+            wrap_if(lhs, |n| binding(n).is_some_and(|p| p <= binds)),
+            wrap_if(rhs, |n| binding(n).is_some_and(|p| p <= binds)),
+          ),
+          // The rest are left associative
+          _ => (
+            wrap_if(lhs, |n| binding(n).is_some_and(|p| p < binds)),
+            wrap_if(rhs, |n| binding(n).is_some_and(|p| p <= binds)),
+          ),
+        };
+
+        Node::Expression(Box::new(Node::Term(Box::new(lhs), op, Box::new(rhs))))
+      }
+      Node::UnaryOperator(op, rhs) => {
+        let rhs = wrap_if(rhs.parenthesize(), is_operation);
+
+        Node::UnaryOperator(op, Box::new(rhs))
+      }
+      // The braces delimit the arms, so they're parenthesized on their own
+      Node::Match(keyword, value, arms) => Node::Match(
+        keyword,
+        Box::new(value.parenthesize()),
+        arms
+          .into_iter()
+          .map(|(pattern, arm)| (pattern, arm.parenthesize()))
+          .collect(),
+      ),
+      // So does the `${...}` of each expression
+      Node::Interpolation(segments) => Node::Interpolation(
+        segments
+          .iter()
+          .map(|segment| segment.map(|expr| expr.clone().parenthesize()))
+          .collect(),
+      ),
+      // And the `[...]` and `(...)` of arrays, indexes, and tuples
+      Node::Array(elements) => Node::Array(elements.into_iter().map(Node::parenthesize).collect()),
+      Node::Tuple(elements) => Node::Tuple(elements.into_iter().map(Node::parenthesize).collect()),
+      // Indexing binds tighter than any operation, and a `let` would take the index as its body
+      Node::Index(bracket, array, index) => Node::Index(
+        bracket,
+        Box::new(wrap_if(array.parenthesize(), binds_looser_than_postfix)),
+        Box::new(index.parenthesize()),
+      ),
+      Node::Field(dot, tuple, index) => Node::Field(
+        dot,
+        Box::new(wrap_if(tuple.parenthesize(), binds_looser_than_postfix)),
+        index,
+      ),
+      Node::Struct(name, fields) => Node::Struct(
+        name,
+        fields
+          .into_iter()
+          .map(|(field, value)| (field, value.parenthesize()))
+          .collect(),
+      ),
+      Node::NamedField(dot, value, field) => Node::NamedField(
+        dot,
+        Box::new(wrap_if(value.parenthesize(), binds_looser_than_postfix)),
+        field,
+      ),
+      // The body extends as far as it can, so it's parenthesized on its own
+      Node::Lambda(pipe, params, body) => Node::Lambda(pipe, params, Box::new(body.parenthesize())),
+      _ => self,
+    }
+  }
+}
+
+/// Formats a [Node] as nested s-expressions.
+///
+/// Each assignment of a [Node::Program] is printed on its own line.
+pub struct SExpr<'a>(&'a Node);
+
+impl std::fmt::Display for SExpr<'_> {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self.0 {
+      Node::Program(nodes) => {
+        for (i, node) in nodes.iter().enumerate() {
+          if i != 0 {
+            writeln!(f)?;
+          }
+
+          write!(f, "{}", node.sexpr())?;
+        }
+
+        Ok(())
+      }
+      Node::Assignment(ident, expr) => write!(f, "(assign {} {})", ident.sexpr(), expr.sexpr()),
+      Node::ConstAssignment(_, ident, expr) => {
+        write!(f, "(const {} {})", ident.literal, expr.sexpr())
+      }
+      Node::MultiAssignment(idents, values) => {
+        write!(f, "(assign (")?;
+
+        for (i, ident) in idents.iter().enumerate() {
+          if i != 0 {
+            write!(f, " ")?;
+          }
+
+          write!(f, "{}", ident.literal)?;
+        }
+
+        write!(f, ")")?;
+
+        for value in values {
+          write!(f, " {}", value.sexpr())?;
+        }
+
+        write!(f, ")")
+      }
+      Node::Print(_, expr) => write!(f, "(print {})", expr.sexpr()),
+      Node::Read(_, ident) => write!(f, "(read {})", ident.literal),
+      Node::If(_, condition, then, otherwise) => {
+        write!(f, "(branch {} {}", condition.sexpr(), then.sexpr())?;
+
+        if let Some(otherwise) = otherwise {
+          write!(f, " {}", otherwise.sexpr())?;
+        }
+
+        write!(f, ")")
+      }
+      Node::For(_, init, condition, step, body) => {
+        let clause = |clause: &Option<Box<Node>>| match clause {
+          Some(clause) => clause.sexpr().to_string(),
+          None => "()".to_string(),
+        };
+
+        write!(
+          f,
+          "(for {} {} {} {})",
+          clause(init),
+          condition.sexpr(),
+          clause(step),
+          body.sexpr()
+        )
+      }
+      Node::Block(nodes) => {
+        write!(f, "(block")?;
+
+        for node in nodes {
+          write!(f, " {}", node.sexpr())?;
+        }
+
+        write!(f, ")")
+      }
+      Node::BlockStatement(_, block) => write!(f, "{}", block.sexpr()),
+      Node::Conditional(flag, statement) => write!(f, "(if {} {})", flag, statement.sexpr()),
+      // These nodes only exist to mirror the grammar, so they're transparent here
+      Node::Expression(node) | Node::Fact(node) => write!(f, "{}", node.sexpr()),
+      Node::Term(lhs, op, rhs) => write!(f, "({} {} {})", op, lhs.sexpr(), rhs.sexpr()),
+      Node::UnaryOperator(op, node) => write!(f, "({} {})", op, node.sexpr()),
+      Node::Identifier(ident) => write!(f, "{}", ident.literal),
+      Node::Literal(lit) => write!(f, "{}", lit.value),
+      Node::Let(ident, value, body) => {
+        write!(
+          f,
+          "(let {} {} {})",
+          ident.literal,
+          value.sexpr(),
+          body.sexpr()
+        )
+      }
+      Node::Call(ident, args, _) => {
+        write!(f, "(call {}", ident.literal)?;
+
+        for arg in args {
+          write!(f, " {}", arg.sexpr())?;
+        }
+
+        write!(f, ")")
+      }
+      Node::Lambda(_, params, body) => {
+        let params = params
+          .iter()
+          .map(|param| param.literal.as_str())
+          .collect::<Vec<_>>();
+
+        write!(f, "(lambda ({}) {})", params.join(" "), body.sexpr())
+      }
+      Node::Match(_, value, arms) => {
+        write!(f, "(match {}", value.sexpr())?;
+
+        for (pattern, arm) in arms {
+          write!(f, " ({} {})", pattern, arm.sexpr())?;
+        }
+
+        write!(f, ")")
+      }
+      Node::Interpolation(segments) => {
+        write!(f, "(interpolate")?;
+
+        for segment in segments {
+          match segment {
+            Segment::Text(text) => write!(f, " \"{}\"", escape(text))?,
+            Segment::Expression(expr) => write!(f, " {}", expr.sexpr())?,
+          }
+        }
+
+        write!(f, ")")
+      }
+      Node::Array(elements) => {
+        write!(f, "(array")?;
+
+        for element in elements {
+          write!(f, " {}", element.sexpr())?;
+        }
+
+        write!(f, ")")
+      }
+      Node::Index(_, array, index) => write!(f, "(index {} {})", array.sexpr(), index.sexpr()),
+      Node::Tuple(elements) => {
+        write!(f, "(tuple")?;
+
+        for element in elements {
+          write!(f, " {}", element.sexpr())?;
+        }
+
+        write!(f, ")")
+      }
+      Node::Field(_, tuple, index) => write!(f, "(field {} {})", tuple.sexpr(), index),
+      Node::Import(_, path) => write!(f, "(import \"{}\")", escape(path)),
+      Node::StructDeclaration(_, name, fields) => {
+        write!(f, "(struct {}", name.literal)?;
+
+        for field in fields {
+          write!(f, " {}", field.literal)?;
+        }
+
+        write!(f, ")")
+      }
+      Node::Struct(name, fields) => {
+        write!(f, "(new {}", name.literal)?;
+
+        for (field, value) in fields {
+          write!(f, " ({} {})", field.literal, value.sexpr())?;
+        }
+
+        write!(f, ")")
+      }
+      Node::NamedField(_, value, field) => {
+        write!(f, "(field {} {})", value.sexpr(), field.literal)
+      }
+      Node::Return(_, Some(value)) => write!(f, "(return {})", value.sexpr()),
+      Node::Return(_, None) => write!(f, "(return)"),
+      Node::Break(_) => write!(f, "(break)"),
+      Node::Continue(_) => write!(f, "(continue)"),
+    }
+  }
+}
+
+/// Formats the node as source code.
+impl std::fmt::Display for Node {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Node::Program(nodes) => {
+        for (i, node) in nodes.iter().enumerate() {
+          if i != 0 {
+            writeln!(f)?;
+          }
+
+          write!(f, "{}", node)?;
+        }
+
+        Ok(())
+      }
+      Node::Assignment(ident, expr) => write!(f, "{} = {};", ident, chain(expr)),
+      Node::ConstAssignment(_, ident, expr) => write!(f, "const {} = {};", ident.literal, expr),
+      Node::MultiAssignment(idents, values) => {
+        let idents = idents
+          .iter()
+          .map(|ident| ident.literal.as_str())
+          .collect::<Vec<_>>();
+        let values = values.iter().map(Node::to_string).collect::<Vec<_>>();
+
+        write!(f, "{} = {};", idents.join(", "), values.join(", "))
+      }
+      Node::Print(_, expr) => write!(f, "print {};", expr),
+      Node::Read(_, ident) => write!(f, "read {};", ident.literal),
+      Node::If(_, condition, then, otherwise) => {
+        write!(f, "if ({}) {}", condition, then)?;
+
+        match otherwise {
+          Some(otherwise) => write!(f, " else {}", otherwise),
+          None => Ok(()),
+        }
+      }
+      Node::For(_, init, condition, step, body) => {
+        // The clauses are printed without the `;` that ends an assignment statement
+        let clause = |clause: &Option<Box<Node>>| match clause.as_deref() {
+          Some(Node::Assignment(ident, expr)) => format!("{} = {}", ident, chain(expr)),
+          _ => String::new(),
+        };
+
+        write!(
+          f,
+          "for ({}; {}; {}) {}",
+          clause(init),
+          condition,
+          clause(step),
+          body
+        )
+      }
+      // Blocks are printed on one line, so that they can be used anywhere a statement can
+      Node::Block(nodes) => {
+        write!(f, "{{")?;
+
+        for node in nodes {
+          write!(f, " {}", node)?;
+        }
+
+        write!(f, " }}")
+      }
+      Node::BlockStatement(_, block) => write!(f, "{}", block),
+      Node::Conditional(flag, statement) => write!(f, "#[if: {}] {}", flag, statement),
+      // A fact wrapping an expression means that it was parenthesized
+      Node::Fact(node) if matches!(**node, Node::Expression(_)) => write!(f, "({})", node),
+      Node::Expression(node) | Node::Fact(node) => write!(f, "{}", node),
+      // A negative base has to be parenthesized, since `-2 ** 2` is `-(2 ** 2)`
+      Node::Term(lhs, Operator::Power, rhs) if matches!(&**lhs, Node::Literal(lit) if lit.value.is_negative()) =>
+      {
+        write!(f, "({}) ** {}", lhs, rhs)
+      }
+      Node::Term(lhs, op, rhs) => write!(f, "{} {} {}", lhs, op, rhs),
+      Node::UnaryOperator(op, node) => {
+        let operand = node.to_string();
+
+        // `-(-3)` would otherwise read as `--3`, which looks like a decrement
+        if operand.starts_with(&op.to_string()) {
+          write!(f, "{}({})", op, operand)
+        } else {
+          write!(f, "{}{}", op, operand)
+        }
+      }
+      Node::Identifier(ident) => write!(f, "{}", ident.literal),
+      Node::Literal(lit) => write!(f, "{}", lit.value),
+      Node::Let(ident, value, body) => write!(f, "let {} = {} in {}", ident.literal, value, body),
+      Node::Call(ident, args, _) => {
+        write!(f, "{}(", ident.literal)?;
+
+        for (i, arg) in args.iter().enumerate() {
+          if i != 0 {
+            write!(f, ", ")?;
+          }
+
+          write!(f, "{}", arg)?;
+        }
+
+        write!(f, ")")
+      }
+      Node::Lambda(_, params, body) => {
+        let params = params
+          .iter()
+          .map(|param| param.literal.as_str())
+          .collect::<Vec<_>>();
+
+        write!(f, "|{}| {}", params.join(", "), body)
+      }
+      Node::Match(_, value, arms) => {
+        write!(f, "match {} {{", value)?;
+
+        for (i, (pattern, arm)) in arms.iter().enumerate() {
+          if i != 0 {
+            write!(f, ",")?;
+          }
+
+          write!(f, " {} => {}", pattern, arm)?;
+        }
+
+        write!(f, " }}")
+      }
+      Node::Interpolation(segments) => {
+        write!(f, "\"")?;
+
+        for segment in segments {
+          match segment {
+            Segment::Text(text) => write!(f, "{}", escape(text))?,
+            Segment::Expression(expr) => write!(f, "${{{}}}", expr)?,
+          }
+        }
+
+        write!(f, "\"")
+      }
+      Node::Array(elements) => {
+        write!(f, "[")?;
+
+        for (i, element) in elements.iter().enumerate() {
+          if i != 0 {
+            write!(f, ", ")?;
+          }
+
+          write!(f, "{}", element)?;
+        }
+
+        write!(f, "]")
+      }
+      Node::Index(_, array, index) => write!(f, "{}[{}]", array, index),
+      Node::Tuple(elements) => {
+        write!(f, "(")?;
+
+        for (i, element) in elements.iter().enumerate() {
+          if i != 0 {
+            write!(f, ", ")?;
+          }
+
+          write!(f, "{}", element)?;
+        }
+
+        // A single element would otherwise be a parenthesized expression
+        match elements.len() {
+          1 => write!(f, ",)"),
+          _ => write!(f, ")"),
+        }
+      }
+      Node::Field(_, tuple, index) => write!(f, "{}.{}", tuple, index),
+      Node::Import(_, path) => write!(f, "import \"{}\";", escape(path)),
+      Node::StructDeclaration(_, name, fields) => {
+        let fields = fields
+          .iter()
+          .map(|field| field.literal.as_str())
+          .collect::<Vec<_>>();
+
+        write!(f, "struct {} {{ {} }}", name.literal, fields.join(", "))
+      }
+      Node::Struct(name, fields) => {
+        let fields = fields
+          .iter()
+          .map(|(field, value)| format!("{}: {}", field.literal, value))
+          .collect::<Vec<_>>();
+
+        write!(f, "{} {{ {} }}", name.literal, fields.join(", "))
+      }
+      Node::NamedField(_, value, field) => write!(f, "{}.{}", value, field.literal),
+      Node::Return(_, Some(value)) => write!(f, "return {};", value),
+      Node::Return(_, None) => write!(f, "return;"),
+      Node::Break(_) => write!(f, "break;"),
+      Node::Continue(_) => write!(f, "continue;"),
+    }
+  }
+}
+
+// Formats the expression of an assignment, so that a chained assignment isn't ended by its own
+// `;`, eg `a = b = 1;`.
+fn chain(expr: &Node) -> String {
+  match expr {
+    Node::Assignment(ident, expr) => format!("{} = {}", ident, chain(expr)),
+    _ => expr.to_string(),
+  }
+}
+
+impl Operator {
+  /// Returns whether the operator compares its operands, resulting in `1` if the comparison
+  /// holds and `0` otherwise.
+  pub fn is_comparison(self) -> bool {
+    matches!(
+      self,
+      Operator::Equal
+        | Operator::NotEqual
+        | Operator::Less
+        | Operator::LessEqual
+        | Operator::Greater
+        | Operator::GreaterEqual
+    )
+  }
+
+  /// Returns how tightly the operator binds, from `0` for `??` up to `7` for `**`.
+  pub fn precedence(self) -> u8 {
+    match self {
+      Operator::Coalesce => 0,
+      Operator::Or => 1,
+      Operator::And => 2,
+      _ if self.is_comparison() => 3,
+      Operator::ShiftLeft | Operator::ShiftRight => 4,
+      Operator::Plus | Operator::Minus => 5,
+      Operator::Power => 7,
+      _ => 6,
+    }
+  }
+
+  /// Returns whether the operator is `&&` or `||`, which result in `1` or `0` like comparisons.
+  pub fn is_logical(self) -> bool {
+    matches!(self, Operator::And | Operator::Or)
+  }
+
+  /// Returns whether the left operand alone decides the result, so that the right operand
+  /// isn't evaluated, like `0 && x` or `1 ?? x`.
+  pub fn short_circuits(self, lhs: &Value) -> bool {
+    match self {
+      Operator::And => lhs.is_zero(),
+      Operator::Or => !lhs.is_zero(),
+      Operator::Coalesce => !lhs.is_none(),
+      _ => false,
+    }
+  }
+
+  /// Compares the operands, returning `None` if the operator isn't a comparison.
+  pub fn compare<T: PartialOrd>(self, lhs: T, rhs: T) -> Option<bool> {
+    match self {
+      Operator::Equal => Some(lhs == rhs),
+      Operator::NotEqual => Some(lhs != rhs),
+      Operator::Less => Some(lhs < rhs),
+      Operator::LessEqual => Some(lhs <= rhs),
+      Operator::Greater => Some(lhs > rhs),
+      Operator::GreaterEqual => Some(lhs >= rhs),
+      _ => None,
+    }
+  }
+
+  /// Returns whether the operation has no value for its operands, like dividing by zero,
+  /// raising to a negative power, or shifting by more bits than an integer has.
+  ///
+  /// Floats can be raised to negative powers, unlike integers.
+  pub fn is_undefined_for(self, lhs: &Value, rhs: &Value) -> bool {
+    match self {
+      Operator::Divide | Operator::Modulo => rhs.is_number() && rhs.is_zero(),
+      Operator::Power => matches!((lhs, rhs), (Value::Int(_), Value::Int(rhs)) if *rhs < 0),
+      Operator::ShiftLeft | Operator::ShiftRight => {
+        !matches!(rhs, Value::Int(rhs) if (0..isize::BITS as isize).contains(rhs))
+      }
+      _ => false,
+    }
+  }
+}
+
+impl Operator {
+  /// Returns whether the binary operator applies to the operands, since strings can only be
+  /// concatenated with `+` and compared with other strings, arrays can only be concatenated with
+  /// `+` and compared with `==` and `!=` to other arrays, and tuples and structs can only be
+  /// compared with `==` and `!=` to other tuples and structs.
+  ///
+  /// `&&` and `||` apply to every value, treating an empty string or array as false, and so does
+  /// `??`. `none` can only be compared with `==` and `!=`, to any value.
+  pub fn applies_to(self, lhs: &Value, rhs: &Value) -> bool {
+    match (lhs, rhs) {
+      _ if self.is_logical() || matches!(self, Operator::Coalesce) => true,
+      (Value::None, _) | (_, Value::None) => matches!(self, Operator::Equal | Operator::NotEqual),
+      _ if lhs.is_number() && rhs.is_number() => true,
+      (Value::Str(_), Value::Str(_)) => matches!(self, Operator::Plus) || self.is_comparison(),
+      (Value::Array(_), Value::Array(_)) => {
+        matches!(self, Operator::Plus | Operator::Equal | Operator::NotEqual)
+      }
+      (Value::Tuple(_), Value::Tuple(_)) | (Value::Struct(..), Value::Struct(..)) => {
+        matches!(self, Operator::Equal | Operator::NotEqual)
+      }
+      _ => false,
+    }
+  }
+}
+
+impl Segment {
+  /// Returns the segment with the function applied to its expression, if it has one.
+  pub fn map(&self, f: impl FnOnce(&Node) -> Node) -> Segment {
+    match self {
+      Segment::Text(text) => Segment::Text(text.clone()),
+      Segment::Expression(expr) => Segment::Expression(f(expr)),
+    }
+  }
+}
+
+impl std::fmt::Display for Pattern {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Pattern::Literal(value) => write!(f, "{}", value),
+      Pattern::Wildcard => write!(f, "_"),
+    }
+  }
+}
+
+impl std::fmt::Display for Operator {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Operator::Plus => write!(f, "+"),
+      Operator::Minus => write!(f, "-"),
+      Operator::Multiply => write!(f, "*"),
+      Operator::Divide => write!(f, "/"),
+      Operator::Modulo => write!(f, "%"),
+      Operator::Power => write!(f, "**"),
+      Operator::ShiftLeft => write!(f, "<<"),
+      Operator::ShiftRight => write!(f, ">>"),
+      Operator::And => write!(f, "&&"),
+      Operator::Or => write!(f, "||"),
+      Operator::Not => write!(f, "!"),
+      Operator::Equal => write!(f, "=="),
+      Operator::NotEqual => write!(f, "!="),
+      Operator::Less => write!(f, "<"),
+      Operator::LessEqual => write!(f, "<="),
+      Operator::Greater => write!(f, ">"),
+      Operator::GreaterEqual => write!(f, ">="),
+      Operator::Coalesce => write!(f, "??"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::Parser;
+
+  #[test]
+  fn sexpr() {
+    let src = "x = 2 + 3 * y;\nz = -(x - 1);\nprint z;\nif (x < 2) { w = 1; } else { w = 2; }";
+    let program = Parser::new(src).parse().unwrap();
+
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(assign x (+ 2 (* 3 y)))\n(assign z (- (- x 1)))\n(print z)\n\
+       (branch (< x 2) (block (assign w 1)) (block (assign w 2)))"
+    );
+  }
 }
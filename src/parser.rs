@@ -1,17 +1,54 @@
-use std::num::IntErrorKind;
-
 use crate::{
   error::DiagnosticError,
   lexer::Lexer,
-  node::{IdentifierNode, LiteralNode, Node, Operator},
-  token::{Token, TokenKind},
-  util::{linebreak_index, token_info},
+  limits::DEFAULT_MAX_DEPTH,
+  lint::{Lint, LintLevel, LintLevels},
+  node::{IdentifierNode, LiteralNode, Node, Operator, Pattern, Segment},
+  pragma::{Pragma, Pragmas},
+  token::{LiteralError, Token, TokenKind},
+  typeck::{Declaration, Type},
+  util::{linebreak_index, token_info, LineIndex},
+  value::Value,
+  version::{Features, LangVersion},
 };
 
+/// Parses [Token]s into a [Node::Program].
 #[derive(Debug)]
 pub struct Parser<'a> {
   src: &'a str,
   lexer: LexerManager,
+  line_index: LineIndex,
+  lints: LintLevels,
+  pragmas: Pragmas,
+  // The `#[if: FLAG]` pragmas that apply to the next statement
+  conditions: Vec<(String, Token)>,
+  terminator: Terminator,
+  // The version that's used unless the program sets one with `#[lang: N]`
+  version: LangVersion,
+  // The number of blocks being parsed, so that a `}` can end the statements of the innermost one
+  blocks: usize,
+  // The number of `for` bodies being parsed, since `break` and `continue` can only be in one
+  loops: usize,
+  // The levels of expressions and blocks being parsed, each of which recurses
+  depth: usize,
+  max_depth: usize,
+  // Operators that can't be used where they are, which are reported once the program is parsed
+  operator_errors: Vec<DiagnosticError>,
+  // The variables declared with a type, eg `x: int = 5;`
+  declarations: Vec<Declaration>,
+  warnings: Vec<DiagnosticError>,
+}
+
+/// What can end a statement.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Terminator {
+  /// Only a semicolon.
+  #[default]
+  Semicolon,
+  /// A semicolon, or a line break where one is missing, with a warning, set with `--asi`.
+  Inferred,
+  /// A semicolon or a line break, set with `--dialect=lines`.
+  Lines,
 }
 
 #[derive(Debug)]
@@ -22,7 +59,6 @@ struct LexerManager {
 
 impl<'a> Parser<'a> {
   /// Creates a new [Parser] from the source string.
-  #[allow(dead_code)]
   pub fn new(src: &'a str) -> Self {
     Self::from_tokens(src, Lexer::new(src).lex())
   }
@@ -35,15 +71,86 @@ impl<'a> Parser<'a> {
         tokens,
         token_pos: 0,
       },
+      line_index: LineIndex::new(src),
+      lints: LintLevels::default(),
+      pragmas: Pragmas::default(),
+      conditions: Vec::new(),
+      terminator: Terminator::default(),
+      version: LangVersion::default(),
+      blocks: 0,
+      loops: 0,
+      depth: 0,
+      max_depth: DEFAULT_MAX_DEPTH,
+      operator_errors: Vec::new(),
+      declarations: Vec::new(),
+      warnings: Vec::new(),
     }
   }
 
+  /// Sets the levels of the lints checked while parsing.
+  pub fn with_lints(mut self, lints: LintLevels) -> Self {
+    self.lints = lints;
+    self
+  }
+
+  /// Sets what can end a statement.
+  ///
+  /// A line break only ends a statement if the next token can't continue its expression, so
+  /// an expression can still be split across lines before an operator, eg `x = 1\n+ 2`.
+  pub fn with_terminator(mut self, terminator: Terminator) -> Self {
+    self.terminator = terminator;
+    self
+  }
+
+  /// Sets the version of the language, which decides the operators that can be used.
+  ///
+  /// This should be the version that the tokens were lexed with. A `#[lang: N]` pragma at the
+  /// start of the program overrides it.
+  pub fn with_version(mut self, version: LangVersion) -> Self {
+    self.version = version;
+    self
+  }
+
+  /// Sets the most levels that expressions and blocks can nest, eg `((1))` nests 3 levels.
+  ///
+  /// Anything nested deeper is reported rather than parsed, so that the parser, which recurses
+  /// for each level, can't overflow the stack.
+  pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+    self.max_depth = max_depth;
+    self
+  }
+
+  /// Returns the pragmas set by the program parsed by the last call to [Parser::parse].
+  pub fn pragmas(&self) -> &Pragmas {
+    &self.pragmas
+  }
+
+  /// Returns the variables declared with a type, eg `x: int = 5;`, in the program parsed by the
+  /// last call to [Parser::parse], in the order that they're declared.
+  pub fn declarations(&self) -> &[Declaration] {
+    &self.declarations
+  }
+
+  /// Returns the warnings reported by the last call to [Parser::parse].
+  pub fn warnings(&self) -> &[DiagnosticError] {
+    &self.warnings
+  }
+
   /// Parses the vector into a [Node], with the root being [Node::Program]
   pub fn parse(&mut self) -> Result<Node, Vec<DiagnosticError>> {
     let mut errors = Vec::new();
+    self.warnings.clear();
+    self.pragmas = Pragmas::default();
+    self.conditions.clear();
+    self.declarations.clear();
 
     let program = self.parse_program(&mut errors);
 
+    if !self.operator_errors.is_empty() {
+      errors.append(&mut self.operator_errors);
+      errors.sort_by_key(|e| (e.line(), e.column()));
+    }
+
     if errors.is_empty() {
       Ok(program)
     } else {
@@ -54,6 +161,31 @@ impl<'a> Parser<'a> {
   fn parse_program(&mut self, errors: &mut Vec<DiagnosticError>) -> Node {
     let mut assignments = Vec::new();
 
+    // Pragmas apply to the whole program, so they have to come before any assignments
+    while let Some(token) = self.lexer.current_token().cloned() {
+      if !matches!(token.kind(), TokenKind::Pragma) {
+        break;
+      }
+
+      let pragma = Pragma::parse(self.src.get(token.range()).unwrap());
+
+      // Statement pragmas are parsed along with their statement
+      if matches!(pragma, Ok(Pragma::If(_))) {
+        break;
+      }
+
+      self.lexer.advance();
+
+      match pragma {
+        Ok(pragma) => self.pragmas.set(pragma),
+        Err(msg) => {
+          let (line, column) = self.line_index.line_col(token.range().start);
+
+          errors.push(DiagnosticError::new(msg, line, column));
+        }
+      }
+    }
+
     self.parse_assignment(&mut assignments, errors);
 
     // The last token should be an EndOfFile one
@@ -66,22 +198,167 @@ impl<'a> Parser<'a> {
   }
 
   fn parse_assignment(&mut self, assignments: &mut Vec<Node>, errors: &mut Vec<DiagnosticError>) {
+    // Each statement is parsed in a loop, rather than by recursing, so that a long program can't
+    // overflow the stack
+    while self.parse_statement(assignments, errors) {}
+  }
+
+  // Parses the next statement of the program or the current block.
+  //
+  // # Returns
+  // Returns whether there may be more statements to parse.
+  fn parse_statement(
+    &mut self,
+    assignments: &mut Vec<Node>,
+    errors: &mut Vec<DiagnosticError>,
+  ) -> bool {
     let ident_token = self.lexer.current_token().cloned();
 
-    // No more assignments to parse.
+    // No more assignments to parse, in the program or the current block
     if ident_token.is_none()
       || matches!(
         ident_token.as_ref().map(Token::kind),
         Some(TokenKind::EndOfFile)
       )
+      || (self.blocks > 0
+        && matches!(
+          ident_token.as_ref().map(Token::kind),
+          Some(TokenKind::RightBrace)
+        ))
     {
-      return;
+      for (_, pragma_token) in self.conditions.drain(..) {
+        let (line, column) = self.line_index.line_col(pragma_token.range().start);
+
+        errors.push(DiagnosticError::new(
+          format!(
+            "The pragma, `{}`, must be followed by an assignment.",
+            self.src.get(pragma_token.range()).unwrap()
+          ),
+          line,
+          column,
+        ));
+      }
+
+      return false;
     }
 
     let ident_token = ident_token.unwrap();
     let ident_token_info = token_info(self.src, &ident_token);
+    let start_pos = self.lexer.token_pos;
+
+    if matches!(ident_token.kind(), TokenKind::Pragma) {
+      let (line, column) = self.line_index.line_col(ident_token.range().start);
+      self.lexer.advance();
+
+      match Pragma::parse(ident_token_info.literal) {
+        Ok(Pragma::If(flag)) => self.conditions.push((flag, ident_token)),
+        Ok(_) => errors.push(DiagnosticError::new(
+          format!(
+            "The pragma, `{}`, must come before any assignments.",
+            ident_token_info.literal
+          ),
+          line,
+          column,
+        )),
+        Err(msg) => errors.push(DiagnosticError::new(msg, line, column)),
+      }
+
+      return true;
+    }
+
+    let conditions = std::mem::take(&mut self.conditions);
+
+    if matches!(ident_token.kind(), TokenKind::If | TokenKind::For) {
+      let statement = if matches!(ident_token.kind(), TokenKind::If) {
+        self.parse_if(errors)
+      } else {
+        self.parse_for(errors)
+      };
+
+      if let Some(statement) = statement {
+        assignments.push(with_conditions(statement, conditions));
+      }
+
+      return true;
+    }
+
+    // A block can also be a statement on its own, to limit where its variables exist
+    if matches!(ident_token.kind(), TokenKind::LeftBrace) {
+      if let Some(block) = self.parse_block(errors) {
+        let statement = Node::BlockStatement(ident_token, Box::new(block));
+        assignments.push(with_conditions(statement, conditions));
+      }
+
+      return true;
+    }
+
+    if matches!(ident_token.kind(), TokenKind::Struct) {
+      if let Some(statement) = self.parse_struct(errors) {
+        assignments.push(with_conditions(statement, conditions));
+      }
+
+      return true;
+    }
+
+    if matches!(ident_token.kind(), TokenKind::Break | TokenKind::Continue) {
+      let statement = self.parse_jump(errors);
+      assignments.push(with_conditions(statement, conditions));
+
+      return true;
+    }
+
+    // Blocks end before their `}` is reached here
+    if matches!(ident_token.kind(), TokenKind::RightBrace) {
+      let (line, column) = self.line_index.line_col(ident_token.range().start);
+      self.lexer.advance();
+
+      errors.push(DiagnosticError::new(
+        "The `}` doesn't close any block.".to_string(),
+        line,
+        column,
+      ));
+
+      return true;
+    }
+
+    // A constant is declared by `const` followed by an assignment to a single variable
+    let const_keyword = matches!(ident_token.kind(), TokenKind::Const).then(|| ident_token.clone());
+    let ident_token = match const_keyword {
+      Some(_) => {
+        self.lexer.advance();
+        self.lexer.current_token().cloned().unwrap()
+      }
+      None => ident_token,
+    };
+    let ident_token_info = token_info(self.src, &ident_token);
+
+    // A print statement is the keyword followed by its expression, instead of an assignment
+    let is_print = const_keyword.is_none() && matches!(ident_token.kind(), TokenKind::Print);
+    // A read statement is the keyword followed by the variable to read into
+    let is_read = const_keyword.is_none() && matches!(ident_token.kind(), TokenKind::Read);
+    // An import is the keyword followed by the path of the file, like a print statement
+    let is_import = const_keyword.is_none() && matches!(ident_token.kind(), TokenKind::Import);
 
-    let identifier_node = if matches!(ident_token.kind(), TokenKind::Identifier) {
+    let identifier_node = if is_print || is_import {
+      self.lexer.advance();
+
+      None
+    } else if is_read {
+      self.lexer.advance();
+
+      match self.expect(TokenKind::Identifier, "an `Identifier`") {
+        Ok(token) => Some(Node::Identifier(IdentifierNode {
+          literal: self.src.get(token.range()).unwrap().to_string(),
+          range: token.range(),
+          line: token.line(),
+        })),
+        Err(e) => {
+          errors.push(e);
+
+          None
+        }
+      }
+    } else if matches!(ident_token.kind(), TokenKind::Identifier) {
       // Only advance if we see a valid identifier, for better error diagonstics
       self.lexer.advance();
 
@@ -104,13 +381,77 @@ impl<'a> Parser<'a> {
       None
     };
 
+    // `x: int = 5;` declares the type of the variable
+    let annotation = match self.lexer.current_token().map(Token::kind) {
+      Some(TokenKind::Colon)
+        if matches!(identifier_node, Some(Node::Identifier(_)))
+          && !is_read
+          && self.features().type_annotations =>
+      {
+        match self.parse_annotation() {
+          Ok(ty) => Some(ty),
+          Err(e) => {
+            errors.push(e);
+
+            None
+          }
+        }
+      }
+      _ => None,
+    };
+
+    // `a, b = 1, 2;` assigns to several variables at once
+    let mut targets = Vec::new();
+
+    while matches!(identifier_node, Some(Node::Identifier(_)))
+      && annotation.is_none()
+      && !is_read
+      && const_keyword.is_none()
+      && matches!(
+        self.lexer.current_token().map(Token::kind),
+        Some(TokenKind::Comma)
+      )
+    {
+      self.lexer.advance();
+
+      match self.expect(TokenKind::Identifier, "an `Identifier`") {
+        Ok(token) => targets.push(IdentifierNode {
+          literal: self.src.get(token.range()).unwrap().to_string(),
+          range: token.range(),
+          line: token.line(),
+        }),
+        Err(e) => {
+          errors.push(e);
+          break;
+        }
+      }
+    }
+
+    // `x++;` and `x--;` are short for `x = x + 1;` and `x = x - 1;`
+    let step = match &identifier_node {
+      Some(Node::Identifier(ident))
+        if !is_read && targets.is_empty() && const_keyword.is_none() && annotation.is_none() =>
+      {
+        self.parse_step(ident)
+      }
+      _ => None,
+    };
+
     // Parse the equal sign
     match self.lexer.current_token() {
+      _ if is_print || is_import || is_read || step.is_some() => {}
       Some(tok) if matches!(tok.kind(), TokenKind::Equal) => {
         self.lexer.advance();
       }
       Some(next_token) if !matches!(next_token.kind(), TokenKind::EndOfFile) => {
         let next_info = token_info(self.src, next_token);
+        // If the identifier token and next token are on the same line, then
+        // point to the start of the next token
+        let (line, column) = if next_token.line() == ident_token.line() {
+          self.line_index.line_col(next_token.range().start)
+        } else {
+          self.line_index.after(&ident_token)
+        };
 
         errors.push(DiagnosticError::new(
           format!(
@@ -118,53 +459,90 @@ impl<'a> Parser<'a> {
             next_info.literal,
             next_token.kind()
           ),
-          ident_token_info.line,
-          // If the identifier token and next token are on the same line, then
-          // point to the start of the next token
-          if next_token.line() == ident_token.line() {
-            next_token.range().start + 1 - linebreak_index(self.src, ident_token.range())
-          } else {
-            ident_token.range().end + 1 - linebreak_index(self.src, ident_token.range())
-          },
+          line,
+          column,
         ));
       }
       // Either no token or we got an `EOF`
       _ => {
+        let (line, column) = self.line_index.after(&ident_token);
+
         errors.push(DiagnosticError::new(
           "Expected an `Equal` token.".to_string(),
-          ident_token_info.line,
-          ident_token.range().end + 1 - linebreak_index(self.src, ident_token.range()),
+          line,
+          column,
         ));
       }
     }
 
-    // Parse the expression
-    let expr_node = match self.parse_expr() {
-      Ok(node) => Some(node),
-      Err(e) => {
-        errors.push(e);
-
-        // Try to recover from the lack of expression, except for cases where the
-        // current token is `EndOfFile` or `Semicolon`
-        if !matches!(
-          self.lexer.current_token().map(Token::kind),
-          Some(TokenKind::EndOfFile | TokenKind::Semicolon)
-        ) {
-          self.lexer.token_pos -= 1;
+    // Parse the expression, or the values of a multiple assignment, where the last value is
+    // treated as the expression
+    let mut values = Vec::new();
+    let expr_node = if is_read {
+      None
+    } else if step.is_some() {
+      step
+    } else {
+      // A constant's value can't assign anything else, eg `const a = b = 1;`
+      let parsed = if const_keyword.is_some() || is_import {
+        self.parse_expr().map(|expr| vec![expr])
+      } else if targets.is_empty() {
+        self.parse_assigned().map(|expr| vec![expr])
+      } else {
+        self.parse_values()
+      };
+
+      match parsed {
+        Ok(mut parsed) => {
+          let expr = parsed.pop();
+          values = parsed;
+
+          expr
         }
+        Err(e) => {
+          errors.push(e);
+
+          // Try to recover from the lack of expression, except for cases where the
+          // current token is `EndOfFile` or `Semicolon`
+          if !matches!(
+            self.lexer.current_token().map(Token::kind),
+            Some(TokenKind::EndOfFile | TokenKind::Semicolon)
+          ) {
+            self.lexer.token_pos = self.lexer.token_pos.saturating_sub(1);
+          }
 
-        None
+          None
+        }
       }
     };
 
-    let expr_token = self.lexer.previous_token().cloned().unwrap();
+    let expr_token = self.lexer.last_token().cloned().unwrap();
     let expr_token_info = token_info(self.src, &expr_token);
+    // A missing semicolon should be reported right after the expression, which may be on a
+    // different line than both the assignment's start and the token that was found instead
+    let (line, column) = self.line_index.after(&expr_token);
 
     // We expect a semicolon
     match self.lexer.current_token().cloned() {
       Some(tok) if matches!(tok.kind(), TokenKind::Semicolon) => {
         self.lexer.advance();
       }
+      Some(tok)
+        if !matches!(self.terminator, Terminator::Semicolon)
+          && (expr_node.is_some() || is_read)
+          && (tok.line() > expr_token.line() || matches!(tok.kind(), TokenKind::EndOfFile)) =>
+      {
+        if matches!(self.terminator, Terminator::Inferred) {
+          self.warnings.push(DiagnosticError::warning(
+            format!(
+              "Inferred a `Semicolon` after `{}`, at the end of the line.",
+              expr_token_info.literal
+            ),
+            line,
+            column,
+          ));
+        }
+      }
       Some(tok) => {
         errors.push(DiagnosticError::new(
           format!(
@@ -173,9 +551,8 @@ impl<'a> Parser<'a> {
             self.src.get(tok.range()).unwrap(),
             tok.kind()
           ),
-          expr_token_info.line,
-          // The column should be after the expression
-          expr_token.range().end + 1 - linebreak_index(self.src, expr_token.range()),
+          line,
+          column,
         ));
       }
       None => {
@@ -185,281 +562,2456 @@ impl<'a> Parser<'a> {
             TokenKind::Semicolon,
             expr_token_info.literal,
           ),
-          expr_token_info.line,
-          // The column should be after the expression
-          expr_token.range().end + 1 - linebreak_index(self.src, expr_token.range()),
+          line,
+          column,
         ));
 
-        return;
+        return false;
       }
     }
 
-    if let (Some(ident), Some(expr)) = (identifier_node, expr_node) {
-      assignments.push(Node::Assignment(Box::new(ident), Box::new(expr)));
-    }
+    let statement = match (identifier_node, expr_node) {
+      (_, Some(expr)) if is_print => Some(Node::Print(ident_token, Box::new(expr))),
+      (_, Some(expr)) if is_import => self.parse_import(ident_token, expr, errors),
+      (Some(Node::Identifier(ident)), _) if is_read => Some(Node::Read(ident_token, ident)),
+      (Some(Node::Identifier(ident)), Some(expr)) if !targets.is_empty() => {
+        targets.insert(0, ident);
+        values.push(expr);
 
-    self.parse_assignment(assignments, errors);
-  }
+        self.check_multi_assignment(&targets, &values, errors);
 
-  fn parse_expr(&mut self) -> Result<Node, DiagnosticError> {
-    fn parse_expr_inner(parser: &mut Parser, lhs_term: Node) -> Result<Node, DiagnosticError> {
-      match parser.lexer.current_token().map(Token::kind) {
-        kind if matches!(kind, Some(TokenKind::Plus | TokenKind::Minus)) => {
-          // Advance since we saw `+`` or `-`
-          parser.lexer.advance();
+        Some(Node::MultiAssignment(targets, values))
+      }
+      (Some(Node::Identifier(ident)), Some(expr)) if const_keyword.is_some() => Some(
+        Node::ConstAssignment(const_keyword.unwrap(), ident, Box::new(expr)),
+      ),
+      (Some(ident), Some(expr)) => Some(Node::Assignment(Box::new(ident), Box::new(expr))),
+      _ => None,
+    };
 
-          let rhs_term = parser.parse_term()?;
+    if let (Some(ty), Some((idents, _))) = (
+      annotation,
+      statement.as_ref().and_then(Node::assignment_chain),
+    ) {
+      self.declarations.push(Declaration {
+        ident: idents[0].clone(),
+        ty,
+      });
+    }
 
-          // Recurse on the expression as needed
-          parse_expr_inner(
-            parser,
-            Node::Term(
-              Box::new(lhs_term),
-              if matches!(kind, Some(TokenKind::Plus)) {
-                Operator::Plus
-              } else {
-                Operator::Minus
-              },
-              Box::new(rhs_term),
-            ),
-          )
-        }
-        // If we got any other character besides `+` or `-`, then we're done recursing the expr
-        _ => Ok(lhs_term),
-      }
+    if let Some(statement) = statement {
+      assignments.push(with_conditions(statement, conditions));
     }
 
-    let lhs_term = self.parse_term()?;
+    // Recovering from some errors doesn't consume any tokens, so skip one to avoid reporting the
+    // same errors forever
+    if self.lexer.token_pos == start_pos {
+      self.lexer.advance();
+    }
 
-    Ok(Node::Expression(Box::new(parse_expr_inner(
-      self, lhs_term,
-    )?)))
+    true
   }
 
-  fn parse_term(&mut self) -> Result<Node, DiagnosticError> {
-    fn parse_term_inner(parser: &mut Parser, lhs_fact: Node) -> Result<Node, DiagnosticError> {
-      match parser.lexer.current_token().map(Token::kind) {
-        Some(TokenKind::Star) => {
-          // Advance token position since we saw `*`
-          parser.lexer.advance();
-
-          let rhs_fact = parser.parse_fact()?;
+  // Parses the type after the `:` of an assignment, eg the `int` of `x: int = 5;`.
+  fn parse_annotation(&mut self) -> Result<Type, DiagnosticError> {
+    self.lexer.advance();
 
-          // Recurse on the term
-          parse_term_inner(
-            parser,
-            Node::Term(Box::new(lhs_fact), Operator::Multiply, Box::new(rhs_fact)),
-          )
-        }
-        // If we got any other token besides `*`, then we got parsed the entire term
-        _ => Ok(lhs_fact),
-      }
-    }
+    let token = self.expect(TokenKind::Identifier, "a type, eg `int`")?;
+    let name = self.src.get(token.range()).unwrap();
 
-    let lhs_fact = self.parse_fact()?;
+    Type::parse(name).ok_or_else(|| {
+      let (line, column) = self.line_index.line_col(token.range().start);
 
-    parse_term_inner(self, lhs_fact)
+      DiagnosticError::new(
+        format!(
+          "The type `{}` doesn't exist. it has to be one of `int`, `float`, `str`, `array`, or \
+           `tuple`.",
+          name
+        ),
+        line,
+        column,
+      )
+    })
   }
 
-  fn parse_fact(&mut self) -> Result<Node, DiagnosticError> {
-    let fact_token = self.lexer.current_token().cloned();
-
-    match fact_token {
-      Some(x)
-        if !matches!(
-          x.kind(),
-          TokenKind::Literal
-            | TokenKind::Identifier
-            | TokenKind::LeftParen
-            | TokenKind::Minus
-            | TokenKind::Plus
-        ) =>
-      {
-        let eof = matches!(x.kind(), TokenKind::EndOfFile);
-
-        // Only advance if we're not at the end
-        if !eof {
-          self.lexer.advance();
-        }
-
-        let token_info = token_info(self.src, &x);
+  // Returns the import of the path that follows its keyword, which has to be a string and outside
+  // of any block.
+  fn parse_import(
+    &self,
+    keyword: Token,
+    path: Node,
+    errors: &mut Vec<DiagnosticError>,
+  ) -> Option<Node> {
+    let (line, column) = self.line_index.line_col(keyword.range().start);
+    let mut path = path;
+
+    while let Node::Expression(inner) | Node::Fact(inner) = path {
+      path = *inner;
+    }
 
-        Err(DiagnosticError::new(
+    let path = match path {
+      Node::Literal(LiteralNode {
+        value: Value::Str(path),
+      }) => path.to_string(),
+      path => {
+        errors.push(DiagnosticError::new(
           format!(
-            "Expected either `+`, `-`, `(`, an `Identifier`, or a `Literal`, but found `{}` ({})",
-            &token_info.literal,
-            x.kind()
+            "The import of `{}` isn't of a string. it needs the path of a file, eg `import \
+             \"other.toy\";`.",
+            path
           ),
-          token_info.line,
-          // If we're at the end, then the fact is expected at the next column
-          if eof {
-            token_info.column + 1
-          } else {
-            token_info.column
-          },
-        ))
+          line,
+          column,
+        ));
+
+        return None;
       }
+    };
 
-      Some(x) if matches!(x.kind(), TokenKind::Literal) => {
-        self.lexer.advance();
+    if self.blocks > 0 {
+      errors.push(DiagnosticError::new(
+        format!(
+          "The import of `{}` is in a block. files can only be imported outside of any block.",
+          path
+        ),
+        line,
+        column,
+      ));
+    }
 
-        let token_info = token_info(self.src, &x);
-        let num_str = token_info.literal;
+    Some(Node::Import(keyword, path))
+  }
 
-        if num_str.starts_with('0') && num_str.len() > 1 {
-          return Err(DiagnosticError::new(
-            format!(
-              "The integer, `{}`, is invalid. literals must be either 0 or non-zero digits.",
-              num_str
-            ),
-            x.line(),
-            // Point to the start of the invalid integer
-            x.range().start + 1 - linebreak_index(self.src, x.range()),
-          ));
-        }
+  // Parses a `struct Name { field, ... }` declaration, which has to be outside of any block and
+  // have at least one field.
+  fn parse_struct(&mut self, errors: &mut Vec<DiagnosticError>) -> Option<Node> {
+    let keyword = self.lexer.current_token().cloned().unwrap();
+    self.lexer.advance();
+
+    let declaration = self.parse_struct_fields();
+
+    // Skip the rest of the declaration, so that its fields aren't parsed as statements
+    if declaration.is_err() {
+      while !matches!(
+        self.lexer.current_token().map(Token::kind),
+        Some(TokenKind::EndOfFile) | None
+      ) {
+        let kind = self.lexer.current_token().map(Token::kind);
+        self.lexer.advance();
 
-        match num_str.parse() {
-          Ok(num) => Ok(Node::Literal(LiteralNode { value: num })),
-          Err(e) => {
-            match e.kind() {
-              IntErrorKind::NegOverflow | IntErrorKind::PosOverflow => Err(DiagnosticError::new(
-                format!(
-                  "The integer,`{}`, is invalid. integers must be in the range [{}, {}].",
-                  num_str,
-                  isize::MIN,
-                  isize::MAX
-                ),
-                x.line(),
-                // Point to the start of the invalid integer
-                x.range().start + 1 - linebreak_index(self.src, x.range()),
-              )),
-              // Any other cases shouldn't be reachable
-              _ => unreachable!("invalid integer"),
-            }
-          }
+        if matches!(kind, Some(TokenKind::RightBrace)) {
+          break;
         }
       }
+    }
 
-      Some(x) if matches!(x.kind(), TokenKind::Identifier) => {
-        self.lexer.advance();
+    let (name, fields) = match declaration {
+      Ok(declaration) => declaration,
+      Err(e) => {
+        errors.push(e);
 
-        Ok(Node::Identifier(IdentifierNode {
-          literal: self.src.get(x.range()).unwrap().to_string(),
-          line: x.line(),
-          range: x.range(),
-        }))
+        return None;
       }
+    };
+    let (line, column) = self.line_index.line_col(keyword.range().start);
 
-      Some(x) if matches!(x.kind(), TokenKind::LeftParen) => {
-        self.lexer.advance();
+    if self.blocks > 0 {
+      errors.push(DiagnosticError::new(
+        format!(
+          "The struct `{}` is declared in a block. structs can only be declared outside of any \
+           block.",
+          name.literal
+        ),
+        line,
+        column,
+      ));
+    }
 
-        let expr = self.parse_expr()?;
+    if fields.is_empty() {
+      errors.push(DiagnosticError::new(
+        format!(
+          "The struct `{0}` has no fields. it needs at least one, eg `struct {0} {{ x }}`.",
+          name.literal
+        ),
+        line,
+        column,
+      ));
+    }
 
-        match self.lexer.current_token().cloned() {
-          Some(x) if matches!(x.kind(), TokenKind::RightParen) => {
-            self.lexer.advance();
-          }
-          Some(x) => {
-            self.lexer.advance();
+    for (i, field) in fields.iter().enumerate() {
+      if fields[..i]
+        .iter()
+        .any(|other| other.literal == field.literal)
+      {
+        let (line, column) = self.line_index.line_col(field.range.start);
 
-            let expr_token = self.lexer.tokens.get(self.lexer.token_pos - 1).unwrap();
-            let expr_token_info = token_info(self.src, expr_token);
-            let curr_token_info = token_info(self.src, &x);
+        errors.push(DiagnosticError::new(
+          format!(
+            "The field `{}` of `{}` is declared more than once.",
+            field.literal, name.literal
+          ),
+          line,
+          column,
+        ));
+      }
+    }
 
-            return Err(DiagnosticError::new(
-              format!(
-                "Expected a `)` after `{}`, but found `{}`",
-                expr_token_info.literal, curr_token_info.literal
-              ),
-              curr_token_info.line,
-              curr_token_info.column,
-            ));
-          }
-          None => {
-            let expr_token = self.lexer.tokens.get(self.lexer.token_pos - 1).unwrap();
-            let expr_token_info = token_info(self.src, expr_token);
+    Some(Node::StructDeclaration(keyword, name, fields))
+  }
 
-            return Err(DiagnosticError::new(
-              format!("Expected a `)` after `{}`.", expr_token_info.literal),
-              x.line(),
-              expr_token.range().end - linebreak_index(self.src, expr_token.range()),
-            ));
-          }
-        }
+  // Parses the name and the `{ field, ... }` of a struct declaration, after its `struct`.
+  fn parse_struct_fields(
+    &mut self,
+  ) -> Result<(IdentifierNode, Vec<IdentifierNode>), DiagnosticError> {
+    let name = self.expect(TokenKind::Identifier, "the name of the struct")?;
+    let name = self.identifier(&name);
+    let mut fields = Vec::new();
 
-        Ok(Node::Fact(Box::new(expr)))
+    self.expect(TokenKind::LeftBrace, "a `{`")?;
+
+    while !matches!(
+      self.lexer.current_token().map(Token::kind),
+      Some(TokenKind::RightBrace)
+    ) {
+      let field = self.expect(TokenKind::Identifier, "the name of a field")?;
+      fields.push(self.identifier(&field));
+
+      if !matches!(
+        self.lexer.current_token().map(Token::kind),
+        Some(TokenKind::Comma)
+      ) {
+        break;
       }
 
-      // Unary operations
-      Some(x) if matches!(x.kind(), TokenKind::Minus) => {
-        self.lexer.advance();
+      self.lexer.advance();
+    }
 
-        let fact = self.parse_fact()?;
+    self.expect(TokenKind::RightBrace, "a `}`")?;
 
-        Ok(Node::Fact(Box::new(Node::UnaryOperator(
-          Operator::Minus,
-          Box::new(fact),
-        ))))
+    Ok((name, fields))
+  }
+
+  // Returns whether the tokens after the name of a struct start a value of it, eg the
+  // `{ x: ` of `Point { x: 1 }`.
+  //
+  // The `{` has to be on the same line as the name, so that a block on the next line, like
+  // `{ t: int = 1; }`, isn't mistaken for one when line breaks end statements.
+  fn starts_struct(&self, name: &Token) -> bool {
+    matches!(
+      self.lexer.current_token(),
+      Some(brace) if matches!(brace.kind(), TokenKind::LeftBrace) && brace.line() == name.line()
+    ) && matches!(
+      self.lexer.peek_token().map(Token::kind),
+      Some(TokenKind::Identifier)
+    ) && matches!(
+      self.lexer.peek_nth_token(2).map(Token::kind),
+      Some(TokenKind::Colon)
+    )
+  }
+
+  // Parses the `{ field: Exp, ... }` of a value of the struct.
+  fn parse_struct_value(&mut self, name: IdentifierNode) -> Result<Node, DiagnosticError> {
+    self.lexer.advance();
+
+    let mut fields = Vec::new();
+
+    while !matches!(
+      self.lexer.current_token().map(Token::kind),
+      Some(TokenKind::RightBrace)
+    ) {
+      let field = self.expect(TokenKind::Identifier, "the name of a field")?;
+      let field = self.identifier(&field);
+
+      self.expect(TokenKind::Colon, "a `:`")?;
+      fields.push((field, self.parse_expr()?));
+
+      if !matches!(
+        self.lexer.current_token().map(Token::kind),
+        Some(TokenKind::Comma)
+      ) {
+        break;
       }
-      Some(x) if matches!(x.kind(), TokenKind::Plus) => {
+
+      self.lexer.advance();
+    }
+
+    self.expect(TokenKind::RightBrace, "a `}`")?;
+
+    Ok(Node::Struct(name, fields))
+  }
+
+  // Parses the `(` and the arguments of a call of the function, which can be followed by a `,`.
+  fn parse_call(&mut self, ident: IdentifierNode) -> Result<Node, DiagnosticError> {
+    self.lexer.advance();
+
+    let mut args = Vec::new();
+
+    while !matches!(
+      self.lexer.current_token().map(Token::kind),
+      Some(TokenKind::RightParen)
+    ) {
+      args.push(self.parse_expr()?);
+
+      if !matches!(
+        self.lexer.current_token().map(Token::kind),
+        Some(TokenKind::Comma)
+      ) {
+        break;
+      }
+
+      self.lexer.advance();
+    }
+
+    let paren = self.expect(TokenKind::RightParen, "a `)`")?;
+    let range = ident.range.start..paren.range().end;
+
+    Ok(Node::Call(ident, args, range))
+  }
+
+  // Returns whether the token starts a lambda, which is a `|` before its parameters, or a `||`
+  // if it doesn't have any.
+  fn starts_lambda(&self, token: &Token) -> bool {
+    matches!(token.kind(), TokenKind::Pipe | TokenKind::PipePipe) && self.features().lambdas
+  }
+
+  // Parses a `|Identifier, ...| Exp` lambda, whose body extends as far as an expression can.
+  fn parse_lambda(&mut self, pipe: Token) -> Result<Node, DiagnosticError> {
+    self.lexer.advance();
+
+    let mut params = Vec::<IdentifierNode>::new();
+
+    if matches!(pipe.kind(), TokenKind::Pipe) {
+      while !matches!(
+        self.lexer.current_token().map(Token::kind),
+        Some(TokenKind::Pipe)
+      ) {
+        let param = self.expect(TokenKind::Identifier, "the name of a parameter")?;
+        let param = self.identifier(&param);
+
+        // The lambda is still parsed, since only its parameter is invalid
+        if params.iter().any(|other| other.literal == param.literal) {
+          let (line, column) = self.line_index.line_col(param.range.start);
+
+          self.operator_errors.push(DiagnosticError::new(
+            format!(
+              "The parameter `{}` of the lambda is declared more than once.",
+              param.literal
+            ),
+            line,
+            column,
+          ));
+        }
+
+        params.push(param);
+
+        if !matches!(
+          self.lexer.current_token().map(Token::kind),
+          Some(TokenKind::Comma)
+        ) {
+          break;
+        }
+
         self.lexer.advance();
+      }
 
-        let fact = self.parse_fact()?;
+      self.expect(TokenKind::Pipe, "a `|`")?;
+    }
 
-        Ok(Node::Fact(Box::new(Node::UnaryOperator(
-          Operator::Plus,
-          Box::new(fact),
-        ))))
+    let body = self.parse_expr()?;
+
+    Ok(Node::Lambda(pipe, params, Box::new(body)))
+  }
+
+  // Returns the identifier that the token is.
+  fn identifier(&self, token: &Token) -> IdentifierNode {
+    IdentifierNode {
+      literal: self.src.get(token.range()).unwrap().to_string(),
+      range: token.range(),
+      line: token.line(),
+    }
+  }
+
+  // Parses an `if (Exp) { ... } else { ... }` statement, where `else if` is short for an `else`
+  // block that only contains another `if`.
+  fn parse_if(&mut self, errors: &mut Vec<DiagnosticError>) -> Option<Node> {
+    let keyword = self.lexer.current_token().cloned().unwrap();
+    self.lexer.advance();
+
+    let condition = match self.parse_condition() {
+      Ok(condition) => Some(condition),
+      Err(e) => {
+        errors.push(e);
+
+        // Skip the rest of the condition, so that the block is still checked
+        while !matches!(
+          self.lexer.current_token().map(Token::kind),
+          Some(TokenKind::LeftBrace | TokenKind::RightBrace | TokenKind::EndOfFile) | None
+        ) {
+          self.lexer.advance();
+        }
+
+        None
       }
+    };
+    let then = self.parse_block(errors);
 
-      Some(other) => {
+    let otherwise = match self.lexer.current_token().map(Token::kind) {
+      Some(TokenKind::Else) => {
         self.lexer.advance();
 
-        let token_info = token_info(self.src, &other);
+        if matches!(
+          self.lexer.current_token().map(Token::kind),
+          Some(TokenKind::If)
+        ) {
+          Some(Node::Block(vec![self.parse_if(errors)?]))
+        } else {
+          Some(self.parse_block(errors)?)
+        }
+      }
+      _ => None,
+    };
+
+    Some(Node::If(
+      keyword,
+      Box::new(condition?),
+      Box::new(then?),
+      otherwise.map(Box::new),
+    ))
+  }
+
+  // Parses the parenthesized condition of an `if`.
+  fn parse_condition(&mut self) -> Result<Node, DiagnosticError> {
+    self.expect(TokenKind::LeftParen, "`(`")?;
+
+    let condition = self.parse_expr()?;
+
+    // The `)` isn't skipped if it's missing, since it's likely the start of the block instead
+    match self.lexer.current_token() {
+      Some(tok) if matches!(tok.kind(), TokenKind::RightParen) => {
+        self.lexer.advance();
+
+        Ok(condition)
+      }
+      _ => {
+        let prev = self.lexer.last_token().unwrap();
+        let (line, column) = self.line_index.after(prev);
 
         Err(DiagnosticError::new(
           format!(
-            "Unexpected `{}` ({}) found when parsing fact.",
-            other.kind(),
-            token_info.literal,
+            "Expected `)` after `{}`, to end the condition.",
+            self.src.get(prev.range()).unwrap()
           ),
-          token_info.line,
-          token_info.column,
+          line,
+          column,
         ))
       }
+    }
+  }
+
+  // Parses a `for (Assignment; Exp; Assignment) { ... }` loop, where either assignment can be
+  // left out.
+  fn parse_for(&mut self, errors: &mut Vec<DiagnosticError>) -> Option<Node> {
+    let keyword = self.lexer.current_token().cloned().unwrap();
+    self.lexer.advance();
+
+    let clauses = match self.parse_for_clauses() {
+      Ok(clauses) => Some(clauses),
+      Err(e) => {
+        errors.push(e);
+
+        // Skip the rest of the clauses, so that the block is still checked
+        while !matches!(
+          self.lexer.current_token().map(Token::kind),
+          Some(TokenKind::LeftBrace | TokenKind::RightBrace | TokenKind::EndOfFile) | None
+        ) {
+          self.lexer.advance();
+        }
+
+        None
+      }
+    };
+    self.loops += 1;
+    let body = self.parse_block(errors);
+    self.loops -= 1;
+
+    let (init, condition, step) = clauses?;
+
+    Some(Node::For(
+      keyword,
+      init.map(Box::new),
+      Box::new(condition),
+      step.map(Box::new),
+      Box::new(body?),
+    ))
+  }
+
+  // Parses a `break;` or `continue;`, which has to be within the body of a `for`.
+  fn parse_jump(&mut self, errors: &mut Vec<DiagnosticError>) -> Node {
+    let keyword = self.lexer.current_token().cloned().unwrap();
+    let literal = self.src.get(keyword.range()).unwrap();
+    self.lexer.advance();
+
+    if self.loops == 0 {
+      let (line, column) = self.line_index.line_col(keyword.range().start);
+
+      errors.push(DiagnosticError::new(
+        format!(
+          "The `{}` isn't in a loop. it can only be used in the body of a `for`.",
+          literal
+        ),
+        line,
+        column,
+      ));
+    }
+
+    let (line, column) = self.line_index.after(&keyword);
+
+    match self.lexer.current_token().cloned() {
+      Some(tok) if matches!(tok.kind(), TokenKind::Semicolon) => self.lexer.advance(),
+      Some(tok)
+        if !matches!(self.terminator, Terminator::Semicolon)
+          && (tok.line() > keyword.line() || matches!(tok.kind(), TokenKind::EndOfFile)) =>
+      {
+        if matches!(self.terminator, Terminator::Inferred) {
+          self.warnings.push(DiagnosticError::warning(
+            format!(
+              "Inferred a `Semicolon` after `{}`, at the end of the line.",
+              literal
+            ),
+            line,
+            column,
+          ));
+        }
+      }
+      Some(tok) => errors.push(DiagnosticError::new(
+        format!(
+          "Expected a `Semicolon` after `{}`, but found `{}` ({}).",
+          literal,
+          self.src.get(tok.range()).unwrap_or(""),
+          tok.kind()
+        ),
+        line,
+        column,
+      )),
+      None => errors.push(DiagnosticError::new(
+        format!("Expected `{}` after `{}`.", TokenKind::Semicolon, literal),
+        line,
+        column,
+      )),
+    }
+
+    match keyword.kind() {
+      TokenKind::Break => Node::Break(keyword),
+      _ => Node::Continue(keyword),
+    }
+  }
+
+  // Parses the parenthesized clauses of a `for`.
+  fn parse_for_clauses(&mut self) -> Result<(Option<Node>, Node, Option<Node>), DiagnosticError> {
+    self.expect(TokenKind::LeftParen, "`(`")?;
+
+    let init = match self.lexer.current_token().map(Token::kind) {
+      Some(TokenKind::Semicolon) => None,
+      _ => Some(self.parse_clause()?),
+    };
+
+    self.expect(TokenKind::Semicolon, "`;`")?;
+
+    let condition = self.parse_expr()?;
+
+    self.expect(TokenKind::Semicolon, "`;`")?;
+
+    let step = match self.lexer.current_token().map(Token::kind) {
+      Some(TokenKind::RightParen) => None,
+      _ => Some(self.parse_clause()?),
+    };
+
+    self.expect(TokenKind::RightParen, "`)`")?;
 
+    Ok((init, condition, step))
+  }
+
+  // Parses an assignment that isn't ended by a `;`, like the clauses of a `for`.
+  fn parse_clause(&mut self) -> Result<Node, DiagnosticError> {
+    let ident = self.expect(TokenKind::Identifier, "an `Identifier`")?;
+    let ident = IdentifierNode {
+      literal: self.src.get(ident.range()).unwrap().to_string(),
+      range: ident.range(),
+      line: ident.line(),
+    };
+
+    let expr = match self.parse_step(&ident) {
+      Some(step) => step,
       None => {
-        let sec_last = self.lexer.tokens.get(self.lexer.token_pos - 2).unwrap();
-        let sec_last_info = token_info(self.src, sec_last);
+        self.expect(TokenKind::Equal, "`=`")?;
+        self.parse_assigned()?
+      }
+    };
 
-        Err(DiagnosticError::new(
+    Ok(Node::Assignment(
+      Box::new(Node::Identifier(ident)),
+      Box::new(expr),
+    ))
+  }
+
+  // Parses the expression of an assignment, which can be another assignment, eg the `b = 1` in
+  // `a = b = 1;`.
+  fn parse_assigned(&mut self) -> Result<Node, DiagnosticError> {
+    let chained = matches!(
+      (
+        self.lexer.current_token().map(Token::kind),
+        self.lexer.peek_token().map(Token::kind)
+      ),
+      (Some(TokenKind::Identifier), Some(TokenKind::Equal))
+    );
+
+    if !chained {
+      return self.parse_expr();
+    }
+
+    let ident = self.lexer.current_token().cloned().unwrap();
+    self.lexer.advance();
+    self.lexer.advance();
+
+    Ok(Node::Assignment(
+      Box::new(Node::Identifier(IdentifierNode {
+        literal: self.src.get(ident.range()).unwrap().to_string(),
+        range: ident.range(),
+        line: ident.line(),
+      })),
+      Box::new(self.parse_assigned()?),
+    ))
+  }
+
+  // Parses the comma-separated values of a multiple assignment, eg the `1, 2` in `a, b = 1, 2;`.
+  fn parse_values(&mut self) -> Result<Vec<Node>, DiagnosticError> {
+    let mut values = vec![self.parse_expr()?];
+
+    while matches!(
+      self.lexer.current_token().map(Token::kind),
+      Some(TokenKind::Comma)
+    ) {
+      self.lexer.advance();
+      values.push(self.parse_expr()?);
+    }
+
+    Ok(values)
+  }
+
+  // Reports a multiple assignment that doesn't have a value for each variable, or that assigns
+  // to the same variable twice.
+  fn check_multi_assignment(
+    &self,
+    targets: &[IdentifierNode],
+    values: &[Node],
+    errors: &mut Vec<DiagnosticError>,
+  ) {
+    let names = targets
+      .iter()
+      .map(|ident| ident.literal.as_str())
+      .collect::<Vec<_>>();
+    let (line, column) = self.line_index.line_col(targets[0].range.start);
+
+    if targets.len() != values.len() {
+      errors.push(DiagnosticError::new(
+        format!(
+          "The assignment to `{}` is invalid. it has a different number of values, {}, than variables, {}.",
+          names.join(", "),
+          values.len(),
+          targets.len()
+        ),
+        line,
+        column,
+      ));
+    }
+
+    for (i, ident) in targets.iter().enumerate() {
+      if names[..i].contains(&ident.literal.as_str()) {
+        let (line, column) = self.line_index.line_col(ident.range.start);
+
+        errors.push(DiagnosticError::new(
           format!(
-            "Expected either `+`, `-`, `(`, an `Identifier`, or a `Literal` after `{}`",
-            &sec_last_info.literal
+            "The variable `{}` is assigned more than once by the same assignment.",
+            ident.literal
           ),
-          sec_last.line(),
-          sec_last_info.column + 1,
-        ))
+          line,
+          column,
+        ));
       }
     }
   }
-}
 
-impl LexerManager {
-  /// Returns the current [Token]
-  pub fn current_token(&self) -> Option<&Token> {
-    self.tokens.get(self.token_pos)
+  // Parses the `++` or `--` after the variable, if there is one, into the expression that it's
+  // short for, eg `x + 1`.
+  fn parse_step(&mut self, ident: &IdentifierNode) -> Option<Node> {
+    let op = match self.lexer.current_token().map(Token::kind) {
+      Some(TokenKind::PlusPlus) => Operator::Plus,
+      Some(TokenKind::MinusMinus) => Operator::Minus,
+      _ => return None,
+    };
+
+    self.lexer.advance();
+
+    Some(Node::Expression(Box::new(Node::Term(
+      Box::new(Node::Identifier(ident.clone())),
+      op,
+      Box::new(Node::Literal(LiteralNode {
+        value: Value::Int(1),
+      })),
+    ))))
   }
 
-  /// Returns the previous [Token].
-  pub fn previous_token(&self) -> Option<&Token> {
-    self.tokens.get(self.token_pos - 1)
+  // Parses statements surrounded by braces.
+  fn parse_block(&mut self, errors: &mut Vec<DiagnosticError>) -> Option<Node> {
+    let open = match self.lexer.current_token().cloned() {
+      Some(tok) if matches!(tok.kind(), TokenKind::LeftBrace) => tok,
+      _ => {
+        errors.push(self.expect(TokenKind::LeftBrace, "`{`").unwrap_err());
+
+        return None;
+      }
+    };
+
+    if let Err(e) = self.nest(&open) {
+      errors.push(e);
+      self.skip_block();
+
+      return None;
+    }
+
+    self.lexer.advance();
+    self.blocks += 1;
+
+    let mut statements = Vec::new();
+    self.parse_assignment(&mut statements, errors);
+
+    self.blocks -= 1;
+    self.depth -= 1;
+
+    match self.lexer.current_token() {
+      Some(tok) if matches!(tok.kind(), TokenKind::RightBrace) => {
+        self.lexer.advance();
+
+        Some(Node::Block(statements))
+      }
+      // The statements only stop early at the end of the source
+      _ => {
+        let (line, column) = self.line_index.line_col(open.range().start);
+
+        errors.push(DiagnosticError::new(
+          "The `{` is never closed. it needs a `}` to end the block.".to_string(),
+          line,
+          column,
+        ));
+
+        None
+      }
+    }
   }
 
-  /// Advances the internal position of the current [Token].
-  pub fn advance(&mut self) {
-    if self.token_pos < self.tokens.len() {
-      self.token_pos += 1;
+  fn parse_expr(&mut self) -> Result<Node, DiagnosticError> {
+    Ok(Node::Expression(Box::new(self.parse_coalesce()?)))
+  }
+
+  // Parses the `??` operations of an expression, which bind the loosest.
+  fn parse_coalesce(&mut self) -> Result<Node, DiagnosticError> {
+    let mut expr = self.parse_or()?;
+
+    while matches!(
+      self.lexer.current_token().map(Token::kind),
+      Some(TokenKind::QuestionQuestion)
+    ) {
+      self.check_operator();
+      self.lexer.advance();
+
+      let rhs = self.parse_or()?;
+      expr = Node::Term(Box::new(expr), Operator::Coalesce, Box::new(rhs));
     }
+
+    Ok(expr)
+  }
+
+  // Parses the `||` operations of an expression.
+  fn parse_or(&mut self) -> Result<Node, DiagnosticError> {
+    let mut expr = self.parse_and()?;
+
+    while matches!(
+      self.lexer.current_token().map(Token::kind),
+      Some(TokenKind::PipePipe)
+    ) {
+      self.check_operator();
+      self.lexer.advance();
+
+      let rhs = self.parse_and()?;
+      expr = Node::Term(Box::new(expr), Operator::Or, Box::new(rhs));
+    }
+
+    Ok(expr)
+  }
+
+  // Parses the `&&` operations of an expression.
+  fn parse_and(&mut self) -> Result<Node, DiagnosticError> {
+    let mut expr = self.parse_comparison()?;
+
+    while matches!(
+      self.lexer.current_token().map(Token::kind),
+      Some(TokenKind::AmpersandAmpersand)
+    ) {
+      self.check_operator();
+      self.lexer.advance();
+
+      let rhs = self.parse_comparison()?;
+      expr = Node::Term(Box::new(expr), Operator::And, Box::new(rhs));
+    }
+
+    Ok(expr)
+  }
+
+  // Parses a comparison, or the shift that would be its left operand if there isn't one.
+  fn parse_comparison(&mut self) -> Result<Node, DiagnosticError> {
+    let mut expr = self.parse_shift()?;
+
+    while let Some(op) = self
+      .lexer
+      .current_token()
+      .and_then(|tok| comparison(tok.kind()))
+    {
+      self.check_operator();
+
+      // `a < b < c` would compare `a < b`, which is `0` or `1`, rather than `b`, to `c`
+      if matches!(&expr, Node::Term(_, lhs_op, _) if lhs_op.is_comparison()) {
+        let tok = self.lexer.current_token().unwrap();
+        let (line, column) = self.line_index.line_col(tok.range().start);
+
+        self.operator_errors.push(DiagnosticError::new(
+          format!(
+            "The `{}` can't compare the result of another comparison. it needs parentheses.",
+            self.src.get(tok.range()).unwrap()
+          ),
+          line,
+          column,
+        ));
+      }
+
+      self.lexer.advance();
+
+      let rhs = self.parse_shift()?;
+      expr = Node::Term(Box::new(expr), op, Box::new(rhs));
+    }
+
+    Ok(expr)
+  }
+
+  // Parses the `<<` and `>>` operations of an expression.
+  fn parse_shift(&mut self) -> Result<Node, DiagnosticError> {
+    let mut expr = self.parse_sum()?;
+
+    while let Some(op) = self.lexer.current_token().and_then(|tok| match tok.kind() {
+      TokenKind::LessLess => Some(Operator::ShiftLeft),
+      TokenKind::GreaterGreater => Some(Operator::ShiftRight),
+      _ => None,
+    }) {
+      self.check_operator();
+      self.lexer.advance();
+
+      let rhs = self.parse_sum()?;
+      expr = Node::Term(Box::new(expr), op, Box::new(rhs));
+    }
+
+    Ok(expr)
+  }
+
+  // Parses the `+` and `-` operations of an expression.
+  fn parse_sum(&mut self) -> Result<Node, DiagnosticError> {
+    fn parse_expr_inner(parser: &mut Parser, lhs_term: Node) -> Result<Node, DiagnosticError> {
+      parser.lexer.split_double_operator();
+
+      match parser.lexer.current_token().map(Token::kind) {
+        kind if matches!(kind, Some(TokenKind::Plus | TokenKind::Minus)) => {
+          // Advance since we saw `+`` or `-`
+          parser.lexer.advance();
+
+          let rhs_term = parser.parse_term()?;
+
+          // Recurse on the expression as needed
+          parse_expr_inner(
+            parser,
+            Node::Term(
+              Box::new(lhs_term),
+              if matches!(kind, Some(TokenKind::Plus)) {
+                Operator::Plus
+              } else {
+                Operator::Minus
+              },
+              Box::new(rhs_term),
+            ),
+          )
+        }
+        // If we got any other character besides `+` or `-`, then we're done recursing the expr
+        _ => Ok(lhs_term),
+      }
+    }
+
+    let lhs_term = self.parse_term()?;
+
+    parse_expr_inner(self, lhs_term)
+  }
+
+  fn parse_term(&mut self) -> Result<Node, DiagnosticError> {
+    fn parse_term_inner(parser: &mut Parser, lhs_fact: Node) -> Result<Node, DiagnosticError> {
+      match parser.lexer.current_token().map(Token::kind) {
+        Some(kind @ (TokenKind::Star | TokenKind::Slash | TokenKind::Percent)) => {
+          parser.check_operator();
+          // Advance token position since we saw `*`, `/`, or `%`
+          parser.lexer.advance();
+
+          let rhs_fact = parser.parse_fact()?;
+          let op = match kind {
+            TokenKind::Star => Operator::Multiply,
+            TokenKind::Slash => Operator::Divide,
+            _ => Operator::Modulo,
+          };
+
+          // Recurse on the term
+          parse_term_inner(
+            parser,
+            Node::Term(Box::new(lhs_fact), op, Box::new(rhs_fact)),
+          )
+        }
+        // Reserved operators are reported, but their operands are still parsed so that parsing
+        // can carry on after them
+        Some(TokenKind::Ampersand | TokenKind::Pipe) => {
+          parser.check_operator();
+          parser.lexer.advance();
+          parser.parse_fact()?;
+
+          parse_term_inner(parser, lhs_fact)
+        }
+        // If we got any other token besides `*`, `/`, or `%`, then we got parsed the entire term
+        _ => Ok(lhs_fact),
+      }
+    }
+
+    let lhs_fact = self.parse_fact()?;
+
+    parse_term_inner(self, lhs_fact)
+  }
+
+  fn parse_fact(&mut self) -> Result<Node, DiagnosticError> {
+    self.lexer.split_double_operator();
+
+    // Parentheses and unary operators both nest by parsing another fact
+    if let Some(tok) = self.lexer.current_token().cloned() {
+      if let Err(e) = self.nest(&tok) {
+        // The rest of the expression is skipped, rather than having each level that's left
+        // report the tokens that it didn't expect
+        self.skip_expression();
+
+        return Err(e);
+      }
+    }
+
+    let fact = self.parse_unary();
+    self.depth -= 1;
+
+    fact
+  }
+
+  // Counts another level of nesting, started by the token.
+  //
+  // # Returns
+  // Returns an error if it's nested deeper than allowed, in which case the level isn't counted.
+  fn nest(&mut self, token: &Token) -> Result<(), DiagnosticError> {
+    if self.depth >= self.max_depth {
+      let token_info = token_info(self.src, token);
+
+      return Err(DiagnosticError::new(
+        format!(
+          "The `{}` is nested too deeply. at most {} levels are allowed.",
+          token_info.literal, self.max_depth
+        ),
+        token_info.line,
+        token_info.column,
+      ));
+    }
+
+    self.depth += 1;
+
+    Ok(())
+  }
+
+  // Skips past the block that starts at the current `{`, including any blocks nested in it.
+  fn skip_block(&mut self) {
+    let mut open = 0;
+
+    while let Some(tok) = self.lexer.current_token() {
+      match tok.kind() {
+        TokenKind::LeftBrace => open += 1,
+        TokenKind::RightBrace => open -= 1,
+        TokenKind::EndOfFile => return,
+        _ => {}
+      }
+
+      self.lexer.advance();
+
+      if open == 0 {
+        return;
+      }
+    }
+  }
+
+  // Skips to the end of the current expression, which is the next `;`, `{`, or `}`.
+  fn skip_expression(&mut self) {
+    while !matches!(
+      self.lexer.current_token().map(Token::kind),
+      Some(
+        TokenKind::Semicolon | TokenKind::LeftBrace | TokenKind::RightBrace | TokenKind::EndOfFile
+      ) | None
+    ) {
+      self.lexer.advance();
+    }
+  }
+
+  // Parses a unary operation, or an atom and any postfix or power operations after it.
+  fn parse_unary(&mut self) -> Result<Node, DiagnosticError> {
+    let fact_token = self.lexer.current_token().cloned();
+
+    match fact_token {
+      Some(x)
+        if !matches!(
+          x.kind(),
+          TokenKind::Literal
+            | TokenKind::StringLiteral
+            | TokenKind::StringStart
+            | TokenKind::CharLiteral
+            | TokenKind::NoneLiteral
+            | TokenKind::LeftBracket
+            | TokenKind::Identifier
+            | TokenKind::LeftParen
+            | TokenKind::Minus
+            | TokenKind::Plus
+            | TokenKind::Let
+            | TokenKind::Match
+            | TokenKind::Bang
+        ) && !self.starts_lambda(&x) =>
+      {
+        let eof = matches!(x.kind(), TokenKind::EndOfFile);
+
+        let token_info = token_info(self.src, &x);
+        // If we're at the end, then the fact is expected right after the last token, rather
+        // than on any trailing lines
+        let (line, column) = match self.lexer.previous_token() {
+          Some(prev) if eof => self.line_index.after(prev),
+          _ => (token_info.line, token_info.column),
+        };
+
+        // Only advance if we're not at the end
+        if !eof {
+          self.lexer.advance();
+        }
+
+        Err(DiagnosticError::new(
+          format!(
+            "Expected either {}, but found `{}` ({})",
+            self.expected_fact(),
+            &token_info.literal,
+            x.kind()
+          ),
+          line,
+          column,
+        ))
+      }
+
+      Some(x) if matches!(x.kind(), TokenKind::Bang) => {
+        self.check_operator();
+        self.lexer.advance();
+
+        let fact = self.parse_fact()?;
+
+        Ok(Node::Fact(Box::new(Node::UnaryOperator(
+          Operator::Not,
+          Box::new(fact),
+        ))))
+      }
+
+      // Unary operations, which apply to the whole power, so `-2 ** 2` is `-4`
+      Some(x) if matches!(x.kind(), TokenKind::Minus) => {
+        self.lexer.advance();
+
+        let fact = self.parse_fact()?;
+
+        Ok(Node::Fact(Box::new(Node::UnaryOperator(
+          Operator::Minus,
+          Box::new(fact),
+        ))))
+      }
+      Some(x) if matches!(x.kind(), TokenKind::Plus) => {
+        self.lexer.advance();
+
+        let fact = self.parse_fact()?;
+
+        Ok(Node::Fact(Box::new(Node::UnaryOperator(
+          Operator::Plus,
+          Box::new(fact),
+        ))))
+      }
+
+      _ => {
+        let mut base = self.parse_atom(fact_token)?;
+
+        // Indexes and fields bind tighter than powers, so `a[0] ** 2` squares the element
+        while let Some(postfix) = self
+          .lexer
+          .current_token()
+          .filter(|tok| matches!(tok.kind(), TokenKind::LeftBracket | TokenKind::Dot))
+          .cloned()
+        {
+          self.lexer.advance();
+
+          if matches!(postfix.kind(), TokenKind::Dot) {
+            // A name after the `.` is a field of a struct, eg `p.x`
+            if let Some(field) = self
+              .lexer
+              .current_token()
+              .filter(|tok| matches!(tok.kind(), TokenKind::Identifier) && self.features().structs)
+              .cloned()
+            {
+              self.lexer.advance();
+
+              base = Node::NamedField(postfix, Box::new(base), self.identifier(&field));
+              continue;
+            }
+
+            let index = self.parse_field_index(&postfix)?;
+
+            base = Node::Field(postfix, Box::new(base), index);
+            continue;
+          }
+
+          let index = self.parse_expr()?;
+          self.expect(TokenKind::RightBracket, "a `]`")?;
+
+          base = Node::Index(postfix, Box::new(base), Box::new(index));
+        }
+
+        // Powers are right associative, so `2 ** 3 ** 2` is `2 ** 9`
+        if matches!(
+          self.lexer.current_token().map(Token::kind),
+          Some(TokenKind::StarStar)
+        ) {
+          self.check_operator();
+          self.lexer.advance();
+
+          let exponent = self.parse_fact()?;
+
+          Ok(Node::Term(
+            Box::new(base),
+            Operator::Power,
+            Box::new(exponent),
+          ))
+        } else {
+          Ok(base)
+        }
+      }
+    }
+  }
+
+  // Lists the tokens that can start a fact in this version of the language, for when one is
+  // missing.
+  fn expected_fact(&self) -> String {
+    let features = self.features();
+    let mut expected = vec!["`+`", "`-`", "`(`"];
+
+    if features.operators {
+      expected.push("`!`");
+    }
+
+    if features.arrays {
+      expected.push("`[`");
+    }
+
+    if features.lambdas {
+      expected.push("`|`");
+    }
+
+    if features.keywords {
+      expected.extend(["`let`", "`match`", "`none`"]);
+    }
+
+    expected.push("an `Identifier`");
+
+    if features.strings {
+      expected.push("a string");
+    }
+
+    if features.chars {
+      expected.push("a character");
+    }
+
+    format!("{}, or a `Literal`", expected.join(", "))
+  }
+
+  // Returns the syntax that the program can use.
+  fn features(&self) -> Features {
+    self.pragmas.version.unwrap_or(self.version).features()
+  }
+
+  // Reports the current token if it's an operator that this version of the language doesn't
+  // have.
+  fn check_operator(&mut self) {
+    let tok = self.lexer.current_token().cloned().unwrap();
+    let reason = match tok.kind() {
+      kind
+        if !self.features().operators && (is_v2_operator(kind) || comparison(kind).is_some()) =>
+      {
+        "it needs `--lang-version 2`"
+      }
+      TokenKind::Ampersand | TokenKind::Pipe => "it's reserved for a later version",
+      _ => return,
+    };
+    let (line, column) = self.line_index.line_col(tok.range().start);
+
+    self.operator_errors.push(DiagnosticError::new(
+      format!(
+        "The `{}` operator is not supported in this language version. {}.",
+        self.src.get(tok.range()).unwrap(),
+        reason
+      ),
+      line,
+      column,
+    ));
+  }
+
+  fn parse_atom(&mut self, atom_token: Option<Token>) -> Result<Node, DiagnosticError> {
+    match atom_token {
+      Some(x) if matches!(x.kind(), TokenKind::Literal) => {
+        self.lexer.advance();
+
+        let num_str = self.src.get(x.range()).unwrap();
+
+        match x.value() {
+          Some(Ok(lit)) => {
+            if lit.leading_zeros {
+              self.lint_leading_zeros(&x, num_str)?;
+            }
+
+            Ok(Node::Literal(LiteralNode { value: lit.value }))
+          }
+          Some(Err(LiteralError::Overflow)) if num_str.contains('.') => Err(DiagnosticError::new(
+            format!(
+              "The float, `{}`, is invalid. floats must be finite.",
+              num_str
+            ),
+            x.line(),
+            x.range().start + 1 - linebreak_index(self.src, x.range()),
+          )),
+          Some(Err(LiteralError::Overflow)) => Err(DiagnosticError::new(
+            format!(
+              "The integer,`{}`, is invalid. integers must be in the range [{}, {}].",
+              num_str,
+              isize::MIN,
+              isize::MAX
+            ),
+            x.line(),
+            // Point to the start of the invalid integer
+            x.range().start + 1 - linebreak_index(self.src, x.range()),
+          )),
+          Some(Err(LiteralError::MissingDigits)) => Err(DiagnosticError::new(
+            format!(
+              "The integer, `{}`, is invalid. it needs {} digits after its prefix.",
+              num_str,
+              radix_name(num_str)
+            ),
+            x.line(),
+            x.range().start + 1 - linebreak_index(self.src, x.range()),
+          )),
+          Some(Err(LiteralError::InvalidDigit(offset))) => Err(DiagnosticError::new(
+            format!(
+              "The integer, `{}`, is invalid. `{}` isn't a {} digit.",
+              num_str,
+              &num_str[offset..offset + 1],
+              radix_name(num_str)
+            ),
+            x.line(),
+            // Point to the invalid digit, rather than the start of the integer
+            x.range().start + offset + 1 - linebreak_index(self.src, x.range()),
+          )),
+          // Only strings have escapes
+          Some(Err(LiteralError::InvalidEscape(_) | LiteralError::InvalidLength)) => {
+            unreachable!("number literals can't have escapes or lengths")
+          }
+          // The lexer attaches a value to every literal token
+          None => unreachable!("literal token without a value"),
+        }
+      }
+
+      Some(x) if matches!(x.kind(), TokenKind::StringLiteral | TokenKind::CharLiteral) => {
+        self.lexer.advance();
+
+        Ok(Node::Literal(LiteralNode {
+          value: self.string_value(&x)?,
+        }))
+      }
+
+      Some(x) if matches!(x.kind(), TokenKind::NoneLiteral) => {
+        self.lexer.advance();
+
+        Ok(Node::Literal(LiteralNode { value: Value::None }))
+      }
+
+      Some(x) if matches!(x.kind(), TokenKind::StringStart) => {
+        self.lexer.advance();
+
+        let mut segments = Vec::new();
+        let mut piece = x;
+        // An invalid escape is only reported after the whole string is parsed, so that the
+        // pieces after it aren't mistaken for the start of a statement
+        let mut escape_error = None;
+
+        loop {
+          match self.string_value(&piece) {
+            // Empty text isn't kept, eg before the `${` of `"${x}"`
+            Ok(Value::Str(text)) if !text.is_empty() => {
+              segments.push(Segment::Text(text.to_string()))
+            }
+            Ok(_) => {}
+            Err(e) => {
+              escape_error.get_or_insert(e);
+            }
+          }
+
+          if matches!(piece.kind(), TokenKind::StringEnd) {
+            break;
+          }
+
+          segments.push(Segment::Expression(self.parse_expr()?));
+
+          piece = match self.lexer.current_token().cloned() {
+            Some(next) if matches!(next.kind(), TokenKind::StringMiddle) => {
+              self.lexer.advance();
+              next
+            }
+            _ => self.expect(TokenKind::StringEnd, "a `}`")?,
+          };
+        }
+
+        match escape_error {
+          Some(e) => Err(e),
+          None => Ok(Node::Interpolation(segments)),
+        }
+      }
+
+      Some(x) if matches!(x.kind(), TokenKind::Identifier) => {
+        self.lexer.advance();
+
+        let ident = self.identifier(&x);
+
+        if self.features().structs && self.starts_struct(&x) {
+          return self.parse_struct_value(ident);
+        }
+
+        // A name followed by `(` is a call of the function, eg `max(a, b)`
+        if self.features().calls
+          && matches!(
+            self.lexer.current_token().map(Token::kind),
+            Some(TokenKind::LeftParen)
+          )
+        {
+          return self.parse_call(ident);
+        }
+
+        Ok(Node::Identifier(ident))
+      }
+
+      Some(x) if matches!(x.kind(), TokenKind::LeftParen) => {
+        self.lexer.advance();
+
+        let expr = self.parse_expr()?;
+
+        // A `,` after the first expression makes it a tuple, eg `(x,)` rather than `(x)`
+        if matches!(
+          self.lexer.current_token().map(Token::kind),
+          Some(TokenKind::Comma)
+        ) && self.features().tuples
+        {
+          self.lexer.advance();
+
+          let mut elements = vec![expr];
+
+          while !matches!(
+            self.lexer.current_token().map(Token::kind),
+            Some(TokenKind::RightParen)
+          ) {
+            elements.push(self.parse_expr()?);
+
+            if !matches!(
+              self.lexer.current_token().map(Token::kind),
+              Some(TokenKind::Comma)
+            ) {
+              break;
+            }
+
+            self.lexer.advance();
+          }
+
+          self.expect(TokenKind::RightParen, "a `)`")?;
+
+          return Ok(Node::Tuple(elements));
+        }
+
+        match self.lexer.current_token().cloned() {
+          Some(x) if matches!(x.kind(), TokenKind::RightParen) => {
+            self.lexer.advance();
+          }
+          Some(x) => {
+            let expr_token = self.lexer.last_token().cloned().unwrap();
+            let expr_token_info = token_info(self.src, &expr_token);
+            let curr_token_info = token_info(self.src, &x);
+            let (line, column) = self.line_index.after(&expr_token);
+
+            // Leave terminators for the assignment to handle, so that they aren't also
+            // reported as missing
+            if !matches!(x.kind(), TokenKind::Semicolon | TokenKind::EndOfFile) {
+              self.lexer.advance();
+            }
+
+            return Err(DiagnosticError::new(
+              format!(
+                "Expected a `)` after `{}`, but found `{}`",
+                expr_token_info.literal, curr_token_info.literal
+              ),
+              line,
+              column,
+            ));
+          }
+          None => {
+            let expr_token = self.lexer.last_token().unwrap();
+            let expr_token_info = token_info(self.src, expr_token);
+            let (line, column) = self.line_index.after(expr_token);
+
+            return Err(DiagnosticError::new(
+              format!("Expected a `)` after `{}`.", expr_token_info.literal),
+              line,
+              column,
+            ));
+          }
+        }
+
+        Ok(Node::Fact(Box::new(expr)))
+      }
+
+      Some(x) if matches!(x.kind(), TokenKind::LeftBracket) => {
+        self.lexer.advance();
+
+        let mut elements = Vec::new();
+
+        // The last element can be followed by a `,`, like the arms of a `match`
+        while !matches!(
+          self.lexer.current_token().map(Token::kind),
+          Some(TokenKind::RightBracket)
+        ) {
+          elements.push(self.parse_expr()?);
+
+          if !matches!(
+            self.lexer.current_token().map(Token::kind),
+            Some(TokenKind::Comma)
+          ) {
+            break;
+          }
+
+          self.lexer.advance();
+        }
+
+        self.expect(TokenKind::RightBracket, "a `]`")?;
+
+        Ok(Node::Array(elements))
+      }
+
+      Some(x) if matches!(x.kind(), TokenKind::Let) => {
+        self.lexer.advance();
+
+        let ident_token = self.expect(TokenKind::Identifier, "an `Identifier`")?;
+        self.expect(TokenKind::Equal, "`=`")?;
+        let value = self.parse_expr()?;
+        self.expect(TokenKind::In, "`in`")?;
+        let body = self.parse_expr()?;
+
+        Ok(Node::Let(
+          IdentifierNode {
+            literal: self.src.get(ident_token.range()).unwrap().to_string(),
+            line: ident_token.line(),
+            range: ident_token.range(),
+          },
+          Box::new(value),
+          Box::new(body),
+        ))
+      }
+
+      Some(x) if self.starts_lambda(&x) => self.parse_lambda(x),
+
+      Some(x) if matches!(x.kind(), TokenKind::Match) => {
+        self.lexer.advance();
+
+        let value = self.parse_expr()?;
+        self.expect(TokenKind::LeftBrace, "`{`")?;
+
+        let mut arms = Vec::new();
+
+        loop {
+          let pattern = self.parse_pattern()?;
+          self.expect(TokenKind::FatArrow, "`=>`")?;
+          arms.push((pattern, self.parse_expr()?));
+
+          if !matches!(
+            self.lexer.current_token().map(Token::kind),
+            Some(TokenKind::Comma)
+          ) {
+            break;
+          }
+
+          self.lexer.advance();
+
+          // The last arm can be followed by a `,`
+          if matches!(
+            self.lexer.current_token().map(Token::kind),
+            Some(TokenKind::RightBrace)
+          ) {
+            break;
+          }
+        }
+
+        self.expect(TokenKind::RightBrace, "`}`")?;
+        self.lint_non_exhaustive_match(&x, &arms);
+
+        Ok(Node::Match(x, Box::new(value), arms))
+      }
+
+      Some(other) => {
+        self.lexer.advance();
+
+        let token_info = token_info(self.src, &other);
+
+        Err(DiagnosticError::new(
+          format!(
+            "Unexpected `{}` ({}) found when parsing fact.",
+            other.kind(),
+            token_info.literal,
+          ),
+          token_info.line,
+          token_info.column,
+        ))
+      }
+
+      None => {
+        let sec_last = self.lexer.tokens.get(self.lexer.token_pos - 2).unwrap();
+        let sec_last_info = token_info(self.src, sec_last);
+
+        Err(DiagnosticError::new(
+          format!(
+            "Expected either {} after `{}`",
+            self.expected_fact(),
+            &sec_last_info.literal
+          ),
+          sec_last.line(),
+          sec_last_info.column + 1,
+        ))
+      }
+    }
+  }
+}
+
+impl Parser<'_> {
+  // Returns the value of a string or character literal, or of a piece of a string around a
+  // `${...}`, reporting an invalid escape in it.
+  fn string_value(&self, token: &Token) -> Result<Value, DiagnosticError> {
+    let literal = self.src.get(token.range()).unwrap();
+    let subject = match token.kind() {
+      TokenKind::CharLiteral => "character",
+      _ => "string",
+    };
+
+    match token.value() {
+      Some(Ok(lit)) => Ok(lit.value),
+      Some(Err(LiteralError::InvalidEscape(offset))) => {
+        let escape = literal[offset..].chars().take(2).collect::<String>();
+
+        Err(DiagnosticError::new(
+          format!(
+            "The {}, `{}`, is invalid. `{}` isn't an escape, expected one of `\\n`, `\\r`, \
+             `\\t`, `\\0`, `\\\\`, `\\\"`, `\\'`, or `\\$`.",
+            subject, literal, escape
+          ),
+          token.line(),
+          // Point to the invalid escape, rather than the start of the string
+          token.range().start + offset + 1 - linebreak_index(self.src, token.range()),
+        ))
+      }
+      Some(Err(LiteralError::InvalidLength)) => Err(DiagnosticError::new(
+        format!(
+          "The character, `{}`, is invalid. it needs exactly one character between its `'`s.",
+          literal
+        ),
+        token.line(),
+        token.range().start + 1 - linebreak_index(self.src, token.range()),
+      )),
+      // Strings and characters can only be invalid because of their contents
+      Some(Err(_)) | None => unreachable!("string token without a value"),
+    }
+  }
+
+  // Parses the index after the `.` of a tuple field, which has to be a decimal integer, eg the `0`
+  // of `t.0`.
+  fn parse_field_index(&mut self, dot: &Token) -> Result<usize, DiagnosticError> {
+    let tok = self.lexer.current_token().cloned();
+    let literal = tok.as_ref().and_then(|tok| self.src.get(tok.range()));
+
+    match (&tok, literal) {
+      (Some(tok), Some(literal))
+        if matches!(tok.kind(), TokenKind::Literal)
+          && literal.bytes().all(|b| b.is_ascii_digit()) =>
+      {
+        if let Ok(index) = literal.parse() {
+          self.lexer.advance();
+
+          return Ok(index);
+        }
+      }
+      _ => {}
+    }
+
+    let (line, column) = self.line_index.after(dot);
+
+    Err(DiagnosticError::new(
+      format!(
+        "Expected a field after `.`, eg `.0` or `.x`, but found `{}`.",
+        literal
+          .filter(|_| tok.is_some())
+          .unwrap_or("the end of the file")
+      ),
+      line,
+      column,
+    ))
+  }
+
+  // Consumes the current token if it's of the given kind, otherwise reports that it was expected
+  // right after the previous token.
+  fn expect(&mut self, kind: TokenKind, expected: &str) -> Result<Token, DiagnosticError> {
+    let prev_token = self.lexer.previous_token().cloned();
+
+    match self.lexer.current_token().cloned() {
+      Some(tok) if tok.kind() == kind => {
+        self.lexer.advance();
+
+        Ok(tok)
+      }
+      found => {
+        let (line, column) = match &prev_token {
+          Some(prev) => self.line_index.after(prev),
+          None => (1, 1),
+        };
+        let prev_literal = prev_token.map_or("", |prev| self.src.get(prev.range()).unwrap());
+
+        let msg = match found {
+          Some(tok) => {
+            // Leave terminators for the assignment to handle, so that they aren't also
+            // reported as missing
+            if !matches!(tok.kind(), TokenKind::Semicolon | TokenKind::EndOfFile) {
+              self.lexer.advance();
+            }
+
+            format!(
+              "Expected {} after `{}`, but found `{}` ({}).",
+              expected,
+              prev_literal,
+              self.src.get(tok.range()).unwrap(),
+              tok.kind()
+            )
+          }
+          None => format!("Expected {} after `{}`.", expected, prev_literal),
+        };
+
+        Err(DiagnosticError::new(msg, line, column))
+      }
+    }
+  }
+
+  // Reports an integer literal with leading zeros according to its lint level.
+  // Parses the pattern of a `match` arm, which is a literal, a negated literal, or `_`.
+  fn parse_pattern(&mut self) -> Result<Pattern, DiagnosticError> {
+    let negated = matches!(
+      self.lexer.current_token().map(Token::kind),
+      Some(TokenKind::Minus)
+    );
+
+    if negated {
+      self.lexer.advance();
+    }
+
+    match self.lexer.current_token().cloned() {
+      Some(x) if matches!(x.kind(), TokenKind::Literal | TokenKind::CharLiteral) => {
+        match self.parse_atom(Some(x))? {
+          Node::Literal(lit) if negated => Ok(Pattern::Literal(match lit.value {
+            Value::Int(value) => Value::Int(-value),
+            Value::Float(value) => Value::Float(-value),
+            Value::Str(_)
+            | Value::Array(_)
+            | Value::Tuple(_)
+            | Value::Struct(..)
+            | Value::Function(_)
+            | Value::None => unreachable!("number literals are parsed into numbers"),
+          })),
+          Node::Literal(lit) => Ok(Pattern::Literal(lit.value)),
+          _ => unreachable!("literal tokens are parsed into literals"),
+        }
+      }
+      Some(x) if !negated && matches!(x.kind(), TokenKind::StringLiteral) => {
+        match self.parse_atom(Some(x))? {
+          Node::Literal(lit) => Ok(Pattern::Literal(lit.value)),
+          _ => unreachable!("string tokens are parsed into literals"),
+        }
+      }
+      Some(x) if !negated && matches!(x.kind(), TokenKind::NoneLiteral) => {
+        self.lexer.advance();
+
+        Ok(Pattern::Literal(Value::None))
+      }
+      Some(x) if !negated && self.src.get(x.range()) == Some("_") => {
+        self.lexer.advance();
+
+        Ok(Pattern::Wildcard)
+      }
+      found => {
+        let tok = found.unwrap_or_else(|| self.lexer.last_token().cloned().unwrap());
+        let info = token_info(self.src, &tok);
+
+        // Leave terminators for the assignment to handle, so that they aren't also reported
+        // as missing
+        if !matches!(tok.kind(), TokenKind::Semicolon | TokenKind::EndOfFile) {
+          self.lexer.advance();
+        }
+
+        Err(DiagnosticError::new(
+          format!(
+            "Expected a `Literal` or `_` as the pattern of a `match` arm, but found `{}` ({}).",
+            info.literal,
+            tok.kind()
+          ),
+          info.line,
+          info.column,
+        ))
+      }
+    }
+  }
+
+  // Reports a `match` without a `_` arm, since any value that no arm matches is an error.
+  fn lint_non_exhaustive_match(&mut self, keyword: &Token, arms: &[(Pattern, Node)]) {
+    if arms
+      .iter()
+      .any(|(pattern, _)| matches!(pattern, Pattern::Wildcard))
+    {
+      return;
+    }
+
+    let (line, column) = self.line_index.line_col(keyword.range().start);
+    let msg = "The `match` has no `_` arm, so values that no arm matches are an error.";
+
+    match self.lints.level(Lint::NonExhaustiveMatch) {
+      LintLevel::Allow => {}
+      LintLevel::Warn => {
+        self
+          .warnings
+          .push(DiagnosticError::warning(msg.to_string(), line, column))
+      }
+      LintLevel::Deny => self.operator_errors.push(DiagnosticError::new(
+        "The `match` is invalid. it needs a `_` arm for values that no arm matches.".to_string(),
+        line,
+        column,
+      )),
+    }
+  }
+
+  fn lint_leading_zeros(&mut self, token: &Token, literal: &str) -> Result<(), DiagnosticError> {
+    let line = token.line();
+    // Point to the start of the integer
+    let column = token.range().start + 1 - linebreak_index(self.src, token.range());
+
+    match self.lints.level(Lint::LeadingZeros) {
+      LintLevel::Allow => Ok(()),
+      LintLevel::Warn => {
+        self.warnings.push(DiagnosticError::warning(
+          format!(
+            "The integer, `{}`, has leading zeros, which are ignored.",
+            literal
+          ),
+          line,
+          column,
+        ));
+
+        Ok(())
+      }
+      LintLevel::Deny => Err(DiagnosticError::new(
+        format!(
+          "The integer, `{}`, is invalid. literals must be either 0 or non-zero digits.",
+          literal
+        ),
+        line,
+        column,
+      )),
+    }
+  }
+}
+
+impl LexerManager {
+  /// Returns the current [Token]
+  pub fn current_token(&self) -> Option<&Token> {
+    self.tokens.get(self.token_pos)
+  }
+
+  /// Returns the [Token] after the current one, if there is one.
+  pub fn peek_token(&self) -> Option<&Token> {
+    self.tokens.get(self.token_pos + 1)
+  }
+
+  /// Returns the [Token] `n` tokens after the current one, if there is one.
+  pub fn peek_nth_token(&self, n: usize) -> Option<&Token> {
+    self.tokens.get(self.token_pos + n)
+  }
+
+  /// Returns the previous [Token], if we're not at the start.
+  pub fn previous_token(&self) -> Option<&Token> {
+    self
+      .token_pos
+      .checked_sub(1)
+      .and_then(|pos| self.tokens.get(pos))
+  }
+
+  /// Returns the previous [Token], or the current one if we're at the start, for errors that are
+  /// reported after whatever came last.
+  pub fn last_token(&self) -> Option<&Token> {
+    self.previous_token().or_else(|| self.current_token())
+  }
+
+  /// Splits a current `++` or `--` into two `+` or `-` tokens, since they're only a single
+  /// token in `x++;` and `x--;` statements, eg `1--2` is `1 - -2`.
+  pub fn split_double_operator(&mut self) {
+    let Some(tok) = self.current_token().cloned() else {
+      return;
+    };
+    let kind = match tok.kind() {
+      TokenKind::PlusPlus => TokenKind::Plus,
+      TokenKind::MinusMinus => TokenKind::Minus,
+      _ => return,
+    };
+    let start = tok.range().start;
+
+    self.tokens.splice(
+      self.token_pos..=self.token_pos,
+      [
+        Token::new(kind, start..start + 1, tok.line()),
+        Token::new(kind, start + 1..start + 2, tok.line()),
+      ],
+    );
+  }
+
+  /// Advances the internal position of the current [Token].
+  pub fn advance(&mut self) {
+    if self.token_pos < self.tokens.len() {
+      self.token_pos += 1;
+    }
+  }
+}
+
+// Returns the name of the base of a prefixed integer literal, eg `binary` for `0b1010`.
+fn radix_name(literal: &str) -> &'static str {
+  match literal.as_bytes().get(1) {
+    Some(b'x') => "hexadecimal",
+    Some(b'o') => "octal",
+    _ => "binary",
+  }
+}
+
+// Returns whether the token is an operator that was added in the second version of the language,
+// other than the comparisons.
+fn is_v2_operator(kind: TokenKind) -> bool {
+  matches!(
+    kind,
+    TokenKind::Slash
+      | TokenKind::Percent
+      | TokenKind::StarStar
+      | TokenKind::LessLess
+      | TokenKind::GreaterGreater
+      | TokenKind::Bang
+      | TokenKind::AmpersandAmpersand
+      | TokenKind::PipePipe
+      | TokenKind::QuestionQuestion
+  )
+}
+
+// Returns the comparison that the token is the operator of, if it's one.
+fn comparison(kind: TokenKind) -> Option<Operator> {
+  match kind {
+    TokenKind::EqualEqual => Some(Operator::Equal),
+    TokenKind::BangEqual => Some(Operator::NotEqual),
+    TokenKind::Less => Some(Operator::Less),
+    TokenKind::LessEqual => Some(Operator::LessEqual),
+    TokenKind::Greater => Some(Operator::Greater),
+    TokenKind::GreaterEqual => Some(Operator::GreaterEqual),
+    _ => None,
+  }
+}
+
+// Wraps the statement in a [Node::Conditional] for each of its `#[if: FLAG]` pragmas, with the
+// first one outermost.
+fn with_conditions(statement: Node, conditions: Vec<(String, Token)>) -> Node {
+  conditions
+    .into_iter()
+    .rev()
+    .fold(statement, |statement, (flag, _)| {
+      Node::Conditional(flag, Box::new(statement))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pragma::filter_statements;
+
+  // Returns the line, column, and message of every error.
+  fn errors(src: &str) -> Vec<(usize, usize, String)> {
+    Parser::new(src)
+      .parse()
+      .unwrap_err()
+      .iter()
+      .map(|err| (err.line(), err.column(), err.to_string()))
+      .collect()
+  }
+
+  #[test]
+  fn errors_at_the_start() {
+    for src in [
+      "* 2",
+      "; x = 1;",
+      "<= ++",
+      "] ! } >> %",
+      "<= / #[modulus: 7]",
+    ] {
+      assert!(!errors(src).is_empty(), "{src}");
+    }
+
+    assert_eq!(
+      errors("* 2")[0],
+      (
+        1,
+        1,
+        "Expected an `Identifier`, but found `*` (Star)".to_owned()
+      )
+    );
+    assert_eq!(
+      errors("; x = 1;")[0],
+      (
+        1,
+        1,
+        "Expected an `Identifier`, but found `;` (Semicolon)".to_owned()
+      )
+    );
+  }
+
+  #[test]
+  fn missing_semicolon_across_lines() {
+    assert_eq!(
+      errors("a = 1 +\n  2 *\n  3\nb = 4;"),
+      vec![(
+        3,
+        4,
+        "Expected a `Semicolon` after `3`, but found `b` (Identifier).".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn missing_paren_across_lines() {
+    assert_eq!(
+      errors("a = (1 +\n  2\n  *\n  3\n;"),
+      vec![(4, 4, "Expected a `)` after `3`, but found `;`".to_string())]
+    );
+  }
+
+  #[test]
+  fn let_expression() {
+    let program = Parser::new("x = 2 * let t = 1 + 2 in t * t;")
+      .parse()
+      .unwrap();
+
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(assign x (* 2 (let t (+ 1 2) (* t t))))"
+    );
+    assert_eq!(
+      errors("x = let t = 1 t;"),
+      vec![(
+        1,
+        14,
+        "Expected `in` after `1`, but found `t` (Identifier).".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn division_precedence() {
+    let program = Parser::new("x = 1 + 8 / 2 * 3 % 5;").parse().unwrap();
+
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(assign x (+ 1 (% (* (/ 8 2) 3) 5)))"
+    );
+  }
+
+  #[test]
+  fn power_associativity() {
+    let program = Parser::new("x = -2 ** 3 ** 2 * 4;").parse().unwrap();
+
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(assign x (* (- (** 2 (** 3 2))) 4))"
+    );
+  }
+
+  #[test]
+  fn print_statement() {
+    let program = Parser::new("print 1 + x;\n#[if: DEBUG] print x;")
+      .parse()
+      .unwrap();
+
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(print (+ 1 x))\n(if DEBUG (print x))"
+    );
+    assert!(Parser::new("print = 1;").parse().is_err());
+  }
+
+  #[test]
+  fn statement_terminators() {
+    let src = "x = 1\ny = x\n  + 2\nz = 3 4;";
+    let mut parser = Parser::new(src).with_terminator(Terminator::Inferred);
+    let errors = parser.parse().unwrap_err();
+
+    assert_eq!(parser.warnings().len(), 2);
+    assert_eq!((errors[0].line(), errors[0].column()), (4, 6));
+
+    let mut parser = Parser::new(src).with_terminator(Terminator::Lines);
+    let errors = parser.parse().unwrap_err();
+
+    assert!(parser.warnings().is_empty());
+    assert_eq!((errors[0].line(), errors[0].column()), (4, 6));
+    assert!(Parser::new("x = 1\ny = 2;").parse().is_err());
+  }
+
+  #[test]
+  fn read_statement() {
+    let program = Parser::new("read x;").parse().unwrap();
+
+    assert_eq!(program.sexpr().to_string(), "(read x)");
+    assert!(Parser::new("read 5;").parse().is_err());
+    assert!(Parser::new("read x + 1;").parse().is_err());
+  }
+
+  #[test]
+  fn import_statement() {
+    let program = Parser::new("import \"lib/a.toy\";").parse().unwrap();
+
+    assert_eq!(program.sexpr().to_string(), "(import \"lib/a.toy\")");
+    assert_eq!(
+      errors("import x;\n{ import \"a\"; }"),
+      vec![
+        (
+          1,
+          1,
+          "The import of `x` isn't of a string. it needs the path of a file, eg `import \
+           \"other.toy\";`."
+            .to_string()
+        ),
+        (
+          2,
+          3,
+          "The import of `a` is in a block. files can only be imported outside of any block."
+            .to_string()
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn if_statements() {
+    let program = Parser::new("if (x) { y = 1; } else if (z) { y = 2; } else { }")
+      .parse()
+      .unwrap();
+
+    assert_eq!(
+      program.to_string(),
+      "if (x) { y = 1; } else { if (z) { y = 2; } else { } }"
+    );
+
+    let errors = Parser::new("if (1) {\n  x = 1;\n}\n}\nif (2) {")
+      .parse()
+      .unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!((errors[0].line(), errors[0].column()), (4, 1));
+    assert_eq!((errors[1].line(), errors[1].column()), (5, 8));
+  }
+
+  #[test]
+  fn for_loops() {
+    let program = Parser::new("for (i = 3; i; i = i - 1) { x = i; }\nfor (; y;) { }")
+      .parse()
+      .unwrap();
+
+    assert_eq!(
+      program.to_string(),
+      "for (i = 3; i; i = i - 1) { x = i; }\nfor (; y; ) { }"
+    );
+    assert_eq!(
+      errors("for (i = 1 i) {\n  x = 1;\n}"),
+      vec![(
+        1,
+        11,
+        "Expected `;` after `1`, but found `i` (Identifier).".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn jumps() {
+    let program = Parser::new("for (; 1;) { if (x) { break; } continue; }")
+      .parse()
+      .unwrap();
+
+    assert_eq!(
+      program.to_string(),
+      "for (; 1; ) { if (x) { break; } continue; }"
+    );
+    assert_eq!(
+      errors("break;\nfor (; 1;) { }\ncontinue\nx = 1;"),
+      vec![
+        (
+          1,
+          1,
+          "The `break` isn't in a loop. it can only be used in the body of a `for`.".to_string()
+        ),
+        (
+          3,
+          1,
+          "The `continue` isn't in a loop. it can only be used in the body of a `for`.".to_string()
+        ),
+        (
+          3,
+          9,
+          "Expected a `Semicolon` after `continue`, but found `x` (Identifier).".to_string()
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn unsupported_operators() {
+    let src = "x = 7 % 2;\ny = (1 < 2) + !x;";
+    let errors = Parser::new(src)
+      .with_version(LangVersion::V1)
+      .parse()
+      .unwrap_err()
+      .iter()
+      .map(|err| (err.line(), err.column(), err.to_string()))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      errors,
+      vec![
+        (
+          1,
+          7,
+          "The `%` operator is not supported in this language version. it needs `--lang-version 2`."
+            .to_string()
+        ),
+        (
+          2,
+          8,
+          "The `<` operator is not supported in this language version. it needs `--lang-version 2`."
+            .to_string()
+        ),
+        (
+          2,
+          15,
+          "The `!` operator is not supported in this language version. it needs `--lang-version 2`."
+            .to_string()
+        ),
+      ]
+    );
+    assert!(Parser::new(&format!("#[lang: 2]\n{}", "x = 7 % 2;"))
+      .with_version(LangVersion::V1)
+      .parse()
+      .is_ok());
+  }
+
+  #[test]
+  fn comparisons() {
+    let program = Parser::new("x = a + 1 <= (b == c) * 2;").parse().unwrap();
+
+    assert_eq!(program.to_string(), "x = a + 1 <= (b == c) * 2;");
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(assign x (<= (+ a 1) (* (== b c) 2)))"
+    );
+    assert_eq!(
+      errors("x = 1 < 2 >= 3;"),
+      vec![(
+        1,
+        11,
+        "The `>=` can't compare the result of another comparison. it needs parentheses."
+          .to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn radix_literals() {
+    let program = Parser::new("x = 0x1F + 0o17 * 0b10;").parse().unwrap();
+
+    assert_eq!(program.to_string(), "x = 31 + 15 * 2;");
+    assert_eq!(
+      errors("x = 0x;\ny = 0b1021;"),
+      vec![
+        (
+          1,
+          5,
+          "The integer, `0x`, is invalid. it needs hexadecimal digits after its prefix."
+            .to_string()
+        ),
+        (
+          2,
+          9,
+          "The integer, `0b1021`, is invalid. `2` isn't a binary digit.".to_string()
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn match_expressions() {
+    let mut parser = Parser::new("x = 1 + match y % 3 { 0 => 1, -1.5 => y, _ => 2 * y, };");
+    let program = parser.parse().unwrap();
+
+    assert!(parser.warnings().is_empty());
+    assert_eq!(
+      program.to_string(),
+      "x = 1 + match y % 3 { 0 => 1, -1.5 => y, _ => 2 * y };"
+    );
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(assign x (+ 1 (match (% y 3) (0 1) (-1.5 y) (_ (* 2 y)))))"
+    );
+
+    let mut parser = Parser::new("x = match y { 0 => 1 };");
+    parser.parse().unwrap();
+
+    assert_eq!(
+      parser.warnings()[0].to_string(),
+      "The `match` has no `_` arm, so values that no arm matches are an error."
+    );
+    assert_eq!(
+      errors("x = match y { z => 1 };")[0],
+      (
+        1,
+        15,
+        "Expected a `Literal` or `_` as the pattern of a `match` arm, but found `z` (Identifier)."
+          .to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn increments() {
+    let program = Parser::new("x++;\ny--;\nfor (i = 0; i < 3; i++) { x++; }")
+      .parse()
+      .unwrap();
+
+    assert_eq!(
+      program.to_string(),
+      "x = x + 1;\ny = y - 1;\nfor (i = 0; i < 3; i = i + 1) { x = x + 1; }"
+    );
+
+    // Within an expression, they're two operators
+    let program = Parser::new("z = 1--2 ++ --x;").parse().unwrap();
+
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(assign z (+ (- 1 (- 2)) (+ (- (- x)))))"
+    );
+  }
+
+  #[test]
+  fn chained_assignments() {
+    let program = Parser::new("a = b = c == 1;\nfor (i = j = 0; i; i = i - 1) { x = 1; }")
+      .parse()
+      .unwrap();
+
+    assert_eq!(
+      program.to_string(),
+      "a = b = c == 1;\nfor (i = j = 0; i; i = i - 1) { x = 1; }"
+    );
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(assign a (assign b (== c 1)))\n(for (assign i (assign j 0)) i (assign i (- i 1)) (block (assign x 1)))"
+    );
+    assert_eq!(program.assigned_variables(), vec!["a", "b", "i", "j", "x"]);
+  }
+
+  #[test]
+  fn multiple_assignments() {
+    let program = Parser::new("a, b = b + 1, a;").parse().unwrap();
+
+    assert_eq!(program.to_string(), "a, b = b + 1, a;");
+    assert_eq!(program.sexpr().to_string(), "(assign (a b) (+ b 1) a)");
+    assert_eq!(
+      errors("a, b = 1;\nc, c = 1, 2;"),
+      vec![
+        (
+          1,
+          1,
+          "The assignment to `a, b` is invalid. it has a different number of values, 1, than variables, 2.".to_string()
+        ),
+        (
+          2,
+          4,
+          "The variable `c` is assigned more than once by the same assignment.".to_string()
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn logical_operators() {
+    let program = Parser::new("x = a || !b && c < 1 || d;").parse().unwrap();
+
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(assign x (|| (|| a (&& (! b) (< c 1))) d))"
+    );
+    assert_eq!(
+      errors("x = a & b;"),
+      vec![(
+        1,
+        7,
+        "The `&` operator is not supported in this language version. it's reserved for a later version."
+          .to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn conditional_statements() {
+    let program = Parser::new("#[if: A] #[if: B] x = 1;\ny = 2;")
+      .parse()
+      .unwrap();
+
+    assert_eq!(
+      program.sexpr().to_string(),
+      "(if A (if B (assign x 1)))\n(assign y 2)"
+    );
+    assert_eq!(
+      filter_statements(program.clone(), &["A".to_string()]).to_string(),
+      "y = 2;"
+    );
+    assert_eq!(
+      filter_statements(program, &["A".to_string(), "B".to_string()]).to_string(),
+      "x = 1;\ny = 2;"
+    );
+    assert_eq!(
+      errors("x = 1;\n#[if: A]"),
+      vec![(
+        2,
+        1,
+        "The pragma, `#[if: A]`, must be followed by an assignment.".to_string()
+      )]
+    );
+  }
+
+  #[test]
+  fn recovery_always_makes_progress() {
+    assert_eq!(errors("x = 3 ) 4;").len(), 7);
+  }
+
+  #[test]
+  fn missing_identifier_at_start() {
+    assert_eq!(errors("*").len(), 4);
+  }
+
+  #[test]
+  fn missing_fact_at_end_of_file() {
+    let errors = errors("a =\n\n  1\n  +\n");
+
+    assert_eq!(
+      errors
+        .iter()
+        .map(|(line, col, _)| (*line, *col))
+        .collect::<Vec<_>>(),
+      vec![(4, 4), (4, 4)]
+    );
+  }
+
+  #[test]
+  fn expected_facts() {
+    assert_eq!(
+      errors("x = ;")[0].2,
+      "Expected either `+`, `-`, `(`, `!`, `[`, `|`, `let`, `match`, `none`, an `Identifier`, a \
+       string, a character, or a `Literal`, but found `;` (Semicolon)"
+    );
+
+    // Older versions only list what they can parse
+    let errors = Parser::new("x = ;")
+      .with_version(LangVersion::V1)
+      .parse()
+      .unwrap_err();
+
+    assert_eq!(
+      errors[0].to_string(),
+      "Expected either `+`, `-`, `(`, an `Identifier`, or a `Literal`, but found `;` (Semicolon)"
+    );
+  }
+
+  #[test]
+  fn nesting_limit() {
+    let nested = |max_depth, src: &str| {
+      Parser::new(src)
+        .with_max_depth(max_depth)
+        .parse()
+        .map(|_| ())
+        .map_err(|errors| errors.iter().map(ToString::to_string).collect::<Vec<_>>())
+    };
+
+    assert!(nested(3, "x = ((1));").is_ok());
+    assert!(nested(3, "x = - - 1;").is_ok());
+    assert!(nested(3, "{ { x = 1; } }").is_ok());
+
+    // Only the first level that's too deep is reported, and parsing carries on after it
+    let too_deep = "The `{}` is nested too deeply. at most 3 levels are allowed.";
+
+    assert_eq!(
+      nested(3, "x = (((1)));\ny = - - - - 2;"),
+      Err(vec![
+        too_deep.replace("{}", "1"),
+        too_deep.replace("{}", "-"),
+      ])
+    );
+    assert_eq!(
+      nested(3, "{ { { { x = 1; } } } }\ny = 2;"),
+      Err(vec![too_deep.replace("{}", "{")])
+    );
+  }
+
+  #[test]
+  fn long_programs_dont_recurse() {
+    let src = "x = 1;\n".repeat(20_000);
+
+    assert!(Parser::new(&src).parse().is_ok());
   }
 }
@@ -0,0 +1,127 @@
+use crate::{node::Node, version::LangVersion};
+
+/// A directive that changes how the whole program is run, eg `#[modulus: 97]`, or how a single
+/// statement is, eg `#[if: DEBUG]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Pragma {
+  /// Makes all arithmetic happen modulo the number.
+  Modulus(isize),
+  /// Only keeps the following statement if the flag is passed with `--flag`.
+  If(String),
+  /// Sets the version of the language that the rest of the source is lexed with.
+  Lang(LangVersion),
+}
+
+/// The pragmas set by a program.
+#[derive(Clone, Debug, Default)]
+pub struct Pragmas {
+  /// The modulus that all arithmetic happens in, if any.
+  pub modulus: Option<isize>,
+  /// The version of the language set by the program, if any.
+  pub version: Option<LangVersion>,
+}
+
+impl Pragma {
+  /// Parses the source of a pragma token, including the surrounding `#[` and `]`.
+  ///
+  /// # Returns
+  /// Returns the message of the diagnostic to report if the pragma is invalid.
+  pub fn parse(src: &str) -> Result<Self, String> {
+    let inner = src
+      .strip_prefix("#[")
+      .and_then(|s| s.strip_suffix(']'))
+      .unwrap_or(src);
+
+    let (name, value) = inner.split_once(':').ok_or_else(|| {
+      format!(
+        "The pragma, `{}`, is invalid. pragmas must be of the form `#[name: value]`.",
+        src
+      )
+    })?;
+
+    match name.trim() {
+      "modulus" => match value.trim().parse::<isize>() {
+        Ok(modulus) if modulus > 0 => Ok(Pragma::Modulus(modulus)),
+        _ => Err(format!(
+          "The modulus, `{}`, is invalid. it must be a positive integer.",
+          value.trim()
+        )),
+      },
+      "lang" => LangVersion::parse(value).map(Pragma::Lang).ok_or_else(|| {
+        format!(
+          "The version, `{}`, is invalid. it must be either 1 or 2.",
+          value.trim()
+        )
+      }),
+      "if" => {
+        let flag = value.trim();
+        let is_identifier = flag.starts_with(|c: char| c.is_ascii_alphabetic())
+          && flag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if is_identifier {
+          Ok(Pragma::If(flag.to_string()))
+        } else {
+          Err(format!(
+            "The flag, `{}`, is invalid. flags must be identifiers.",
+            flag
+          ))
+        }
+      }
+      other => Err(format!("The pragma, `{}`, is unknown.", other)),
+    }
+  }
+}
+
+impl Pragmas {
+  /// Applies the pragma to this set.
+  pub fn set(&mut self, pragma: Pragma) {
+    match pragma {
+      Pragma::Modulus(modulus) => self.modulus = Some(modulus),
+      Pragma::Lang(version) => self.version = Some(version),
+      // Only applies to the statement that follows it
+      Pragma::If(_) => {}
+    }
+  }
+}
+
+/// Removes the statements of the program whose `#[if: FLAG]` flags weren't all passed, and
+/// unwraps the rest, including the statements in blocks.
+pub fn filter_statements(program: Node, flags: &[String]) -> Node {
+  fn filter(statement: Node, flags: &[String]) -> Option<Node> {
+    match statement {
+      Node::Conditional(flag, statement) if flags.contains(&flag) => filter(*statement, flags),
+      Node::Conditional(..) => None,
+      Node::If(keyword, condition, then, otherwise) => Some(Node::If(
+        keyword,
+        condition,
+        Box::new(filter_statements(*then, flags)),
+        otherwise.map(|otherwise| Box::new(filter_statements(*otherwise, flags))),
+      )),
+      Node::BlockStatement(brace, block) => Some(Node::BlockStatement(
+        brace,
+        Box::new(filter_statements(*block, flags)),
+      )),
+      Node::For(keyword, init, condition, step, body) => Some(Node::For(
+        keyword,
+        init,
+        condition,
+        step,
+        Box::new(filter_statements(*body, flags)),
+      )),
+      _ => Some(statement),
+    }
+  }
+
+  let filter_all = |statements: Vec<Node>| {
+    statements
+      .into_iter()
+      .filter_map(|statement| filter(statement, flags))
+      .collect()
+  };
+
+  match program {
+    Node::Program(statements) => Node::Program(filter_all(statements)),
+    Node::Block(statements) => Node::Block(filter_all(statements)),
+    _ => program,
+  }
+}
@@ -0,0 +1,483 @@
+use std::ops::Range;
+use toy_language::{
+  error::DiagnosticError,
+  node::{IdentifierNode, Node, Operator, Segment},
+  token::{Token, TokenKind},
+  LineIndex,
+};
+
+// Where a variable is used within the expression around it, which decides whether the
+// expression that it's replaced with has to be parenthesized.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Position {
+  // Anywhere delimited on both sides, eg a statement's expression or an array's element
+  Free,
+  // The left operand of the operator
+  Lhs(Operator),
+  // The right operand of the operator
+  Rhs(Operator),
+  // The operand of a unary operator
+  Unary,
+  // Before an index or field, eg the `a` of `a[0]`
+  Postfix,
+}
+
+// A use of the variable being inlined.
+struct Use<'n> {
+  ident: &'n IdentifierNode,
+  position: Position,
+  // Whether a `let` around the use binds a variable that the definition reads
+  captured: bool,
+}
+
+/// Inlines the variable, replacing each of its uses with the expression of its definition and
+/// removing the definition, eg `y = x * 2;` becomes `y = (a + 1) * 2;` for `x = a + 1;`.
+///
+/// The expression is only parenthesized where it would otherwise be parsed differently. The
+/// variable has to be assigned exactly once, by a statement of its own outside of any block, and
+/// nothing that the expression reads can be assigned after it, since that would change the value
+/// at the uses. The tokens have to be the ones that the program was parsed from.
+pub fn inline(
+  src: &str,
+  tokens: &[Token],
+  program: &Node,
+  name: &str,
+) -> Result<String, DiagnosticError> {
+  let line_index = LineIndex::new(src);
+  let error = |msg: String, offset: usize| {
+    let (line, column) = line_index.line_col(offset);
+
+    DiagnosticError::new(msg, line, column)
+  };
+
+  let statements = match program {
+    Node::Program(statements) => statements.as_slice(),
+    _ => std::slice::from_ref(program),
+  };
+  let mut writes = Vec::new();
+
+  for statement in statements {
+    collect_writes(statement, &mut writes);
+  }
+
+  // The definition is the first statement of the form `x = Exp;` or `const x = Exp;`
+  let definition = statements.iter().find_map(|statement| match statement {
+    Node::Assignment(ident, expr) => match (&**ident, &**expr) {
+      (_, Node::Assignment(..)) => None,
+      (Node::Identifier(ident), expr) if ident.literal == name => Some((statement, ident, expr)),
+      _ => None,
+    },
+    Node::ConstAssignment(_, ident, expr) if ident.literal == name => {
+      Some((statement, ident, &**expr))
+    }
+    _ => None,
+  });
+  let (statement, ident, expr) = match (definition, writes.iter().find(|w| w.literal == name)) {
+    (Some(definition), _) => definition,
+    (None, Some(write)) => {
+      return Err(error(
+        format!(
+          "`{}` can't be inlined, since it isn't assigned by a statement of its own. it needs \
+           to be defined like `{} = 1;`, outside of any block.",
+          name, name
+        ),
+        write.range.start,
+      ))
+    }
+    (None, None) => {
+      return Err(error(
+        format!("`{}` can't be inlined, since it's never assigned.", name),
+        0,
+      ))
+    }
+  };
+
+  if let Some(write) = writes
+    .iter()
+    .find(|write| write.literal == name && write.range != ident.range)
+  {
+    return Err(error(
+      format!(
+        "`{}` can't be inlined, since it's assigned more than once. it's defined on line {}.",
+        name, ident.line
+      ),
+      write.range.start,
+    ));
+  }
+
+  let mut reads = Vec::new();
+  collect_reads(expr, &mut reads);
+
+  if let Some(write) = writes
+    .iter()
+    .find(|write| write.range.start > ident.range.start && reads.contains(&&*write.literal))
+  {
+    return Err(error(
+      format!(
+        "`{}` can't be inlined, since `{}`, which it's defined with, is reassigned after it.",
+        name, write.literal
+      ),
+      write.range.start,
+    ));
+  }
+
+  let start = statement.start().unwrap();
+  let end = statement_end(tokens, start);
+  let value = src[after_equal(tokens, ident.range.start)..end].trim();
+  let value = value.strip_suffix(';').unwrap_or(value).trim_end();
+
+  let mut uses = Vec::new();
+
+  for statement in statements {
+    collect_uses(statement, name, &reads, Position::Free, false, &mut uses);
+  }
+
+  let mut edits = vec![(removal_range(src, start..end), String::new())];
+
+  for u in &uses {
+    if u.ident.range.start < end {
+      return Err(error(
+        format!(
+          "`{}` can't be inlined, since it's used before its definition on line {}.",
+          name, ident.line
+        ),
+        u.ident.range.start,
+      ));
+    }
+
+    if u.captured {
+      return Err(error(
+        format!(
+          "`{}` can't be inlined here, since a `let` around it binds a variable that it's \
+           defined with.",
+          name
+        ),
+        u.ident.range.start,
+      ));
+    }
+
+    let replacement = match needs_parentheses(expr, u.position) {
+      true => format!("({})", value),
+      false => value.to_string(),
+    };
+
+    edits.push((u.ident.range.clone(), replacement));
+  }
+
+  edits.sort_by_key(|(range, _)| range.start);
+
+  let mut inlined = String::with_capacity(src.len());
+  let mut last = 0;
+
+  for (range, replacement) in edits {
+    inlined.push_str(&src[last..range.start]);
+    inlined.push_str(&replacement);
+    last = range.end;
+  }
+
+  inlined.push_str(&src[last..]);
+
+  Ok(inlined)
+}
+
+// Returns the end of the assignment statement that starts at the offset, after its `;`.
+fn statement_end(tokens: &[Token], start: usize) -> usize {
+  let mut depth = 0usize;
+  let mut end = start;
+
+  for token in tokens.iter().skip_while(|tok| tok.range().start < start) {
+    match token.kind() {
+      TokenKind::LeftParen
+      | TokenKind::LeftBrace
+      | TokenKind::LeftBracket
+      | TokenKind::StringStart => depth += 1,
+      TokenKind::RightParen
+      | TokenKind::RightBrace
+      | TokenKind::RightBracket
+      | TokenKind::StringEnd => depth = depth.saturating_sub(1),
+      TokenKind::Semicolon if depth == 0 => return token.range().end,
+      TokenKind::EndOfFile => break,
+      _ => {}
+    }
+
+    end = token.range().end;
+  }
+
+  end
+}
+
+// Returns the offset just after the `=` of the assignment whose variable starts at the offset.
+fn after_equal(tokens: &[Token], start: usize) -> usize {
+  tokens
+    .iter()
+    .find(|tok| tok.range().start > start && matches!(tok.kind(), TokenKind::Equal))
+    .map_or(start, |tok| tok.range().end)
+}
+
+// Widens the range of a removed statement to its whole line when nothing else is on it, so that
+// no blank line is left behind, or to the spaces after it otherwise.
+fn removal_range(src: &str, range: Range<usize>) -> Range<usize> {
+  let line_start = src[..range.start].rfind('\n').map_or(0, |i| i + 1);
+  let rest = &src[range.end..];
+  let spaces = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+  let after = range.end + spaces;
+
+  let alone = src[line_start..range.start].trim().is_empty()
+    && (after == src.len() || src[after..].starts_with(['\n', '\r']));
+
+  match alone {
+    true => line_start..src[after..].find('\n').map_or(src.len(), |i| after + i + 1),
+    false => range.start..after,
+  }
+}
+
+// Returns whether the expression has to be parenthesized to be used at the position.
+fn needs_parentheses(expr: &Node, position: Position) -> bool {
+  match (expr, position) {
+    (_, Position::Free) => false,
+    // A parenthesized expression
+    (Node::Fact(inner), _) if matches!(**inner, Node::Expression(_)) => false,
+    (Node::Expression(inner) | Node::Fact(inner), _) => needs_parentheses(inner, position),
+    // A `let` would take everything after it as its body
+    (Node::Let(..), _) => true,
+    (Node::UnaryOperator(..), position) => matches!(
+      position,
+      Position::Unary | Position::Postfix | Position::Lhs(Operator::Power)
+    ),
+    (Node::Term(_, op, _), position) => {
+      let binds = op.precedence();
+
+      match position {
+        Position::Postfix | Position::Lhs(Operator::Power) => true,
+        Position::Unary | Position::Rhs(Operator::Power) => *op != Operator::Power,
+        Position::Lhs(outer) if !outer.is_comparison() => binds < outer.precedence(),
+        Position::Lhs(outer) | Position::Rhs(outer) => binds <= outer.precedence(),
+        Position::Free => false,
+      }
+    }
+    _ => false,
+  }
+}
+
+// Collects the variables that the statement assigns, including those in blocks.
+fn collect_writes<'n>(node: &'n Node, writes: &mut Vec<&'n IdentifierNode>) {
+  match node {
+    Node::Assignment(..) | Node::ConstAssignment(..) => {
+      let (idents, _) = node.assignment_chain().unwrap();
+
+      writes.extend(idents);
+    }
+    Node::MultiAssignment(idents, _) => writes.extend(idents),
+    Node::Read(_, ident) => writes.push(ident),
+    Node::Program(nodes) | Node::Block(nodes) => {
+      for node in nodes {
+        collect_writes(node, writes);
+      }
+    }
+    Node::If(_, _, then, otherwise) => {
+      for node in [then].into_iter().chain(otherwise) {
+        collect_writes(node, writes);
+      }
+    }
+    Node::For(_, init, _, step, body) => {
+      for node in init.iter().chain([body]).chain(step) {
+        collect_writes(node, writes);
+      }
+    }
+    Node::Conditional(_, statement) | Node::BlockStatement(_, statement) => {
+      collect_writes(statement, writes)
+    }
+    _ => {}
+  }
+}
+
+// Collects the names of the variables that the expression reads.
+fn collect_reads<'n>(node: &'n Node, reads: &mut Vec<&'n str>) {
+  if let Node::Identifier(ident) = node {
+    reads.push(&ident.literal);
+  }
+
+  let mut children = Vec::new();
+  children_of(node, &mut children);
+
+  for (child, _) in children {
+    collect_reads(child, reads);
+  }
+}
+
+// Collects the uses of the variable in the node, skipping those that a `let` shadows.
+fn collect_uses<'n>(
+  node: &'n Node,
+  name: &str,
+  reads: &[&str],
+  position: Position,
+  captured: bool,
+  uses: &mut Vec<Use<'n>>,
+) {
+  match node {
+    Node::Identifier(ident) if ident.literal == name => uses.push(Use {
+      ident,
+      position,
+      captured,
+    }),
+    // The variables of an assignment aren't uses, only its expression is
+    Node::Assignment(_, expr) => collect_uses(expr, name, reads, Position::Free, captured, uses),
+    Node::Let(ident, value, body) => {
+      collect_uses(value, name, reads, Position::Free, captured, uses);
+
+      if ident.literal != name {
+        let captured = captured || reads.contains(&ident.literal.as_str());
+
+        collect_uses(body, name, reads, Position::Free, captured, uses);
+      }
+    }
+    Node::Lambda(_, params, body) => {
+      if params.iter().all(|param| param.literal != name) {
+        let captured = captured
+          || params
+            .iter()
+            .any(|param| reads.contains(&param.literal.as_str()));
+
+        collect_uses(body, name, reads, Position::Free, captured, uses);
+      }
+    }
+    _ => {
+      let mut children = Vec::new();
+      children_of(node, &mut children);
+
+      for (child, child_position) in children {
+        // Wrappers are transparent, unless they're parentheses
+        let child_position = match node {
+          Node::Fact(inner) if matches!(**inner, Node::Expression(_)) => Position::Free,
+          Node::Expression(_) | Node::Fact(_) => position,
+          _ => child_position,
+        };
+
+        collect_uses(child, name, reads, child_position, captured, uses);
+      }
+    }
+  }
+}
+
+// Collects the nodes directly within the node, along with their positions in it.
+fn children_of<'n>(node: &'n Node, children: &mut Vec<(&'n Node, Position)>) {
+  match node {
+    Node::Program(nodes) | Node::Block(nodes) | Node::Array(nodes) | Node::Tuple(nodes) => {
+      children.extend(nodes.iter().map(|node| (node, Position::Free)))
+    }
+    Node::Call(_, args, _) => children.extend(args.iter().map(|node| (node, Position::Free))),
+    Node::MultiAssignment(_, values) => {
+      children.extend(values.iter().map(|node| (node, Position::Free)))
+    }
+    Node::Assignment(_, node)
+    | Node::ConstAssignment(_, _, node)
+    | Node::Print(_, node)
+    | Node::Conditional(_, node)
+    | Node::BlockStatement(_, node)
+    | Node::Expression(node)
+    | Node::Fact(node)
+    | Node::Lambda(_, _, node)
+    | Node::Return(_, Some(node)) => children.push((node, Position::Free)),
+    Node::If(_, condition, then, otherwise) => {
+      for node in [condition, then].into_iter().chain(otherwise) {
+        children.push((node, Position::Free));
+      }
+    }
+    Node::For(_, init, condition, step, body) => {
+      for node in init.iter().chain([condition, body]).chain(step) {
+        children.push((node, Position::Free));
+      }
+    }
+    Node::Term(lhs, op, rhs) => {
+      children.push((lhs, Position::Lhs(*op)));
+      children.push((rhs, Position::Rhs(*op)));
+    }
+    Node::UnaryOperator(_, node) => children.push((node, Position::Unary)),
+    Node::Let(_, value, body) => {
+      children.push((value, Position::Free));
+      children.push((body, Position::Free));
+    }
+    Node::Match(_, value, arms) => {
+      children.push((value, Position::Free));
+      children.extend(arms.iter().map(|(_, arm)| (arm, Position::Free)));
+    }
+    Node::Interpolation(segments) => {
+      for segment in segments {
+        if let Segment::Expression(expr) = segment {
+          children.push((expr, Position::Free));
+        }
+      }
+    }
+    Node::Index(_, array, index) => {
+      children.push((array, Position::Postfix));
+      children.push((index, Position::Free));
+    }
+    Node::Field(_, tuple, _) | Node::NamedField(_, tuple, _) => {
+      children.push((tuple, Position::Postfix))
+    }
+    Node::Struct(_, fields) => {
+      children.extend(fields.iter().map(|(_, value)| (value, Position::Free)))
+    }
+    Node::Read(..)
+    | Node::StructDeclaration(..)
+    | Node::Import(..)
+    | Node::Identifier(_)
+    | Node::Literal(_)
+    | Node::Return(_, None)
+    | Node::Break(_)
+    | Node::Continue(_) => {}
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use toy_language::{lexer::Lexer, parser::Parser};
+
+  fn inline_src(src: &str, name: &str) -> Result<String, String> {
+    let tokens = Lexer::new(src).lex();
+    let program = Parser::from_tokens(src, tokens.clone()).parse().unwrap();
+
+    inline(src, &tokens, &program, name).map_err(|err| err.to_string())
+  }
+
+  #[test]
+  fn parenthesizes_as_needed() {
+    let src = "a = 2;\nx = a + 1; /* sum */\ny = x * 2 + [x][0] - x;\nz = -x ** x;\nprint x;\n";
+
+    assert_eq!(
+      inline_src(src, "x").unwrap(),
+      "a = 2;\n/* sum */\ny = (a + 1) * 2 + [a + 1][0] - (a + 1);\nz = -(a + 1) ** (a + 1);\n\
+       print a + 1;\n"
+    );
+
+    let src = "x = (1 + 2) ** 2;\ny = 3 * x; z = x ** 2;";
+
+    assert_eq!(
+      inline_src(src, "x").unwrap(),
+      "y = 3 * (1 + 2) ** 2; z = ((1 + 2) ** 2) ** 2;"
+    );
+  }
+
+  #[test]
+  fn unsafe_inlines() {
+    assert_eq!(
+      inline_src("x = 1;\ny = x;\nx = 2;", "x").unwrap_err(),
+      "`x` can't be inlined, since it's assigned more than once. it's defined on line 1."
+    );
+    assert_eq!(
+      inline_src("a = 1;\nx = a;\na++;\ny = x;", "x").unwrap_err(),
+      "`x` can't be inlined, since `a`, which it's defined with, is reassigned after it."
+    );
+    assert_eq!(
+      inline_src("x = a;\ny = let a = 2 in x;", "x").unwrap_err(),
+      "`x` can't be inlined here, since a `let` around it binds a variable that it's defined \
+       with."
+    );
+    assert_eq!(
+      inline_src("if (1) { x = 1; }", "x").unwrap_err(),
+      "`x` can't be inlined, since it isn't assigned by a statement of its own. it needs to be \
+       defined like `x = 1;`, outside of any block."
+    );
+  }
+}
@@ -0,0 +1,461 @@
+use crate::{
+  capability::{Capabilities, Capability},
+  error::DiagnosticError,
+  include::{self, Expansion},
+  interpreter::Interpreter,
+  lexer::{invalid_tokens, Lexer},
+  limits::Limits,
+  lint::LintLevels,
+  node::Node,
+  parser::{Parser, Terminator},
+  pragma::{filter_statements, Pragmas},
+  token::Token,
+  typeck,
+  util::linebreak_index,
+  version::LangVersion,
+};
+use std::{
+  fs, iter,
+  path::{Path, PathBuf},
+};
+
+/// The phase that a file being run is in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+  /// Reading arguments and the source file.
+  Startup,
+  /// Lexing the source into tokens.
+  Lexing,
+  /// Parsing the tokens into an AST.
+  Parsing,
+  /// Checking the types of the AST.
+  Checking,
+  /// Evaluating the AST.
+  Evaluating,
+}
+
+/// What a [Runner] reports as it runs a file and the files that it imports, eg to print warnings
+/// as they happen.
+///
+/// Every method does nothing by default.
+pub trait Observer {
+  /// Called when a file starts a phase.
+  fn phase(&mut self, _file_name: &str, _src: &str, _phase: Phase) {}
+
+  /// Called with the tokens of every file that's lexed.
+  fn lexed(&mut self, _tokens: &[Token]) {}
+
+  /// Called with every file once the files that it includes are spliced into it.
+  fn expanded(&mut self, _file_name: &str, _expansion: &Expansion) {}
+
+  /// Called with the name of every file that's imported, before it's lexed.
+  fn imported(&mut self, _file_name: &str) {}
+
+  /// Called with the AST of every file that's parsed.
+  fn parsed(&mut self, _ast: &Node) {}
+
+  /// Called with the warnings that parsing or evaluating a file reported, if there are any.
+  fn warned(&mut self, _file_name: &str, _src: &str, _warnings: &[DiagnosticError]) {}
+
+  /// Called with the number of operations that evaluating some statements of a file took.
+  fn evaluated(&mut self, _operations: usize) {}
+}
+
+impl Observer for () {}
+
+/// The settings that a file and the files that it imports are lexed, parsed, and run with.
+#[derive(Clone, Debug, Default)]
+pub struct Settings {
+  /// The levels of the lints.
+  pub lints: LintLevels,
+  /// What ends a statement.
+  pub terminator: Terminator,
+  /// The version of the language.
+  pub version: LangVersion,
+  /// The flags that statements marked with `#[if: <flag>]` are run for.
+  pub flags: Vec<String>,
+  /// The caps on the size of each file.
+  pub limits: Limits,
+  /// The capabilities that the files are granted.
+  pub capabilities: Capabilities,
+}
+
+/// The errors of a file that failed to run, which may be one that was imported.
+#[derive(Clone, Debug)]
+pub struct Failure {
+  /// The name of the file, which is its path for imported files.
+  pub file_name: String,
+  /// The source of the file, with the files that it includes spliced in.
+  pub src: String,
+  /// The errors that the file reported.
+  pub errors: Vec<DiagnosticError>,
+}
+
+/// Runs files along with the files that they import, which are run where they're imported.
+pub struct Runner<O> {
+  settings: Settings,
+  observer: O,
+  // The files being imported, starting at the one that's run, along with the names that they're
+  // reported with. Importing one of them again would be a cycle
+  importing: Vec<(PathBuf, String)>,
+}
+
+impl Failure {
+  fn new(file_name: &str, src: &str, errors: Vec<DiagnosticError>) -> Self {
+    Self {
+      file_name: file_name.to_string(),
+      src: src.to_string(),
+      errors,
+    }
+  }
+}
+
+impl<O: Observer> Runner<O> {
+  /// Creates a new runner with the settings, which reports what happens to the observer.
+  pub fn new(settings: Settings, observer: O) -> Self {
+    Self {
+      settings,
+      observer,
+      importing: Vec::new(),
+    }
+  }
+
+  /// Returns the settings that files are run with.
+  pub fn settings(&self) -> &Settings {
+    &self.settings
+  }
+
+  /// Returns the observer.
+  pub fn observer(&self) -> &O {
+    &self.observer
+  }
+
+  /// Returns the observer mutably.
+  pub fn observer_mut(&mut self) -> &mut O {
+    &mut self.observer
+  }
+
+  /// Expands, lexes, parses, and runs the file in the interpreter, along with the files that it
+  /// imports.
+  ///
+  /// The interpreter is returned with the variables that the file set, and with the modulus that
+  /// it sets, if it has one. It's loaded with the program of the file, without the statements
+  /// whose flags weren't passed, so that its reduction steps are those of the file that's run
+  /// rather than the last one that it imports.
+  pub fn run_file(
+    &mut self,
+    mut interpreter: Interpreter,
+    file_name: &str,
+    src: &str,
+  ) -> Result<Interpreter, Failure> {
+    let fail = |errors| Failure::new(file_name, src, errors);
+    let src = self.expand_includes(file_name, src).map_err(fail)?;
+    let fail = |errors| Failure::new(file_name, &src, errors);
+    let tokens = self.lex(file_name, &src).map_err(fail)?;
+    let (ast, pragmas) = self.parse(file_name, &src, tokens).map_err(fail)?;
+
+    self.settings.limits.check(&src, &ast).map_err(fail)?;
+
+    if pragmas.modulus.is_some() {
+      interpreter = interpreter.with_modulus(pragmas.modulus);
+    }
+
+    interpreter = interpreter.with_max_call_depth(self.settings.limits.max_call_depth);
+    self.run(&mut interpreter, file_name, &src, ast.clone())?;
+    interpreter.load(&src, filter_statements(ast, &self.settings.flags));
+
+    Ok(interpreter)
+  }
+
+  /// Splices the files that the source includes into it, so that they're lexed as one source.
+  pub fn expand_includes(
+    &mut self,
+    file_name: &str,
+    src: &str,
+  ) -> Result<String, Vec<DiagnosticError>> {
+    let (expansion, errors) = include::expand(file_name, src, self.settings.capabilities);
+
+    self.observer.expanded(file_name, &expansion);
+
+    if errors.is_empty() {
+      Ok(expansion.src().to_string())
+    } else {
+      Err(errors)
+    }
+  }
+
+  /// Lexes the source, returning errors for any invalid tokens.
+  pub fn lex(&mut self, file_name: &str, src: &str) -> Result<Vec<Token>, Vec<DiagnosticError>> {
+    self.observer.phase(file_name, src, Phase::Lexing);
+
+    let tokens = Lexer::new(src).with_version(self.settings.version).lex();
+
+    self.observer.lexed(&tokens);
+
+    let lex_errors = invalid_tokens(src, &tokens);
+
+    if lex_errors.is_empty() {
+      Ok(tokens)
+    } else {
+      Err(lex_errors)
+    }
+  }
+
+  /// Parses the lexed tokens of the source and checks their types.
+  pub fn parse(
+    &mut self,
+    file_name: &str,
+    src: &str,
+    tokens: Vec<Token>,
+  ) -> Result<(Node, Pragmas), Vec<DiagnosticError>> {
+    self.observer.phase(file_name, src, Phase::Parsing);
+
+    let mut parser = Parser::from_tokens(src, tokens)
+      .with_lints(self.settings.lints.clone())
+      .with_terminator(self.settings.terminator)
+      .with_version(self.settings.version)
+      .with_max_depth(self.settings.limits.max_depth);
+    let parsed = parser.parse();
+
+    if !parser.warnings().is_empty() {
+      self.observer.warned(file_name, src, parser.warnings());
+    }
+
+    let ast = parsed?;
+
+    self.observer.parsed(&ast);
+
+    // Type mismatches are reported before anything runs
+    self.observer.phase(file_name, src, Phase::Checking);
+    typeck::check(src, &ast, parser.declarations())?;
+
+    Ok((ast, parser.pragmas().clone()))
+  }
+
+  /// Runs the parsed program of the file in the interpreter, running each file that it imports
+  /// where it's imported.
+  ///
+  /// The statements whose flags weren't passed are skipped.
+  pub fn run(
+    &mut self,
+    interpreter: &mut Interpreter,
+    file_name: &str,
+    src: &str,
+    ast: Node,
+  ) -> Result<(), Failure> {
+    self.importing = vec![(canonical_path(Path::new(file_name)), file_name.to_string())];
+
+    let ast = filter_statements(ast, &self.settings.flags);
+
+    self.run_program(interpreter, file_name, src, ast)
+  }
+
+  // Runs the program in the interpreter, running each file that it imports where it's imported.
+  //
+  // The statements between imports are evaluated on their own, so that the variables of an
+  // imported file are set before the statements after its import run.
+  fn run_program(
+    &mut self,
+    interpreter: &mut Interpreter,
+    file_name: &str,
+    src: &str,
+    ast: Node,
+  ) -> Result<(), Failure> {
+    let Node::Program(statements) = ast else {
+      unreachable!("the parser only returns programs");
+    };
+    let mut pending = Vec::new();
+
+    for statement in statements {
+      match statement {
+        Node::Import(keyword, path) => {
+          self.evaluate_statements(interpreter, file_name, src, std::mem::take(&mut pending))?;
+          self.import_file(interpreter, file_name, src, &keyword, &path)?;
+        }
+        statement => pending.push(statement),
+      }
+    }
+
+    self.evaluate_statements(interpreter, file_name, src, pending)
+  }
+
+  // Evaluates statements of the file, reporting any warnings.
+  fn evaluate_statements(
+    &mut self,
+    interpreter: &mut Interpreter,
+    file_name: &str,
+    src: &str,
+    statements: Vec<Node>,
+  ) -> Result<(), Failure> {
+    if statements.is_empty() {
+      return Ok(());
+    }
+
+    self.observer.phase(file_name, src, Phase::Evaluating);
+
+    interpreter.load(src, Node::Program(statements));
+    let result = interpreter.evaluate();
+
+    self.observer.evaluated(interpreter.operations());
+
+    if !interpreter.warnings().is_empty() {
+      self.observer.warned(file_name, src, interpreter.warnings());
+    }
+
+    result.map_err(|errors| Failure::new(file_name, src, errors))
+  }
+
+  // Lexes, parses, and runs the file that an import names, which is relative to the directory of
+  // the file that imports it.
+  fn import_file(
+    &mut self,
+    interpreter: &mut Interpreter,
+    file_name: &str,
+    src: &str,
+    keyword: &Token,
+    path: &str,
+  ) -> Result<(), Failure> {
+    let import_error = |msg| {
+      let column = keyword.range().start + 1 - linebreak_index(src, keyword.range());
+
+      Failure::new(
+        file_name,
+        src,
+        vec![DiagnosticError::new(msg, keyword.line(), column)],
+      )
+    };
+    // Nothing is read without the capability, so that the program can't see other files
+    if !self.settings.capabilities.is_granted(Capability::Import) {
+      return Err(import_error(format!(
+        "The capability `{}` is not granted, so `{}` can't be imported. it needs `--allow={}`.",
+        Capability::Import,
+        path,
+        Capability::Import
+      )));
+    }
+
+    let imported = Path::new(file_name)
+      .parent()
+      .unwrap_or(Path::new(""))
+      .join(path);
+    let imported_name = imported.to_string_lossy().into_owned();
+    let imported_src = fs::read_to_string(&imported).map_err(|err| {
+      import_error(format!(
+        "The file `{}` can't be imported. reading it failed with `{}`.",
+        path, err
+      ))
+    })?;
+    let imported_src = self
+      .expand_includes(&imported_name, &imported_src)
+      .map_err(|errors| Failure::new(&imported_name, &imported_src, errors))?;
+    let canonical = canonical_path(&imported);
+
+    if let Some(start) = self
+      .importing
+      .iter()
+      .position(|(file, _)| *file == canonical)
+    {
+      let chain = self.importing[start..]
+        .iter()
+        .map(|(_, name)| name.as_str())
+        .chain(iter::once(imported_name.as_str()))
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>();
+
+      return Err(import_error(format!(
+        "The import of `{}` is a cycle. the files import each other in the order {}.",
+        path,
+        chain.join(", ")
+      )));
+    }
+
+    self.observer.imported(&imported_name);
+
+    let fail = |errors| Failure::new(&imported_name, &imported_src, errors);
+    let tokens = self.lex(&imported_name, &imported_src).map_err(fail)?;
+    let (ast, pragmas) = self
+      .parse(&imported_name, &imported_src, tokens)
+      .map_err(fail)?;
+
+    // Imported files are run with the modulus of the file that's run, if it has one
+    if pragmas.modulus.is_some() {
+      return Err(import_error(format!(
+        "The file `{}` sets a modulus, so it can't be imported. only the file that's run can set \
+         one.",
+        path
+      )));
+    }
+
+    self
+      .settings
+      .limits
+      .check(&imported_src, &ast)
+      .map_err(fail)?;
+    self.importing.push((canonical, imported_name.clone()));
+
+    let ast = filter_statements(ast, &self.settings.flags);
+    let result = self.run_program(interpreter, &imported_name, &imported_src, ast);
+
+    self.importing.pop();
+
+    result
+  }
+}
+
+// Returns the path that a file is at, without any `.` or `..`, so that the same file imported by
+// different relative paths is recognized.
+fn canonical_path(path: &Path) -> PathBuf {
+  fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl std::fmt::Display for Phase {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Phase::Startup => write!(f, "starting up"),
+      Phase::Lexing => write!(f, "lexing"),
+      Phase::Parsing => write!(f, "parsing"),
+      Phase::Checking => write!(f, "checking types"),
+      Phase::Evaluating => write!(f, "evaluating"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Runs the source as `main.toy` with the capabilities, returning the messages of its errors.
+  fn run_errors(src: &str, capabilities: Capabilities) -> Vec<String> {
+    let settings = Settings {
+      capabilities,
+      ..Settings::default()
+    };
+    let interpreter = Interpreter::new(src, Node::Program(Vec::new()));
+
+    match Runner::new(settings, ()).run_file(interpreter, "main.toy", src) {
+      Ok(_) => Vec::new(),
+      Err(failure) => failure.errors.iter().map(ToString::to_string).collect(),
+    }
+  }
+
+  #[test]
+  fn imports_need_capability() {
+    let src = "import \"/etc/hostname\";";
+
+    assert_eq!(
+      run_errors(src, Capabilities::default()),
+      vec![
+        "The capability `import` is not granted, so `/etc/hostname` can't be imported. it needs \
+         `--allow=import`."
+      ]
+    );
+
+    let errors = run_errors(
+      "import \"missing.toy\";",
+      Capabilities::default().with(Capability::Import),
+    );
+
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("The file `missing.toy` can't be imported. reading it failed"));
+  }
+}
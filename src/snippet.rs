@@ -0,0 +1,135 @@
+/// The most characters of a line shown in a snippet, before it's trimmed around the column.
+pub const MAX_LINE_WIDTH: usize = 80;
+
+const ELLIPSIS: &str = "...";
+
+/// Renders the line of the source at the given line and column, with `context` lines before and
+/// after it, and a caret under the column.
+///
+/// The column is in bytes, like the columns of diagnostics, so the caret is placed under the
+/// character that starts at it, even if the line has characters that take more than a byte.
+///
+/// Lines are prefixed with a gutter of their line numbers, and lines longer than
+/// [MAX_LINE_WIDTH] are trimmed around the column, with `...` where they were cut.
+pub fn render(src: &str, line: usize, column: usize, context: usize) -> String {
+  // Splitting on every linebreak keeps the empty line after a trailing linebreak, where errors at
+  // the end of the source are reported
+  let lines = src
+    .split('\n')
+    .map(|line| line.strip_suffix('\r').unwrap_or(line))
+    .collect::<Vec<_>>();
+  let line = line.clamp(1, lines.len());
+  let first = line.saturating_sub(context).max(1);
+  let last = line.saturating_add(context).min(lines.len());
+  let width = last.to_string().len();
+  let column = char_column(lines[line - 1], column.max(1) - 1);
+  let error_len = lines[line - 1].chars().count();
+
+  // Every line is trimmed from the same start, so that the columns stay aligned
+  let start = if error_len > MAX_LINE_WIDTH {
+    column
+      .saturating_sub(MAX_LINE_WIDTH / 2)
+      .min(error_len - MAX_LINE_WIDTH)
+  } else {
+    0
+  };
+  let mut rendered = format!("{:width$} |\n", "");
+
+  for number in first..=last {
+    let text = lines[number - 1];
+
+    let numbered = format!("{:>width$} | {}", number, trim(text, start));
+
+    rendered.push_str(numbered.trim_end());
+    rendered.push('\n');
+
+    if number == line {
+      let offset = column - start + if start > 0 { ELLIPSIS.len() } else { 0 };
+
+      rendered.push_str(&format!("{:width$} | {:offset$}^\n", "", ""));
+    }
+  }
+
+  rendered.pop();
+  rendered
+}
+
+// Returns the number of characters of the line before the byte offset, counting any bytes past
+// its end as a character each.
+fn char_column(text: &str, offset: usize) -> usize {
+  let chars = text.char_indices().take_while(|(i, _)| *i < offset).count();
+
+  chars + offset.saturating_sub(text.len())
+}
+
+// Returns the characters of the line from the start, at most `MAX_LINE_WIDTH` of them, with `...`
+// where characters were cut.
+fn trim(text: &str, start: usize) -> String {
+  let len = text.chars().count();
+
+  if start == 0 && len <= MAX_LINE_WIDTH {
+    return text.to_string();
+  }
+
+  let mut trimmed = String::new();
+
+  if start > 0 && len > 0 {
+    trimmed.push_str(ELLIPSIS);
+  }
+
+  trimmed.extend(text.chars().skip(start).take(MAX_LINE_WIDTH));
+
+  if start + MAX_LINE_WIDTH < len {
+    trimmed.push_str(ELLIPSIS);
+  }
+
+  trimmed
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn context_lines() {
+    let src = "a = 1;\nb = 2 +;\nc = 3;\n";
+
+    assert_eq!(render(src, 2, 8, 0), "  |\n2 | b = 2 +;\n  |        ^");
+    assert_eq!(
+      render(src, 2, 8, 1),
+      "  |\n1 | a = 1;\n2 | b = 2 +;\n  |        ^\n3 | c = 3;"
+    );
+    assert_eq!(
+      render(src, 1, 1, 9),
+      "  |\n1 | a = 1;\n  | ^\n2 | b = 2 +;\n3 | c = 3;\n4 |"
+    );
+  }
+
+  #[test]
+  fn multibyte_characters() {
+    let src = "x = \"éééé\" +;";
+
+    // The `;` starts at byte 16, but it's the 12th character
+    assert_eq!(
+      render(src, 1, 17, 0),
+      "  |\n1 | x = \"éééé\" +;\n  |             ^"
+    );
+  }
+
+  #[test]
+  fn long_lines() {
+    let src = (0..200)
+      .map(|i| char::from(b'a' + (i % 26) as u8))
+      .collect::<String>();
+    let rendered = render(&src, 1, 100, 0);
+    let lines = rendered.lines().collect::<Vec<_>>();
+    let caret = lines[2].find('^').unwrap();
+
+    assert!(lines[1].starts_with("1 | ...") && lines[1].ends_with("..."));
+    assert_eq!(
+      lines[1].len(),
+      "1 | ".len() + MAX_LINE_WIDTH + 2 * ELLIPSIS.len()
+    );
+    assert_eq!(lines[1].chars().nth(caret), src.chars().nth(99));
+  }
+}
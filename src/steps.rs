@@ -0,0 +1,618 @@
+use crate::{
+  builtin::Builtin,
+  interpreter::Arithmetic,
+  node::{LiteralNode, Node, Operator, Segment},
+  value::Value,
+};
+use std::collections::HashMap;
+
+/// Returns every form of the expression as it's reduced one operation at a time, starting
+/// with the expression itself and ending with its value.
+///
+/// Operations are reduced leftmost-innermost first, the same order that the interpreter
+/// evaluates them in. Reduction stops early if a variable isn't in `variables`.
+pub fn reduction_steps(
+  expr: &Node,
+  variables: &HashMap<&str, Value>,
+  arithmetic: Arithmetic,
+) -> Vec<Node> {
+  let mut steps = vec![expr.clone()];
+
+  while let Some(next) = reduce(steps.last().unwrap(), variables, arithmetic) {
+    // Some reductions, like `-5` to the literal `-5`, don't change how the expression looks
+    if next.to_string() == steps.last().unwrap().to_string() {
+      *steps.last_mut().unwrap() = next;
+    } else {
+      steps.push(next);
+    }
+  }
+
+  steps
+}
+
+/// Why [substitute_variable] couldn't find the expression of a variable.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Unsubstituted {
+  /// The variable is never assigned.
+  Unassigned,
+  /// The variable's value is read in, so it isn't known ahead of time.
+  Read,
+  /// The variable is last assigned in an `if`, a `for` loop, or a block, so its value depends on
+  /// which of them run.
+  ControlFlow,
+}
+
+/// Returns the expression last assigned to the variable, with every variable in it replaced by
+/// the expression that defined it, recursively.
+///
+/// Variables in `free` are never replaced, and neither are those whose values aren't known
+/// ahead of time.
+pub fn substitute_variable(
+  program: &Node,
+  name: &str,
+  free: &[&str],
+) -> Result<Node, Unsubstituted> {
+  let mut definitions = HashMap::new();
+  // The variables that are assigned, but whose values aren't known
+  let mut unknown = HashMap::new();
+
+  if let Node::Program(assignments) = program {
+    for assignment in assignments {
+      match assignment {
+        Node::Assignment(..) | Node::ConstAssignment(..) => {
+          let (idents, expr) = assignment.assignment_chain().unwrap();
+          let expr = substitute(expr, &definitions, free);
+
+          for ident in idents {
+            unknown.remove(ident.literal.as_str());
+            definitions.insert(ident.literal.as_str(), expr.clone());
+          }
+        }
+        // Every value is substituted before any of the variables are redefined
+        Node::MultiAssignment(idents, values) => {
+          let values = values
+            .iter()
+            .map(|value| substitute(value, &definitions, free))
+            .collect::<Vec<_>>();
+
+          for (ident, value) in idents.iter().zip(values) {
+            unknown.remove(ident.literal.as_str());
+            definitions.insert(ident.literal.as_str(), value);
+          }
+        }
+        // A value that's read in isn't known ahead of time, so the variable is left as is
+        Node::Read(_, ident) => {
+          definitions.remove(ident.literal.as_str());
+          unknown.insert(ident.literal.as_str(), Unsubstituted::Read);
+        }
+        // Neither is which block of an `if` runs, or how many times a loop does. A block only
+        // keeps the variables that were set before it, so the rest are still unassigned
+        Node::If(..) | Node::For(..) | Node::BlockStatement(..) => {
+          for name in assignment.assigned_variables() {
+            if definitions.remove(name).is_some() || unknown.contains_key(name) {
+              unknown.insert(name, Unsubstituted::ControlFlow);
+            }
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  definitions
+    .remove(name)
+    .ok_or_else(|| unknown.remove(name).unwrap_or(Unsubstituted::Unassigned))
+}
+
+// Replaces the variables in the node with their definitions.
+fn substitute(node: &Node, definitions: &HashMap<&str, Node>, free: &[&str]) -> Node {
+  match node {
+    Node::Identifier(ident) if free.contains(&ident.literal.as_str()) => node.clone(),
+    Node::Identifier(ident) => match definitions.get(ident.literal.as_str()) {
+      // Values don't need parentheses
+      Some(Node::Expression(inner)) if matches!(**inner, Node::Literal(_)) => (**inner).clone(),
+      Some(expr) => Node::Fact(Box::new(expr.clone())),
+      // Uninitialized variables are left as is
+      None => node.clone(),
+    },
+    Node::Expression(inner) => Node::Expression(Box::new(substitute(inner, definitions, free))),
+    Node::Fact(inner) => Node::Fact(Box::new(substitute(inner, definitions, free))),
+    Node::UnaryOperator(op, rhs) => {
+      Node::UnaryOperator(*op, Box::new(substitute(rhs, definitions, free)))
+    }
+    Node::Term(lhs, op, rhs) => Node::Term(
+      Box::new(substitute(lhs, definitions, free)),
+      *op,
+      Box::new(substitute(rhs, definitions, free)),
+    ),
+    Node::Let(ident, value, body) => {
+      // The binding shadows any variable of the same name within the body
+      let mut bound = free.to_vec();
+      bound.push(ident.literal.as_str());
+
+      Node::Let(
+        ident.clone(),
+        Box::new(substitute(value, definitions, free)),
+        Box::new(substitute(body, definitions, &bound)),
+      )
+    }
+    Node::Lambda(pipe, params, body) => {
+      // Like a binding, the parameters shadow any variables of the same name
+      let mut bound = free.to_vec();
+      bound.extend(params.iter().map(|param| param.literal.as_str()));
+
+      Node::Lambda(
+        pipe.clone(),
+        params.clone(),
+        Box::new(substitute(body, definitions, &bound)),
+      )
+    }
+    Node::Call(ident, args, range) => Node::Call(
+      ident.clone(),
+      args
+        .iter()
+        .map(|arg| substitute(arg, definitions, free))
+        .collect(),
+      range.clone(),
+    ),
+    Node::Match(keyword, value, arms) => Node::Match(
+      keyword.clone(),
+      Box::new(substitute(value, definitions, free)),
+      arms
+        .iter()
+        .map(|(pattern, arm)| (pattern.clone(), substitute(arm, definitions, free)))
+        .collect(),
+    ),
+    Node::Interpolation(segments) => Node::Interpolation(
+      segments
+        .iter()
+        .map(|segment| segment.map(|expr| substitute(expr, definitions, free)))
+        .collect(),
+    ),
+    Node::Array(elements) => Node::Array(
+      elements
+        .iter()
+        .map(|element| substitute(element, definitions, free))
+        .collect(),
+    ),
+    Node::Index(bracket, array, index) => Node::Index(
+      bracket.clone(),
+      Box::new(substitute(array, definitions, free)),
+      Box::new(substitute(index, definitions, free)),
+    ),
+    Node::Tuple(elements) => Node::Tuple(
+      elements
+        .iter()
+        .map(|element| substitute(element, definitions, free))
+        .collect(),
+    ),
+    Node::Field(dot, tuple, index) => Node::Field(
+      dot.clone(),
+      Box::new(substitute(tuple, definitions, free)),
+      *index,
+    ),
+    Node::Struct(name, fields) => Node::Struct(
+      name.clone(),
+      fields
+        .iter()
+        .map(|(field, value)| (field.clone(), substitute(value, definitions, free)))
+        .collect(),
+    ),
+    Node::NamedField(dot, value, field) => Node::NamedField(
+      dot.clone(),
+      Box::new(substitute(value, definitions, free)),
+      field.clone(),
+    ),
+    Node::StructDeclaration(..)
+    | Node::Import(..)
+    | Node::Program(_)
+    | Node::Assignment(..)
+    | Node::ConstAssignment(..)
+    | Node::MultiAssignment(..)
+    | Node::Print(..)
+    | Node::Read(..)
+    | Node::If(..)
+    | Node::For(..)
+    | Node::Block(_)
+    | Node::Conditional(..)
+    | Node::BlockStatement(..)
+    | Node::Return(..)
+    | Node::Break(_)
+    | Node::Continue(_)
+    | Node::Literal(_) => node.clone(),
+  }
+}
+
+// Returns whether the node is a variable without a value, within any parentheses.
+fn is_uninitialized(node: &Node, variables: &HashMap<&str, Value>) -> bool {
+  match node {
+    Node::Expression(inner) | Node::Fact(inner) => is_uninitialized(inner, variables),
+    Node::Identifier(ident) => !variables.contains_key(ident.literal.as_str()),
+    _ => false,
+  }
+}
+
+// Reduces a single operation of the node, returning `None` if the node is already a value or
+// a variable couldn't be resolved.
+fn reduce<'n>(
+  node: &'n Node,
+  variables: &HashMap<&'n str, Value>,
+  arithmetic: Arithmetic,
+) -> Option<Node> {
+  match node {
+    // Values are only reduced further to bring them into the range of a modulus
+    Node::Literal(lit) => {
+      let value = arithmetic.normalize(lit.value.clone());
+
+      (value != lit.value).then_some(Node::Literal(LiteralNode { value }))
+    }
+    Node::Identifier(ident) => variables.get(ident.literal.as_str()).map(|value| {
+      Node::Literal(LiteralNode {
+        value: value.clone(),
+      })
+    }),
+    // A wrapped value, like the `5` in `x = 5;`, only needs to be unwrapped
+    Node::Expression(inner) | Node::Fact(inner) if matches!(**inner, Node::Literal(_)) => {
+      Some((**inner).clone())
+    }
+    Node::Expression(inner) => Some(collapse(
+      reduce(inner, variables, arithmetic)?,
+      Node::Expression,
+    )),
+    Node::Fact(inner) => Some(collapse(reduce(inner, variables, arithmetic)?, Node::Fact)),
+    Node::UnaryOperator(op, rhs) => match &**rhs {
+      Node::Literal(lit) => Some(Node::Literal(LiteralNode {
+        value: arithmetic.unary(*op, &lit.value).0,
+      })),
+      _ => Some(Node::UnaryOperator(
+        *op,
+        Box::new(reduce(rhs, variables, arithmetic)?),
+      )),
+    },
+    // An uninitialized variable on the left of `??` is `none`, like when it runs
+    Node::Term(lhs, Operator::Coalesce, rhs) if is_uninitialized(lhs, variables) => {
+      Some(Node::Term(
+        Box::new(Node::Literal(LiteralNode { value: Value::None })),
+        Operator::Coalesce,
+        rhs.clone(),
+      ))
+    }
+    Node::Term(lhs, op, rhs) => match (&**lhs, &**rhs) {
+      // The right operand of `&&` and `||` is skipped if the left one decides the result
+      (Node::Literal(l), _) if op.short_circuits(&arithmetic.normalize(l.value.clone())) => {
+        let lhs = arithmetic.normalize(l.value.clone());
+        let value = match op {
+          Operator::Coalesce => lhs,
+          _ => Value::Int(!lhs.is_zero() as isize),
+        };
+
+        Some(Node::Literal(LiteralNode { value }))
+      }
+      // Some operations, like dividing by zero, don't have a value
+      (Node::Literal(l), Node::Literal(r)) if arithmetic.is_undefined(*op, &l.value, &r.value) => {
+        None
+      }
+      (Node::Literal(l), Node::Literal(r)) => Some(Node::Literal(LiteralNode {
+        value: arithmetic.binary(*op, &l.value, &r.value).0,
+      })),
+      (Node::Literal(_), _) => Some(Node::Term(
+        lhs.clone(),
+        *op,
+        Box::new(reduce(rhs, variables, arithmetic)?),
+      )),
+      _ => Some(Node::Term(
+        Box::new(reduce(lhs, variables, arithmetic)?),
+        *op,
+        rhs.clone(),
+      )),
+    },
+    Node::Let(ident, value, body) => match (&**value, &**body) {
+      (Node::Literal(_), Node::Literal(_)) => Some((**body).clone()),
+      (Node::Literal(lit), _) => {
+        let mut scope = variables.clone();
+        scope.insert(ident.literal.as_str(), lit.value.clone());
+
+        Some(Node::Let(
+          ident.clone(),
+          value.clone(),
+          Box::new(reduce(body, &scope, arithmetic)?),
+        ))
+      }
+      _ => Some(Node::Let(
+        ident.clone(),
+        Box::new(reduce(value, variables, arithmetic)?),
+        body.clone(),
+      )),
+    },
+    // Once the value is known, the `match` is replaced by the arm it picks
+    Node::Match(keyword, value, arms) => match &**value {
+      Node::Literal(lit) => arms
+        .iter()
+        .find(|(pattern, _)| arithmetic.matches(pattern, &lit.value))
+        .map(|(_, arm)| arm.clone()),
+      _ => Some(Node::Match(
+        keyword.clone(),
+        Box::new(reduce(value, variables, arithmetic)?),
+        arms.clone(),
+      )),
+    },
+    // The expressions are reduced from left to right, and then spliced into the string at once
+    Node::Interpolation(segments) => {
+      let unreduced = segments
+        .iter()
+        .position(|segment| !matches!(segment, Segment::Expression(Node::Literal(_))));
+
+      match unreduced.map(|i| (i, &segments[i])) {
+        Some((i, Segment::Expression(expr))) => {
+          let mut segments = segments.clone();
+          segments[i] = Segment::Expression(reduce(expr, variables, arithmetic)?);
+
+          Some(Node::Interpolation(segments))
+        }
+        _ => {
+          let text = segments
+            .iter()
+            .map(|segment| match segment {
+              Segment::Text(text) => text.clone(),
+              Segment::Expression(Node::Literal(lit)) => lit.value.text(),
+              Segment::Expression(_) => unreachable!("every expression is reduced"),
+            })
+            .collect::<String>();
+
+          Some(Node::Literal(LiteralNode {
+            value: Value::Str(text.into()),
+          }))
+        }
+      }
+    }
+    // Like interpolations, the elements are reduced from left to right, and then collected at once
+    Node::Array(elements) | Node::Tuple(elements) => {
+      let is_array = matches!(node, Node::Array(_));
+
+      match elements
+        .iter()
+        .position(|element| !matches!(element, Node::Literal(_)))
+      {
+        Some(i) => {
+          let mut elements = elements.clone();
+          elements[i] = reduce(&elements[i], variables, arithmetic)?;
+
+          Some(match is_array {
+            true => Node::Array(elements),
+            false => Node::Tuple(elements),
+          })
+        }
+        None => {
+          let values = elements
+            .iter()
+            .map(|element| match element {
+              Node::Literal(lit) => lit.value.clone(),
+              _ => unreachable!("every element is reduced"),
+            })
+            .collect();
+
+          Some(Node::Literal(LiteralNode {
+            value: match is_array {
+              true => Value::Array(values),
+              false => Value::Tuple(values),
+            },
+          }))
+        }
+      }
+    }
+    Node::Field(dot, tuple, index) => match &**tuple {
+      // A field out of range doesn't have a value
+      Node::Literal(lit) => Some(Node::Literal(LiteralNode {
+        value: lit.value.field(*index)?.clone(),
+      })),
+      _ => Some(Node::Field(
+        dot.clone(),
+        Box::new(reduce(tuple, variables, arithmetic)?),
+        *index,
+      )),
+    },
+    // The values of the fields are reduced from left to right, but the struct itself is only built
+    // when it runs, since that needs the order of the fields in its declaration
+    Node::Struct(name, fields) => {
+      let i = fields
+        .iter()
+        .position(|(_, value)| !matches!(value, Node::Literal(_)))?;
+      let mut fields = fields.clone();
+      fields[i].1 = reduce(&fields[i].1, variables, arithmetic)?;
+
+      Some(Node::Struct(name.clone(), fields))
+    }
+    Node::NamedField(dot, value, field) => match &**value {
+      // A missing field doesn't have a value
+      Node::Literal(lit) => Some(Node::Literal(LiteralNode {
+        value: lit.value.named_field(&field.literal)?.clone(),
+      })),
+      Node::Struct(_, fields)
+        if fields
+          .iter()
+          .all(|(_, value)| matches!(value, Node::Literal(_))) =>
+      {
+        fields
+          .iter()
+          .find(|(name, _)| name.literal == field.literal)
+          .map(|(_, value)| value.clone())
+      }
+      _ => Some(Node::NamedField(
+        dot.clone(),
+        Box::new(reduce(value, variables, arithmetic)?),
+        field.clone(),
+      )),
+    },
+    Node::Index(bracket, array, index) => match (&**array, &**index) {
+      // An index out of bounds doesn't have a value
+      (Node::Literal(array), Node::Literal(index)) => Some(Node::Literal(LiteralNode {
+        value: array
+          .value
+          .element(&arithmetic.normalize(index.value.clone()))?
+          .clone(),
+      })),
+      (Node::Literal(_), _) => Some(Node::Index(
+        bracket.clone(),
+        array.clone(),
+        Box::new(reduce(index, variables, arithmetic)?),
+      )),
+      _ => Some(Node::Index(
+        bracket.clone(),
+        Box::new(reduce(array, variables, arithmetic)?),
+        index.clone(),
+      )),
+    },
+    // The arguments are reduced from left to right, and then the function is called on them
+    Node::Call(ident, args, range) => {
+      match args.iter().position(|arg| !matches!(arg, Node::Literal(_))) {
+        Some(i) => {
+          let mut args = args.clone();
+          args[i] = reduce(&args[i], variables, arithmetic)?;
+
+          Some(Node::Call(ident.clone(), args, range.clone()))
+        }
+        None => {
+          let builtin = Builtin::from_name(&ident.literal)?;
+          let args = args
+            .iter()
+            .map(|arg| match arg {
+              Node::Literal(lit) => lit.value.clone(),
+              _ => unreachable!("every argument is reduced"),
+            })
+            .collect::<Vec<_>>();
+
+          // Like an operation, a call without a value isn't reduced
+          if !builtin.applies_to(&args) || builtin.is_undefined_for(&args) {
+            return None;
+          }
+
+          Some(Node::Literal(LiteralNode {
+            value: arithmetic.call(builtin, &args).0,
+          }))
+        }
+      }
+    }
+    // A function is only run when it's called, which only the interpreter does
+    Node::Lambda(..) => None,
+    // Only expressions are reduced
+    Node::StructDeclaration(..)
+    | Node::Import(..)
+    | Node::Program(_)
+    | Node::Return(..)
+    | Node::Assignment(..)
+    | Node::ConstAssignment(..)
+    | Node::MultiAssignment(..)
+    | Node::Print(..)
+    | Node::Read(..)
+    | Node::If(..)
+    | Node::For(..)
+    | Node::Block(_)
+    | Node::Conditional(..)
+    | Node::BlockStatement(..)
+    | Node::Break(_)
+    | Node::Continue(_) => None,
+  }
+}
+
+// Drops the wrapping node once its contents are a value, so `(5)` is shown as `5`.
+fn collapse(reduced: Node, wrap: fn(Box<Node>) -> Node) -> Node {
+  match reduced {
+    Node::Literal(_) => reduced,
+    _ => wrap(Box::new(reduced)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::Parser;
+
+  fn steps_of(src: &str) -> Vec<String> {
+    let program = Parser::new(src).parse().unwrap();
+    let variables = HashMap::from([("y", Value::Int(2))]);
+
+    match program {
+      Node::Program(assignments) => match &assignments[0] {
+        Node::Assignment(_, expr) => reduction_steps(expr, &variables, Arithmetic::default())
+          .iter()
+          .map(Node::to_string)
+          .collect(),
+        _ => unreachable!(),
+      },
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn parenthesized() {
+    assert_eq!(steps_of("x = (2+3)*4;"), vec!["(2 + 3) * 4", "5 * 4", "20"]);
+  }
+
+  #[test]
+  fn variables() {
+    assert_eq!(
+      steps_of("x = y * -y;"),
+      vec!["y * -y", "2 * -y", "2 * -2", "-4"]
+    );
+  }
+
+  #[test]
+  fn nested_negations() {
+    assert_eq!(steps_of("x = -(-3);"), vec!["-(-3)", "3"]);
+    assert_eq!(steps_of("x = - -3;"), vec!["-(-3)", "3"]);
+    assert_eq!(
+      steps_of("x = -(-(2 + 1));"),
+      vec!["-(-(2 + 1))", "-(-3)", "3"]
+    );
+  }
+
+  #[test]
+  fn substitution() {
+    let program = Parser::new("a = 2; b = a + 1; a = b * 3; c = a - b;")
+      .parse()
+      .unwrap();
+
+    assert_eq!(
+      substitute_variable(&program, "c", &[]).unwrap().to_string(),
+      "((2 + 1) * 3) - (2 + 1)"
+    );
+  }
+
+  #[test]
+  fn substitution_through_control_flow() {
+    let substituted =
+      |src, name| substitute_variable(&Parser::new(src).parse().unwrap(), name, &[]);
+
+    assert_eq!(
+      substituted("x = 0; if (1) { x = x + 1; }", "x").unwrap_err(),
+      Unsubstituted::ControlFlow
+    );
+    assert_eq!(
+      substituted(
+        "x = 0; for (i = 0; i < 3; i++) { x = x + i; } x = x * 2;",
+        "x"
+      )
+      .unwrap()
+      .to_string(),
+      "x * 2"
+    );
+    assert_eq!(
+      substituted("read x; { x = 1; }", "x").unwrap_err(),
+      Unsubstituted::ControlFlow
+    );
+    assert_eq!(
+      substituted("read x;", "x").unwrap_err(),
+      Unsubstituted::Read
+    );
+    // A variable that's first assigned in a block is gone once the block ends
+    assert_eq!(
+      substituted("{ x = 1; }", "x").unwrap_err(),
+      Unsubstituted::Unassigned
+    );
+  }
+
+  #[test]
+  fn uninitialized() {
+    assert_eq!(steps_of("x = 1 + z;"), vec!["1 + z"]);
+  }
+}
@@ -0,0 +1,66 @@
+use toy_language::error::{DiagnosticError, Severity};
+
+/// Counts of what happened during a run, eg to print with `--summary`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+  /// The number of files that were read.
+  pub files: usize,
+  /// The number of statements that were parsed.
+  pub statements: usize,
+  /// The number of errors that were reported.
+  pub errors: usize,
+  /// The number of warnings that were reported.
+  pub warnings: usize,
+  /// The number of operations that were evaluated.
+  pub operations: usize,
+  /// The number of variables that were set.
+  pub variables: usize,
+  /// The estimated bytes held by the parsed program.
+  pub ast_bytes: usize,
+  /// The estimated bytes held by the variables that were set.
+  pub variable_bytes: usize,
+}
+
+impl Summary {
+  /// Counts the diagnostics by their severity.
+  pub fn record_diagnostics(&mut self, diagnostics: &[DiagnosticError]) {
+    for diagnostic in diagnostics {
+      match diagnostic.severity() {
+        Severity::Error => self.errors += 1,
+        Severity::Warning => self.warnings += 1,
+      }
+    }
+  }
+}
+
+/// Formats the summary like the variables of a program, one count per line.
+impl std::fmt::Display for Summary {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    writeln!(f, "files => {}", self.files)?;
+    writeln!(f, "statements => {}", self.statements)?;
+    writeln!(f, "errors => {}", self.errors)?;
+    writeln!(f, "warnings => {}", self.warnings)?;
+    writeln!(f, "operations => {}", self.operations)?;
+    writeln!(f, "variables => {}", self.variables)?;
+    writeln!(f, "ast bytes => {}", self.ast_bytes)?;
+    write!(f, "variable bytes => {}", self.variable_bytes)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn counts_diagnostics_by_severity() {
+    let mut summary = Summary::default();
+
+    summary.record_diagnostics(&[
+      DiagnosticError::new("error".to_string(), 1, 1),
+      DiagnosticError::warning("warning".to_string(), 1, 1),
+      DiagnosticError::warning("warning".to_string(), 2, 1),
+    ]);
+
+    assert_eq!((summary.errors, summary.warnings), (1, 2));
+  }
+}
@@ -1,6 +1,8 @@
+use crate::value::Value;
 use std::ops::Range;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// A lexed token, which refers back to its source by range.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Token {
   /// The kind of token it is.
   kind: TokenKind,
@@ -8,13 +10,58 @@ pub struct Token {
   range: Range<usize>,
   /// The line of the token.
   line_number: usize,
+  /// The parsed value of a [TokenKind::Literal], [TokenKind::StringLiteral], or
+  /// [TokenKind::CharLiteral] token.
+  value: Option<Result<LiteralValue, LiteralError>>,
 }
 
+/// The value of a [TokenKind::Literal], [TokenKind::StringLiteral], or [TokenKind::CharLiteral]
+/// token.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiteralValue {
+  /// The number, ignoring any leading zeros, or the string, with its escapes replaced.
+  pub value: Value,
+  /// Whether the literal was written with leading zeros, eg `007` or `007.5`.
+  pub leading_zeros: bool,
+}
+
+/// The reasons a [TokenKind::Literal], [TokenKind::StringLiteral], or [TokenKind::CharLiteral] can
+/// fail to be converted into a value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LiteralError {
+  /// The literal doesn't fit in an [isize], or is too large to be a finite float.
+  Overflow,
+  /// A prefixed literal has no digits after its prefix, eg `0x`.
+  MissingDigits,
+  /// A prefixed literal has a digit that its base doesn't have, eg the `2` in `0b12`.
+  ///
+  /// The digit is at the given byte offset into the literal.
+  InvalidDigit(usize),
+  /// A string has a `\` that isn't followed by an escapable character, eg `"\q"`.
+  ///
+  /// The `\` is at the given byte offset into the literal.
+  InvalidEscape(usize),
+  /// A character literal doesn't have exactly one character, eg `''` or `'ab'`.
+  InvalidLength,
+}
+
+/// The kinds of [Token]s.
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum TokenKind {
-  /// Integer literals.
+  /// Integer and float literals, eg `12`, `0x1F`, and `12.5`.
   Literal,
+  /// String literals, eg `"hello"`.
+  StringLiteral,
+  /// Character literals, eg `'a'`, which are their code point.
+  CharLiteral,
+  /// The start of a string with an interpolation, up to and including its first `${`, eg
+  /// `"x is ${` in `"x is ${x}!"`.
+  StringStart,
+  /// The piece of a string between two interpolations, eg `} and ${` in `"${x} and ${y}"`.
+  StringMiddle,
+  /// The end of a string after its last interpolation, eg `}!"` in `"x is ${x}!"`.
+  StringEnd,
   /// Identifiers.
   ///
   /// Identifiers start with a letter, but can be followed with digits
@@ -25,14 +72,100 @@ pub enum TokenKind {
   LeftParen,
   /// The literal character `)`.
   RightParen,
+  /// The literal character `{`.
+  LeftBrace,
+  /// The literal character `}`.
+  RightBrace,
+  /// The literal character `[`.
+  LeftBracket,
+  /// The literal character `]`.
+  RightBracket,
+  /// The literal character `.`, when it isn't part of a float.
+  Dot,
+  /// The literal character `:`, which separates a variable from its type, eg `x: int`.
+  Colon,
   /// The literal character `*`.
   Star,
+  /// The literal characters `**`.
+  StarStar,
+  /// The literal character `/`.
+  Slash,
+  /// The literal character `%`.
+  Percent,
   /// The literal character `-`
   Minus,
+  /// The literal characters `--`, which are two `-` within an expression.
+  MinusMinus,
   /// The literal character `+`
   Plus,
+  /// The literal characters `++`, which are two `+` within an expression.
+  PlusPlus,
   /// The literal character `;`
   Semicolon,
+  /// The literal character `,`.
+  Comma,
+  /// The literal characters `=>`, which separate the pattern and value of a `match` arm.
+  FatArrow,
+  /// The literal characters `==`.
+  EqualEqual,
+  /// The literal characters `!=`.
+  BangEqual,
+  /// The literal character `<`.
+  Less,
+  /// The literal characters `<=`.
+  LessEqual,
+  /// The literal characters `<<`.
+  LessLess,
+  /// The literal character `>`.
+  Greater,
+  /// The literal characters `>=`.
+  GreaterEqual,
+  /// The literal characters `>>`.
+  GreaterGreater,
+  /// The literal character `!`.
+  Bang,
+  /// The literal characters `&&`.
+  AmpersandAmpersand,
+  /// The literal characters `||`.
+  PipePipe,
+  /// The literal character `&`, which is reserved for a later version of the language.
+  Ampersand,
+  /// The literal character `|`, which is reserved for a later version of the language.
+  Pipe,
+  /// The literal characters `??`.
+  QuestionQuestion,
+  /// A pragma, eg `#[modulus: 97]`.
+  Pragma,
+  /// The keyword `let`.
+  Let,
+  /// The keyword `in`.
+  In,
+  /// The keyword `print`.
+  Print,
+  /// The keyword `read`.
+  Read,
+  /// The keyword `if`.
+  If,
+  /// The keyword `else`.
+  Else,
+  /// The keyword `for`.
+  For,
+  /// The keyword `match`.
+  Match,
+  /// The keyword `break`.
+  Break,
+  /// The keyword `continue`.
+  Continue,
+  /// The keyword `const`.
+  Const,
+  /// The keyword `none`, the value of nothing.
+  NoneLiteral,
+  /// The keyword `struct`.
+  Struct,
+  /// The keyword `import`.
+  Import,
+  /// A block comment, eg `/* ... */`, which can span multiple lines.
+  Comment,
   /// A whitespace token.
   ///
   /// This is any one of these characters, `\n` & `\r`, `\t`, ` `, `\xOC`.
@@ -50,9 +183,17 @@ impl Token {
       kind,
       range,
       line_number: line,
+      value: None,
     }
   }
 
+  /// Attaches the parsed value of a [TokenKind::Literal], [TokenKind::StringLiteral], or
+  /// [TokenKind::CharLiteral] to this token.
+  pub fn with_value(mut self, value: Result<LiteralValue, LiteralError>) -> Self {
+    self.value = Some(value);
+    self
+  }
+
   /// Returns the [TokenKind] of this token.
   pub fn kind(&self) -> TokenKind {
     self.kind
@@ -67,6 +208,12 @@ impl Token {
   pub fn line(&self) -> usize {
     self.line_number
   }
+
+  /// The parsed value of this token, if it's a [TokenKind::Literal], [TokenKind::StringLiteral], or
+  /// [TokenKind::CharLiteral].
+  pub fn value(&self) -> Option<Result<LiteralValue, LiteralError>> {
+    self.value.clone()
+  }
 }
 
 impl std::fmt::Display for TokenKind {
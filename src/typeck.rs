@@ -0,0 +1,514 @@
+use crate::{
+  error::DiagnosticError,
+  node::{IdentifierNode, LiteralNode, Node, Operator, Segment},
+  token::Token,
+  util::LineIndex,
+  value::{operator_rule, Function, Value},
+};
+use std::{collections::HashMap, fmt, rc::Rc};
+
+/// The type of a value, which a variable can be declared with, eg the `int` of `x: int = 5;`.
+///
+/// Variables can't be declared with [Type::Struct], [Type::Function], or [Type::None].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Type {
+  /// `int`, the type of integers.
+  Int,
+  /// `float`, the type of floats.
+  Float,
+  /// `str`, the type of strings.
+  Str,
+  /// `array`, the type of arrays, whatever their elements are.
+  Array,
+  /// `tuple`, the type of tuples, whatever their fields are.
+  Tuple,
+  /// The type of values of structs, whatever their struct is.
+  Struct,
+  /// The type of functions, whatever their parameters are.
+  Function,
+  /// The type of `none`.
+  None,
+}
+
+/// A variable declared with a type, eg `x: int = 5;`.
+#[derive(Clone, Debug)]
+pub struct Declaration {
+  /// The variable, where it's declared.
+  pub ident: IdentifierNode,
+  /// The type that it's declared with.
+  pub ty: Type,
+}
+
+impl Type {
+  /// Parses the name of a type, eg `int`.
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "int" => Some(Type::Int),
+      "float" => Some(Type::Float),
+      "str" => Some(Type::Str),
+      "array" => Some(Type::Array),
+      "tuple" => Some(Type::Tuple),
+      _ => None,
+    }
+  }
+
+  /// Returns the type of the value.
+  pub fn of(value: &Value) -> Self {
+    match value {
+      Value::Int(_) => Type::Int,
+      Value::Float(_) => Type::Float,
+      Value::Str(_) => Type::Str,
+      Value::Array(_) => Type::Array,
+      Value::Tuple(_) => Type::Tuple,
+      Value::Struct(..) => Type::Struct,
+      Value::Function(_) => Type::Function,
+      Value::None => Type::None,
+    }
+  }
+
+  /// Returns whether a value of the other type can be assigned to a variable of this type.
+  ///
+  /// Besides values of the same type, a float variable accepts integers, since arithmetic mixes
+  /// them freely.
+  pub fn accepts(self, other: Type) -> bool {
+    self == other || (self == Type::Float && other == Type::Int)
+  }
+
+  // A value of this type, to decide which operators apply to it like the interpreter does
+  fn sample(self) -> Value {
+    match self {
+      Type::Int => Value::Int(1),
+      Type::Float => Value::Float(1.0),
+      Type::Str => Value::Str("".into()),
+      Type::Array => Value::Array(Vec::new().into()),
+      Type::Tuple => Value::Tuple(Vec::new().into()),
+      Type::Struct => Value::Struct("".into(), Vec::new().into()),
+      Type::Function => Value::Function(Rc::new(Function {
+        params: Vec::new(),
+        body: Node::Literal(LiteralNode { value: Value::None }),
+        captured: Vec::new(),
+      })),
+      Type::None => Value::None,
+    }
+  }
+}
+
+impl fmt::Display for Type {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      Type::Int => "int",
+      Type::Float => "float",
+      Type::Str => "str",
+      Type::Array => "array",
+      Type::Tuple => "tuple",
+      Type::Struct => "struct",
+      Type::Function => "function",
+      Type::None => "none",
+    };
+
+    write!(f, "{}", name)
+  }
+}
+
+/// Checks the parsed program against the types that its variables are declared with, before it's
+/// run.
+///
+/// The type of an expression is only known from its literals, the declared variables that it
+/// reads, and the operators applied to them, so anything else, like an element of an array, is
+/// never reported.
+///
+/// # Returns
+/// Returns an error for every variable declared with two different types, every assignment of a
+/// value whose type its variable doesn't accept, and every operator applied to values of types
+/// that it doesn't apply to.
+pub fn check(
+  src: &str,
+  program: &Node,
+  declarations: &[Declaration],
+) -> Result<(), Vec<DiagnosticError>> {
+  let mut checker = Checker {
+    line_index: LineIndex::new(src),
+    types: HashMap::new(),
+    bound: Vec::new(),
+    subject: (String::new(), 0),
+    errors: Vec::new(),
+  };
+
+  for declaration in declarations {
+    let ident = &declaration.ident;
+
+    match checker.types.get(ident.literal.as_str()) {
+      Some(&(ty, line)) if ty != declaration.ty => {
+        let (line_number, column) = checker.line_index.line_col(ident.range.start);
+
+        checker.errors.push(DiagnosticError::new(
+          format!(
+            "`{}` is declared as `{}`, but it's already declared as `{}` on line {}.",
+            ident.literal, declaration.ty, ty, line
+          ),
+          line_number,
+          column,
+        ));
+      }
+      Some(_) => {}
+      None => {
+        checker
+          .types
+          .insert(&ident.literal, (declaration.ty, ident.line));
+      }
+    }
+  }
+
+  if let Node::Program(statements) = program {
+    for statement in statements {
+      checker.statement(statement);
+    }
+  }
+
+  checker.errors.sort_by_key(|e| (e.line(), e.column()));
+
+  if checker.errors.is_empty() {
+    Ok(())
+  } else {
+    Err(checker.errors)
+  }
+}
+
+struct Checker<'a> {
+  line_index: LineIndex,
+  // The declared type of each variable, and the line that it's declared on
+  types: HashMap<&'a str, (Type, usize)>,
+  // The variables bound by the `let` expressions around the current expression, innermost last
+  bound: Vec<(&'a str, Option<Type>)>,
+  // What the current statement is called in diagnostics, and where it starts
+  subject: (String, usize),
+  errors: Vec<DiagnosticError>,
+}
+
+impl<'a> Checker<'a> {
+  fn statement(&mut self, statement: &'a Node) {
+    match statement {
+      Node::Assignment(..) | Node::ConstAssignment(..) => {
+        let (idents, expr) = statement.assignment_chain().unwrap();
+        let verb = match statement {
+          Node::ConstAssignment(..) => "The declaration of",
+          _ => "The assignment to",
+        };
+
+        // Like when it runs, the operators belong to the innermost assignment of a chain
+        let innermost = idents.last().unwrap();
+        self.subject = (
+          format!("{} `{}`", verb, innermost.literal),
+          innermost.range.start,
+        );
+
+        let ty = self.expression(expr);
+
+        for ident in idents.iter().rev() {
+          self.subject = (format!("{} `{}`", verb, ident.literal), ident.range.start);
+          self.assign(ident, ty);
+        }
+      }
+      Node::MultiAssignment(idents, values) => {
+        for (ident, value) in idents.iter().zip(values) {
+          self.subject = (
+            format!("The assignment to `{}`", ident.literal),
+            ident.range.start,
+          );
+
+          let ty = self.expression(value);
+          self.assign(ident, ty);
+        }
+      }
+      Node::Read(keyword, ident) => {
+        self.subject = (
+          format!("The read into `{}`", ident.literal),
+          keyword.range().start,
+        );
+        self.assign(ident, Some(Type::Int));
+      }
+      Node::Print(keyword, expr) => {
+        self.subject = ("The print statement".to_string(), keyword.range().start);
+        self.expression(expr);
+      }
+      Node::If(keyword, condition, then, otherwise) => {
+        self.condition(keyword, "if", condition);
+        self.statement(then);
+
+        if let Some(otherwise) = otherwise {
+          self.statement(otherwise);
+        }
+      }
+      Node::For(keyword, init, condition, step, body) => {
+        if let Some(init) = init {
+          self.statement(init);
+        }
+
+        self.condition(keyword, "for", condition);
+
+        if let Some(step) = step {
+          self.statement(step);
+        }
+
+        self.statement(body);
+      }
+      Node::Block(statements) => {
+        for statement in statements {
+          self.statement(statement);
+        }
+      }
+      Node::BlockStatement(_, statement) | Node::Conditional(_, statement) => {
+        self.statement(statement)
+      }
+      _ => {}
+    }
+  }
+
+  // Checks the condition of an `if` or a `for`, named by its keyword.
+  fn condition(&mut self, keyword: &Token, name: &str, condition: &'a Node) {
+    self.subject = (
+      format!("The condition of the `{}`", name),
+      keyword.range().start,
+    );
+    self.expression(condition);
+  }
+
+  // Reports the assignment if the variable is declared with a type that doesn't accept the
+  // value's.
+  fn assign(&mut self, ident: &IdentifierNode, ty: Option<Type>) {
+    let (Some(ty), Some(&(declared, line))) = (ty, self.types.get(ident.literal.as_str())) else {
+      return;
+    };
+
+    if !declared.accepts(ty) {
+      self.report(format!(
+        "{} assigns {}, but `{}` is declared as `{}` on line {}.",
+        self.subject.0,
+        ty.sample().kind(),
+        ident.literal,
+        declared,
+        line
+      ));
+    }
+  }
+
+  // Returns the type of the expression, if it's known, reporting any operator within it that
+  // doesn't apply to its operands.
+  fn expression(&mut self, expr: &'a Node) -> Option<Type> {
+    match expr {
+      Node::Literal(literal) => Some(Type::of(&literal.value)),
+      Node::Identifier(ident) => match self
+        .bound
+        .iter()
+        .rev()
+        .find(|(name, _)| *name == ident.literal)
+      {
+        Some(&(_, ty)) => ty,
+        None => self.types.get(ident.literal.as_str()).map(|&(ty, _)| ty),
+      },
+      Node::Expression(expr) | Node::Fact(expr) => self.expression(expr),
+      Node::Term(lhs, op, rhs) => {
+        let lhs = self.expression(lhs);
+        let rhs = self.expression(rhs);
+
+        self.operation(*op, lhs, rhs)
+      }
+      Node::UnaryOperator(op, operand) => match self.expression(operand) {
+        _ if matches!(op, Operator::Not) => Some(Type::Int),
+        Some(ty @ (Type::Int | Type::Float)) => Some(ty),
+        Some(ty) => {
+          self.report(format!(
+            "{} applies `{}` to {}. {}.",
+            self.subject.0,
+            op,
+            ty.sample().kind(),
+            operator_rule(&ty.sample(), None)
+          ));
+
+          None
+        }
+        None => None,
+      },
+      Node::Let(ident, value, body) => {
+        let ty = self.expression(value);
+
+        self.bound.push((&ident.literal, ty));
+        let ty = self.expression(body);
+        self.bound.pop();
+
+        ty
+      }
+      // The types of the arguments aren't known until the function is called
+      Node::Lambda(_, params, body) => {
+        let bound = self.bound.len();
+
+        self
+          .bound
+          .extend(params.iter().map(|param| (param.literal.as_str(), None)));
+        self.expression(body);
+        self.bound.truncate(bound);
+
+        Some(Type::Function)
+      }
+      Node::Match(_, scrutinee, arms) => {
+        self.expression(scrutinee);
+
+        let types = arms
+          .iter()
+          .map(|(_, arm)| self.expression(arm))
+          .collect::<Vec<_>>();
+
+        match types.first() {
+          Some(&first) if types.iter().all(|&ty| ty == first) => first,
+          _ => None,
+        }
+      }
+      Node::Interpolation(segments) => {
+        for segment in segments {
+          if let Segment::Expression(expr) = segment {
+            self.expression(expr);
+          }
+        }
+
+        Some(Type::Str)
+      }
+      Node::Array(elements) => {
+        for element in elements {
+          self.expression(element);
+        }
+
+        Some(Type::Array)
+      }
+      Node::Tuple(elements) => {
+        for element in elements {
+          self.expression(element);
+        }
+
+        Some(Type::Tuple)
+      }
+      Node::Index(_, array, index) => {
+        self.expression(array);
+        self.expression(index);
+
+        None
+      }
+      Node::Struct(_, fields) => {
+        for (_, value) in fields {
+          self.expression(value);
+        }
+
+        Some(Type::Struct)
+      }
+      Node::Field(_, tuple, _) | Node::NamedField(_, tuple, _) => {
+        self.expression(tuple);
+
+        None
+      }
+      Node::Call(_, arguments, _) => {
+        for argument in arguments {
+          self.expression(argument);
+        }
+
+        None
+      }
+      // A chained assignment within the expression has already been checked as a statement
+      _ => None,
+    }
+  }
+
+  // Returns the type that the operation results in, if it's known, reporting it if the operator
+  // doesn't apply to the operands.
+  fn operation(&mut self, op: Operator, lhs: Option<Type>, rhs: Option<Type>) -> Option<Type> {
+    // `??` has the type of its right operand only if the left one is `none`
+    if matches!(op, Operator::Coalesce) {
+      return match lhs? {
+        Type::None => rhs,
+        lhs => Some(lhs),
+      };
+    }
+
+    if op.is_logical() || op.is_comparison() {
+      if let (Some(lhs), Some(rhs)) = (lhs, rhs) {
+        self.check_operands(op, lhs, rhs);
+      }
+
+      return Some(Type::Int);
+    }
+
+    let (lhs, rhs) = (lhs?, rhs?);
+
+    if !self.check_operands(op, lhs, rhs) {
+      return None;
+    }
+
+    match (lhs, rhs) {
+      (Type::Int, Type::Int) => Some(Type::Int),
+      (Type::Int | Type::Float, Type::Int | Type::Float) => Some(Type::Float),
+      // Only `+` applies to two strings or two arrays, which concatenates them
+      (ty, _) => Some(ty),
+    }
+  }
+
+  // Reports the operation if the operator doesn't apply to the operands, returning whether it
+  // does.
+  fn check_operands(&mut self, op: Operator, lhs: Type, rhs: Type) -> bool {
+    let (lhs, rhs) = (lhs.sample(), rhs.sample());
+
+    if op.applies_to(&lhs, &rhs) {
+      return true;
+    }
+
+    self.report(format!(
+      "{} applies `{}` to {} and {}. {}.",
+      self.subject.0,
+      op,
+      lhs.kind(),
+      rhs.kind(),
+      operator_rule(&lhs, Some(&rhs))
+    ));
+
+    false
+  }
+
+  // Reports an error at the start of the current statement.
+  fn report(&mut self, msg: String) {
+    let (line, column) = self.line_index.line_col(self.subject.1);
+
+    self.errors.push(DiagnosticError::new(msg, line, column));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::Parser;
+
+  fn errors(src: &str) -> Vec<String> {
+    let mut parser = Parser::new(src);
+    let program = parser.parse().unwrap();
+
+    match check(src, &program, parser.declarations()) {
+      Ok(()) => Vec::new(),
+      Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+    }
+  }
+
+  #[test]
+  fn accepts_matching_types() {
+    let src = "x: int = 5;\ny: float = x * 2;\ns: str = \"a\" + \"b\";\nx = let n = 1 in n + x;\n\
+               t: tuple = (x, s);\nconst PI: float = 3.14;";
+
+    assert!(errors(src).is_empty());
+  }
+
+  #[test]
+  fn reports_mismatches() {
+    let errors = errors("x: int = 1.5;\ns: str = \"a\";\ny = s * 2;\nx = a[0];\nx: str = \"b\";");
+
+    assert_eq!(errors.len(), 4);
+    assert!(errors[0].contains("assigns a float, but `x` is declared as `int`"));
+    assert!(errors[1].contains("applies `*` to a string and an integer"));
+    assert!(errors[2].contains("already declared as `int` on line 1"));
+    assert!(errors[3].contains("assigns a string, but `x` is declared as `int`"));
+  }
+}
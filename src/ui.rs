@@ -0,0 +1,229 @@
+use std::io;
+use toy_language::{
+  error::{DiagnosticError, Severity},
+  interpreter::Interpreter,
+  lexer::Lexer,
+  node::Node,
+  run::{Observer, Runner, Settings},
+  token::TokenKind,
+  LineIndex,
+};
+
+/// A diagnostic that a program is expected to report, written in its source as a comment.
+///
+/// `/*~ ERROR text */` expects an error whose message contains `text` on the line of the comment,
+/// and each `^` after the `~` moves it up a line, eg `/*~^ WARNING text */` for the line above.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+  /// The line that the diagnostic is expected on, starting at 1.
+  pub line: usize,
+  /// Whether an error or a warning is expected.
+  pub severity: Severity,
+  /// The text that the diagnostic's message has to contain.
+  pub message: String,
+}
+
+/// A difference between the diagnostics that a program reports and its [Annotation]s.
+#[derive(Clone, Debug)]
+pub enum Mismatch {
+  /// A diagnostic was reported that no annotation expects.
+  Unexpected(DiagnosticError),
+  /// An annotation expects a diagnostic that wasn't reported.
+  Missing(Annotation),
+  /// A comment starting with `/*~` on the line isn't a valid annotation.
+  Malformed(usize, String),
+}
+
+/// Returns the annotations in the source, or the comments that look like annotations but aren't.
+pub fn annotations(src: &str) -> (Vec<Annotation>, Vec<Mismatch>) {
+  let line_index = LineIndex::new(src);
+  let mut annotations = Vec::new();
+  let mut malformed = Vec::new();
+
+  for token in Lexer::new(src).lex_with_whitespace() {
+    let Some(text) = src[token.range()]
+      .strip_prefix("/*~")
+      .filter(|_| matches!(token.kind(), TokenKind::Comment))
+    else {
+      continue;
+    };
+
+    let (line, _) = line_index.line_col(token.range().start);
+    let text = text.strip_suffix("*/").unwrap_or(text);
+    let above = text.len() - text.trim_start_matches('^').len();
+    let (kind, message) = text[above..]
+      .trim()
+      .split_once(' ')
+      .unwrap_or((text[above..].trim(), ""));
+    let severity = match kind {
+      "ERROR" => Severity::Error,
+      "WARNING" => Severity::Warning,
+      _ => {
+        malformed.push(Mismatch::Malformed(line, src[token.range()].to_string()));
+        continue;
+      }
+    };
+
+    match line.checked_sub(above).filter(|&line| line > 0) {
+      Some(line) => annotations.push(Annotation {
+        line,
+        severity,
+        message: message.trim().to_string(),
+      }),
+      None => malformed.push(Mismatch::Malformed(line, src[token.range()].to_string())),
+    }
+  }
+
+  (annotations, malformed)
+}
+
+// Collects the warnings that a run reports.
+#[derive(Default)]
+struct Warnings(Vec<DiagnosticError>);
+
+impl Observer for Warnings {
+  fn warned(&mut self, _file_name: &str, _src: &str, warnings: &[DiagnosticError]) {
+    self.0.extend(warnings.iter().cloned());
+  }
+}
+
+/// Lexes, parses, and runs the file like the command line does without any options, returning
+/// every error and warning that it reports.
+///
+/// The program's output is discarded, and it has no input to read.
+pub fn diagnostics(file_name: &str, src: &str) -> Vec<DiagnosticError> {
+  let interpreter = Interpreter::new(src, Node::Program(Vec::new()))
+    .with_output(io::sink())
+    .with_input(io::empty());
+  let mut runner = Runner::new(Settings::default(), Warnings::default());
+  let result = runner.run_file(interpreter, file_name, src);
+  let mut diagnostics = std::mem::take(&mut runner.observer_mut().0);
+
+  if let Err(failure) = result {
+    diagnostics.extend(failure.errors);
+  }
+
+  diagnostics
+}
+
+/// Checks that the diagnostics of the program match its annotations exactly, returning every
+/// mismatch, ordered by line.
+///
+/// Each diagnostic has to be expected by a different annotation, on the same line and with the
+/// same severity.
+pub fn check(file_name: &str, src: &str) -> Vec<Mismatch> {
+  let (mut expected, mut mismatches) = annotations(src);
+
+  for diagnostic in diagnostics(file_name, src) {
+    let found = expected.iter().position(|annotation| {
+      annotation.line == diagnostic.line()
+        && annotation.severity == diagnostic.severity()
+        && diagnostic.to_string().contains(&annotation.message)
+    });
+
+    match found {
+      Some(i) => {
+        expected.remove(i);
+      }
+      None => mismatches.push(Mismatch::Unexpected(diagnostic)),
+    }
+  }
+
+  mismatches.extend(expected.into_iter().map(Mismatch::Missing));
+  mismatches.sort_by_key(Mismatch::line);
+  mismatches
+}
+
+impl Mismatch {
+  /// Returns the line that the mismatch is on.
+  pub fn line(&self) -> usize {
+    match self {
+      Mismatch::Unexpected(diagnostic) => diagnostic.line(),
+      Mismatch::Missing(annotation) => annotation.line,
+      Mismatch::Malformed(line, _) => *line,
+    }
+  }
+}
+
+impl std::fmt::Display for Mismatch {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Mismatch::Unexpected(diagnostic) => write!(
+        f,
+        "{}:{}: unexpected {}: {}",
+        diagnostic.line(),
+        diagnostic.column(),
+        diagnostic.severity(),
+        diagnostic
+      ),
+      Mismatch::Missing(annotation) => write!(
+        f,
+        "{}: expected {} containing `{}`, but none was reported",
+        annotation.line,
+        match annotation.severity {
+          Severity::Error => "an error",
+          Severity::Warning => "a warning",
+        },
+        annotation.message
+      ),
+      Mismatch::Malformed(line, comment) => write!(
+        f,
+        "{}: invalid annotation `{}`, expected `/*~ ERROR <text> */` or `/*~ WARNING <text> */`",
+        line, comment
+      ),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{fs, path::Path};
+
+  #[test]
+  fn sample_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("sample_files/ui");
+
+    for entry in fs::read_dir(dir).unwrap() {
+      let path = entry.unwrap().path();
+      let mismatches = check(path.to_str().unwrap(), &fs::read_to_string(&path).unwrap())
+        .iter()
+        .map(Mismatch::to_string)
+        .collect::<Vec<_>>();
+
+      assert!(
+        mismatches.is_empty(),
+        "{}: {:#?}",
+        path.display(),
+        mismatches
+      );
+    }
+  }
+
+  #[test]
+  fn matches_annotations() {
+    let src = "x = 1 / 0; /*~ ERROR divides by zero */\ny = 007;\n/*~^ WARNING leading zeros */";
+
+    assert!(
+      check("main.toy", src).is_empty(),
+      "{:?}",
+      check("main.toy", src)
+    );
+
+    let src = "x = 1 / 0;\ny = 1; /*~ ERROR overflows */\n/*~ NOTE x */";
+    let mismatches = check("main.toy", src)
+      .iter()
+      .map(Mismatch::to_string)
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      mismatches,
+      vec![
+        "1:1: unexpected error: The assignment to `x` divides by zero.",
+        "2: expected an error containing `overflows`, but none was reported",
+        "3: invalid annotation `/*~ NOTE x */`, expected `/*~ ERROR <text> */` or \
+         `/*~ WARNING <text> */`",
+      ]
+    );
+  }
+}
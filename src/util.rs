@@ -31,3 +31,35 @@ pub fn linebreak_index(src: &str, range: Range<usize>) -> usize {
     .and_then(|s| s.rfind('\n'))
     .map_or(0, |i| i + 1)
 }
+
+/// Maps byte offsets in a source string to lines and columns.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+  /// The byte offset that each line starts at.
+  line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+  /// Creates a new [LineIndex] for the source string.
+  pub fn new(src: &str) -> Self {
+    let line_starts = std::iter::once(0)
+      .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+      .collect();
+
+    Self { line_starts }
+  }
+
+  /// Returns the line and column, both starting at 1, of the byte offset.
+  pub fn line_col(&self, offset: usize) -> (usize, usize) {
+    // The index of the last line that starts at or before the offset
+    let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+
+    (line + 1, offset - self.line_starts[line] + 1)
+  }
+
+  /// Returns the line and column right after the [Token], which is where a missing token that
+  /// should follow it would go.
+  pub fn after(&self, token: &Token) -> (usize, usize) {
+    self.line_col(token.range().end)
+  }
+}
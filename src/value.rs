@@ -0,0 +1,316 @@
+use crate::node::{IdentifierNode, Node};
+use std::rc::Rc;
+
+/// A value in this language, either an integer, a float, a string, an array, a tuple, a struct,
+/// a function, or `none`.
+///
+/// Arithmetic on two integers stays an integer, but an operation with a float on either side
+/// promotes both operands to floats. Strings can only be concatenated with `+` and compared with
+/// other strings, and arrays can only be concatenated with `+` and compared with `==` and `!=`,
+/// like tuples and structs can only be compared. `none` can only be compared with `==` and `!=`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+  /// An integer, eg `12`.
+  Int(isize),
+  /// A floating-point number, eg `12.5`.
+  Float(f64),
+  /// A string, eg `"hello"`.
+  Str(Rc<str>),
+  /// An array, eg `[1, 2, 3]`.
+  Array(Rc<[Value]>),
+  /// A tuple, eg `(1, "a")`, which always has at least one element.
+  Tuple(Rc<[Value]>),
+  /// A value of a struct, eg `Point { x: 1, y: 2 }`, with the name of the struct and the value
+  /// of each of its fields, in the order that they're declared.
+  Struct(Rc<str>, Rc<[(Rc<str>, Value)]>),
+  /// A function made by a lambda, eg `|x| x * 2`, which can only be called.
+  Function(Rc<Function>),
+  /// `none`, the absence of a value, which `??` replaces with its right operand.
+  None,
+}
+
+/// A function made by evaluating a lambda, with the values of the variables of the blocks and
+/// `let`s that it was made in.
+#[derive(Debug)]
+pub struct Function {
+  /// The parameters, which are bound to the arguments of a call.
+  pub params: Vec<IdentifierNode>,
+  /// The expression that a call evaluates.
+  pub body: Node,
+  /// The variables that were visible when it was made, other than the program's own, which are
+  /// read when it's called instead.
+  pub captured: Vec<(String, Value)>,
+}
+
+// Functions are only equal to themselves, since their bodies can't be compared
+impl PartialEq for Function {
+  fn eq(&self, other: &Self) -> bool {
+    std::ptr::eq(self, other)
+  }
+}
+
+impl Value {
+  /// Returns the value as a float, converting it if it's an integer.
+  ///
+  /// Strings, arrays, tuples, structs, functions, and `none` aren't numbers, so they're `NaN`.
+  pub fn as_float(&self) -> f64 {
+    match self {
+      Value::Int(value) => *value as f64,
+      Value::Float(value) => *value,
+      Value::Str(_)
+      | Value::Array(_)
+      | Value::Tuple(_)
+      | Value::Struct(..)
+      | Value::Function(_)
+      | Value::None => f64::NAN,
+    }
+  }
+
+  /// Returns whether the value is below zero.
+  pub fn is_negative(&self) -> bool {
+    match self {
+      Value::Int(value) => *value < 0,
+      Value::Float(value) => *value < 0.0,
+      Value::Str(_)
+      | Value::Array(_)
+      | Value::Tuple(_)
+      | Value::Struct(..)
+      | Value::Function(_)
+      | Value::None => false,
+    }
+  }
+
+  /// Returns whether the value is zero, an empty string or array, or `none`, which conditions
+  /// treat as false.
+  pub fn is_zero(&self) -> bool {
+    match self {
+      Value::Int(value) => *value == 0,
+      Value::Float(value) => *value == 0.0,
+      Value::Str(value) => value.is_empty(),
+      Value::Array(values) | Value::Tuple(values) => values.is_empty(),
+      // Structs always have at least one field, and functions are never empty
+      Value::Struct(..) | Value::Function(_) => false,
+      Value::None => true,
+    }
+  }
+
+  /// Returns whether the value is `none`.
+  pub fn is_none(&self) -> bool {
+    matches!(self, Value::None)
+  }
+
+  /// Returns whether the value is a string.
+  pub fn is_str(&self) -> bool {
+    matches!(self, Value::Str(_))
+  }
+
+  /// Returns whether the value is an integer or a float.
+  pub fn is_number(&self) -> bool {
+    matches!(self, Value::Int(_) | Value::Float(_))
+  }
+
+  /// Returns the element of an array at the index, or `None` if this isn't an array, or the index
+  /// isn't an integer within its bounds.
+  pub fn element(&self, index: &Value) -> Option<&Value> {
+    match (self, index) {
+      (Value::Array(values), Value::Int(index)) => values.get(usize::try_from(*index).ok()?),
+      _ => None,
+    }
+  }
+
+  /// Returns the field of a tuple at the index, eg `0` for `t.0`, or `None` if this isn't a tuple
+  /// or it doesn't have that many fields.
+  pub fn field(&self, index: usize) -> Option<&Value> {
+    match self {
+      Value::Tuple(values) => values.get(index),
+      _ => None,
+    }
+  }
+
+  /// Returns the field of a struct with the name, eg `x` for `p.x`, or `None` if this isn't a
+  /// struct or it doesn't have that field.
+  pub fn named_field(&self, name: &str) -> Option<&Value> {
+    match self {
+      Value::Struct(_, fields) => fields
+        .iter()
+        .find(|(field, _)| &**field == name)
+        .map(|(_, value)| value),
+      _ => None,
+    }
+  }
+
+  /// Returns the value as it's printed or spliced into a string, which is a string's characters
+  /// without quotes or escapes, or any other value as it's written.
+  pub fn text(&self) -> String {
+    match self {
+      Value::Str(value) => value.to_string(),
+      value => value.to_string(),
+    }
+  }
+
+  /// Returns the kind of the value with an article, for diagnostics, eg `a string`.
+  pub fn kind(&self) -> &'static str {
+    match self {
+      Value::Int(_) => "an integer",
+      Value::Float(_) => "a float",
+      Value::Str(_) => "a string",
+      Value::Array(_) => "an array",
+      Value::Tuple(_) => "a tuple",
+      Value::Struct(..) => "a struct",
+      Value::Function(_) => "a function",
+      Value::None => "none",
+    }
+  }
+}
+
+impl From<isize> for Value {
+  fn from(value: isize) -> Self {
+    Value::Int(value)
+  }
+}
+
+impl From<&str> for Value {
+  fn from(value: &str) -> Self {
+    Value::Str(value.into())
+  }
+}
+
+/// Returns which operators apply to the operands, for diagnostics about an operator that doesn't
+/// apply to them, eg that strings can only be concatenated with `+` and compared.
+pub fn operator_rule(lhs: &Value, rhs: Option<&Value>) -> &'static str {
+  match (lhs, rhs) {
+    (Value::Function(_), _) | (_, Some(Value::Function(_))) => {
+      "functions can't be operated on, only called"
+    }
+    (Value::None, _) | (_, Some(Value::None)) => "none can only be compared with `==` and `!=`",
+    (Value::Struct(..), _) | (_, Some(Value::Struct(..))) => {
+      "structs can only be compared with `==` and `!=` to other structs"
+    }
+    (Value::Tuple(_), _) | (_, Some(Value::Tuple(_))) => {
+      "tuples can only be compared with `==` and `!=` to other tuples"
+    }
+    (Value::Array(_), _) | (_, Some(Value::Array(_))) => {
+      "arrays can only be concatenated with `+` and compared with `==` and `!=` to other arrays"
+    }
+    _ => "strings can only be concatenated with `+` and compared with other strings",
+  }
+}
+
+/// Escapes the characters of a string that can't be written as is between its quotes, so that
+/// it lexes back to the same characters, including a `$` that would start an interpolation.
+pub fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  let mut chars = text.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      '\n' => escaped.push_str("\\n"),
+      '\r' => escaped.push_str("\\r"),
+      '\t' => escaped.push_str("\\t"),
+      '$' if chars.peek() == Some(&'{') => escaped.push_str("\\$"),
+      c => escaped.push(c),
+    }
+  }
+
+  escaped
+}
+
+/// Formats the integer with `_` between every group of three digits, eg `-1_234_567`.
+pub fn group_digits(value: isize) -> String {
+  let digits = value.unsigned_abs().to_string();
+  let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+
+  if value < 0 {
+    grouped.push('-');
+  }
+
+  for (i, digit) in digits.chars().enumerate() {
+    if i != 0 && (digits.len() - i).is_multiple_of(3) {
+      grouped.push('_');
+    }
+
+    grouped.push(digit);
+  }
+
+  grouped
+}
+
+/// Formats the value for output, grouping the digits of integers if `group` is set.
+pub fn format_value(value: Value, group: bool) -> String {
+  match value {
+    Value::Int(value) if group => group_digits(value),
+    _ => value.to_string(),
+  }
+}
+
+/// Formats the value so that it lexes back to the same value, so whole floats keep their `.0`
+/// and strings are quoted, with their characters escaped by [escape].
+///
+/// Arrays are written as their elements between `[` and `]`, eg `[1, "a"]`, and tuples between
+/// `(` and `)`, with a trailing `,` if they only have one, eg `(1,)`. Functions are written as
+/// the lambda that made them.
+impl std::fmt::Display for Value {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Value::Int(value) => write!(f, "{}", value),
+      Value::Float(value) if value.fract() == 0.0 => write!(f, "{:.1}", value),
+      Value::Float(value) => write!(f, "{}", value),
+      Value::Str(value) => write!(f, "\"{}\"", escape(value)),
+      Value::Array(values) => {
+        write!(f, "[")?;
+
+        for (i, value) in values.iter().enumerate() {
+          if i != 0 {
+            write!(f, ", ")?;
+          }
+
+          write!(f, "{}", value)?;
+        }
+
+        write!(f, "]")
+      }
+      Value::Tuple(values) => {
+        write!(f, "(")?;
+
+        for (i, value) in values.iter().enumerate() {
+          if i != 0 {
+            write!(f, ", ")?;
+          }
+
+          write!(f, "{}", value)?;
+        }
+
+        // A single element would otherwise be read back as a parenthesized expression
+        match values.len() {
+          1 => write!(f, ",)"),
+          _ => write!(f, ")"),
+        }
+      }
+      Value::Struct(name, fields) => {
+        write!(f, "{} {{ ", name)?;
+
+        for (i, (field, value)) in fields.iter().enumerate() {
+          if i != 0 {
+            write!(f, ", ")?;
+          }
+
+          write!(f, "{}: {}", field, value)?;
+        }
+
+        write!(f, " }}")
+      }
+      Value::Function(function) => {
+        let params = function
+          .params
+          .iter()
+          .map(|param| param.literal.as_str())
+          .collect::<Vec<_>>();
+
+        write!(f, "|{}| {}", params.join(", "), function.body)
+      }
+      Value::None => write!(f, "none"),
+    }
+  }
+}
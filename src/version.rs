@@ -0,0 +1,86 @@
+/// A version of the language, which decides the syntax that programs can use.
+///
+/// Older versions keep old programs lexing exactly as they used to, eg so that `print` can still
+/// be the name of a variable.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LangVersion {
+  /// The original language, with only `+`, `-`, `*`, integers, and variables.
+  V1,
+  /// The current language.
+  #[default]
+  V2,
+}
+
+/// The syntax that's available in a [LangVersion].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Features {
+  /// Block comments, eg `/* ... */`.
+  pub block_comments: bool,
+  /// The keywords, eg `let` and `print`, rather than them being identifiers.
+  pub keywords: bool,
+  /// The `/`, `%`, `**`, shift, comparison, and logical operators, along with `{` and `}`.
+  ///
+  /// The operators are still lexed without this, so that the parser can report them.
+  pub operators: bool,
+  /// Float literals, eg `12.5`.
+  pub floats: bool,
+  /// Hexadecimal, octal, and binary integer literals, eg `0x1F`, `0o17`, and `0b1010`.
+  pub radix_literals: bool,
+  /// String literals, eg `"hello"`.
+  pub strings: bool,
+  /// Character literals, eg `'a'`.
+  pub chars: bool,
+  /// Array literals and indexing, eg `[1, 2][0]`.
+  pub arrays: bool,
+  /// Tuples and their fields, eg `(1, 2).0`.
+  pub tuples: bool,
+  /// Type annotations on assignments, eg `x: int = 5;`.
+  pub type_annotations: bool,
+  /// Struct declarations, values, and fields, eg `Point { x: 1, y: 2 }.x`.
+  pub structs: bool,
+  /// Running other files where they're imported, eg `import "other.toy";`.
+  pub imports: bool,
+  /// Calls of the built-in functions, eg `max(a, b)`.
+  pub calls: bool,
+  /// Lambdas, eg `|x| x * 2`, and calls of the variables that they're assigned to.
+  pub lambdas: bool,
+}
+
+impl LangVersion {
+  /// Parses a version number, eg `2`.
+  pub fn parse(src: &str) -> Option<Self> {
+    match src.trim() {
+      "1" => Some(LangVersion::V1),
+      "2" => Some(LangVersion::V2),
+      _ => None,
+    }
+  }
+
+  /// Returns the syntax that's available in this version.
+  pub fn features(self) -> Features {
+    let latest = matches!(self, LangVersion::V2);
+
+    Features {
+      block_comments: latest,
+      keywords: latest,
+      operators: latest,
+      floats: latest,
+      radix_literals: latest,
+      strings: latest,
+      chars: latest,
+      arrays: latest,
+      tuples: latest,
+      type_annotations: latest,
+      structs: latest,
+      imports: latest,
+      calls: latest,
+      lambdas: latest,
+    }
+  }
+}
+
+impl Default for Features {
+  fn default() -> Self {
+    LangVersion::default().features()
+  }
+}
@@ -0,0 +1,54 @@
+//! Runs programs that need capabilities, checking that they're only granted with `--allow`.
+
+use std::{
+  env, fs,
+  io::Write,
+  process::{Command, Output, Stdio},
+};
+
+// Runs the source with the arguments, giving it the input.
+fn run(src: &str, args: &[&str], input: &str) -> Output {
+  let path = env::temp_dir().join(format!(
+    "toy_language-capabilities-{}.toy",
+    std::process::id()
+  ));
+  fs::write(&path, src).unwrap();
+
+  let mut child = Command::new(env!("CARGO_BIN_EXE_toy_language"))
+    .args(args)
+    .arg(&path)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+
+  // A program that's denied the input may have exited before it's written
+  let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+
+  let output = child.wait_with_output().unwrap();
+  fs::remove_file(&path).unwrap();
+
+  output
+}
+
+#[test]
+fn read_needs_to_be_allowed() {
+  let src = "read x;\ny = x * 2;";
+
+  let denied = run(src, &[], "21\n");
+  let stderr = String::from_utf8_lossy(&denied.stderr);
+
+  assert!(!denied.status.success());
+  assert!(
+    stderr.contains("The capability `read` is not granted"),
+    "{}",
+    stderr
+  );
+
+  let allowed = run(src, &["--allow=read"], "21\n");
+  let stdout = String::from_utf8_lossy(&allowed.stdout);
+
+  assert!(allowed.status.success(), "{}", stdout);
+  assert!(stdout.contains("y => 42"), "{}", stdout);
+}
@@ -0,0 +1,54 @@
+//! Runs each of the sample files, checking that what it prints matches its output in
+//! `sample_files/output`, which `scripts/run.sh` regenerates.
+
+use std::{
+  env,
+  fs::{self, File},
+  path::Path,
+  process::{Command, Stdio},
+};
+
+#[test]
+fn sample_files_match_their_outputs() {
+  let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let outputs = env::temp_dir().join(format!("toy_language-samples-{}", std::process::id()));
+  let mut mismatched = Vec::new();
+
+  fs::create_dir_all(&outputs).unwrap();
+
+  for entry in fs::read_dir(root.join("sample_files")).unwrap() {
+    let path = entry.unwrap().path();
+
+    if !path.is_file() {
+      continue;
+    }
+
+    let file_name = path.file_name().unwrap();
+    let output_path = outputs.join(file_name);
+    let output = File::create(&output_path).unwrap();
+
+    // Both streams are written to the same file, like the script redirects them
+    Command::new(env!("CARGO_BIN_EXE_toy_language"))
+      .arg(Path::new("sample_files").join(file_name))
+      .current_dir(root)
+      .stdin(Stdio::null())
+      .stdout(output.try_clone().unwrap())
+      .stderr(output)
+      .status()
+      .unwrap();
+
+    let expected = fs::read_to_string(root.join("sample_files/output").join(file_name)).unwrap();
+
+    if fs::read_to_string(&output_path).unwrap() != expected {
+      mismatched.push(file_name.to_string_lossy().into_owned());
+    }
+  }
+
+  fs::remove_dir_all(&outputs).unwrap();
+
+  assert!(
+    mismatched.is_empty(),
+    "the outputs of {:?} changed. run `scripts/run.sh` to update them.",
+    mismatched
+  );
+}